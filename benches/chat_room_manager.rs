@@ -0,0 +1,62 @@
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use socket_io_rust::socket_io_adaptor::chat_room_manager::{BroadcastOptions, ChatRoomManager};
+
+const ROOMS: usize = 100;
+
+/// A manager with `sockets` sockets, spread over `ROOMS` rooms
+fn manager(sockets: usize) -> ChatRoomManager {
+    let mut manager = ChatRoomManager::new();
+    for i in 0..sockets {
+        manager.add(&format!("sid{}", i), &format!("room{}", i % ROOMS));
+    }
+    manager
+}
+
+fn rooms(n: usize) -> Vec<String> {
+    (0..n).map(|i| format!("room{}", i)).collect()
+}
+
+fn opts(rooms: Vec<String>, except: Vec<String>) -> BroadcastOptions {
+    BroadcastOptions {
+        rooms: rooms.into_iter().collect(),
+        except: except.into_iter().collect(),
+        ..Default::default()
+    }
+}
+
+fn fan_out(c: &mut Criterion) {
+    let mut group = c.benchmark_group("room_fan_out");
+    for &sockets in [1_000usize, 10_000, 100_000].iter() {
+        let manager = manager(sockets);
+        let cases = vec![
+            ("all", opts(Vec::new(), Vec::new())),
+            ("one_room", opts(rooms(1), Vec::new())),
+            ("ten_rooms", opts(rooms(10), Vec::new())),
+            ("all_except_one_room", opts(Vec::new(), rooms(1))),
+        ];
+        for (name, opts) in cases {
+            let matching = manager.get_matching_sids(&opts).len();
+            group.throughput(Throughput::Elements(matching as u64));
+            group.bench_with_input(BenchmarkId::new(name, sockets), &opts, |b, opts| {
+                b.iter(|| manager.get_matching_sids(black_box(opts)))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn membership(c: &mut Criterion) {
+    let mut manager = manager(10_000);
+    // as in a namespace, which dispatches the events of each change
+    manager.enable_events();
+    c.bench_function("room_join_leave", |b| {
+        b.iter(|| {
+            manager.add_all("sid", vec!["room0", "room1", "private"]);
+            manager.delete_all(black_box("sid"));
+            manager.take_events()
+        })
+    });
+}
+
+criterion_group!(benches, fan_out, membership);
+criterion_main!(benches);
@@ -0,0 +1,58 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+use socket_io_rust::engine_io_parser::EngineIoParser;
+
+const MESSAGE: &str = r#"2["chat message",{"user":"John Doe","text":"hello, world"}]"#;
+
+fn messages(n: usize) -> Vec<Packet> {
+    (0..n)
+        .map(|_| Packet::new(PacketType::Message, MESSAGE.as_bytes().to_vec()))
+        .collect()
+}
+
+fn packet(c: &mut Criterion) {
+    let encoded = format!("4{}", MESSAGE);
+    let mut group = c.benchmark_group("engine_io_packet");
+    group.throughput(Throughput::Bytes(encoded.len() as u64));
+    group.bench_function("encode", |b| {
+        b.iter(|| {
+            let packet = Packet::new(PacketType::Message, MESSAGE.as_bytes().to_vec());
+            EngineIOParserImpl::encode_packet_str(black_box(packet)).unwrap()
+        })
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| EngineIOParserImpl::decode_packet_str(black_box(&encoded)).unwrap())
+    });
+    group.bench_function("encode_binary", |b| {
+        b.iter(|| EngineIOParserImpl::encode_packet_str(Packet::binary(black_box(vec![7; 1024]))))
+    });
+    group.finish();
+}
+
+fn payload(c: &mut Criterion) {
+    let mut group = c.benchmark_group("engine_io_payload");
+    for &n in [1usize, 16, 256].iter() {
+        let encoded = EngineIOParserImpl::encode_payload(messages(n)).unwrap();
+        let encoded_v3 = EngineIOParserImpl::encode_payload_v3(messages(n)).unwrap();
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_function(format!("encode/{}", n), |b| {
+            b.iter(|| EngineIOParserImpl::encode_payload(black_box(messages(n))).unwrap())
+        });
+        group.bench_function(format!("decode/{}", n), |b| {
+            b.iter(|| EngineIOParserImpl::decode_payload(black_box(&encoded)).unwrap())
+        });
+        group.bench_function(format!("encode_v3/{}", n), |b| {
+            b.iter(|| EngineIOParserImpl::encode_payload_v3(black_box(messages(n))).unwrap())
+        });
+        group.bench_function(format!("decode_v3/{}", n), |b| {
+            b.iter(|| {
+                EngineIOParserImpl::decode_payload_v3(black_box(&encoded_v3), usize::MAX).unwrap()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, packet, payload);
+criterion_main!(benches);
@@ -0,0 +1,49 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use serde_json::json;
+use socket_io_rust::socket_io_packet_codec::attachments::placeholder;
+use socket_io_rust::socket_io_packet_codec::default_packet_codec::DefaultPacketCodec;
+use socket_io_rust::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use socket_io_rust::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use socket_io_rust::socket_io_packet_codec::SocketIoPacketCodec;
+
+fn event() -> SocketIoPacket {
+    SocketIoPacket {
+        packet_type: SocketIoPacketType::Event,
+        nsp: "/chat".to_string(),
+        id: Some(42),
+        data: Some(json!([
+            "chat message",
+            { "user": "John Doe", "text": "hello, world", "tags": ["a", "b", "c"] }
+        ])),
+    }
+}
+
+fn binary_event() -> SocketIoPacket {
+    SocketIoPacket {
+        packet_type: SocketIoPacketType::BinaryEvent,
+        nsp: "/".to_string(),
+        id: None,
+        data: Some(json!(["upload", placeholder(0), { "thumb": placeholder(1) }])),
+    }
+}
+
+fn codec(c: &mut Criterion) {
+    let mut group = c.benchmark_group("socket_io_packet");
+    for (name, packet) in [("event", event()), ("binary_event", binary_event())].iter() {
+        let encoded = DefaultPacketCodec.encode_packet_to_string(packet).unwrap();
+        group.bench_function(format!("encode/{}", name), |b| {
+            b.iter(|| {
+                DefaultPacketCodec
+                    .encode_packet_to_string(black_box(packet))
+                    .unwrap()
+            })
+        });
+        group.bench_function(format!("decode/{}", name), |b| {
+            b.iter(|| DefaultPacketCodec.decode(black_box(&encoded)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, codec);
+criterion_main!(benches);
@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use socket_io_rust::engine_io_parser::EngineIoParser;
+
+fuzz_target!(|data: &[u8]| {
+    // binary websocket frames
+    let _ = EngineIOParserImpl::decode_packet(data);
+    // text websocket frames
+    if let Ok(data) = std::str::from_utf8(data) {
+        let _ = EngineIOParserImpl::decode_packet_str(data);
+    }
+});
@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use socket_io_rust::engine_io_parser::EngineIoParser;
+
+// the body of a polling request, in both protocol versions
+fuzz_target!(|data: &str| {
+    let _ = EngineIOParserImpl::decode_payload_limited(data, 16);
+    let _ = EngineIOParserImpl::decode_payload_v3(data, 16);
+});
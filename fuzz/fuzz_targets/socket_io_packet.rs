@@ -0,0 +1,11 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use socket_io_rust::socket_io_packet_codec::default_packet_codec::DefaultPacketCodec;
+use socket_io_rust::socket_io_packet_codec::SocketIoPacketCodec;
+
+fuzz_target!(|data: &str| {
+    if let Ok(packet) = DefaultPacketCodec.decode(data) {
+        // what was decoded can be sent back
+        DefaultPacketCodec.encode_packet_to_string(&packet).unwrap();
+    }
+});
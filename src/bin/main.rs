@@ -1,5 +1,13 @@
+use actix_rt::signal::unix::{signal, SignalKind};
 use actix_web::{App, HttpServer};
+use socket_io_rust::socket_io::server_options::ServerOptions;
 use socket_io_rust::socket_io::socket_io_server::SocketIoServer;
+use socket_io_rust::socket_io_adaptor::chat_room_manager::BroadcastOptions;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+/// How long the sockets have to be told the server is going away on SIGTERM
+const SHUTDOWN_DEADLINE: Duration = Duration::from_secs(10);
 
 #[allow(clippy::let_and_return)]
 // /// do websocket handshake and start `MyWebSocket` actor
@@ -12,7 +20,13 @@ use socket_io_rust::socket_io::socket_io_server::SocketIoServer;
 // }
 #[actix_rt::main]
 async fn main() -> std::io::Result<()> {
-    std::env::set_var("RUST_LOG", "actix_server=info,actix_web=info");
+    // RUST_LOG=debug follows each session, socket and event;
+    // `socket_io_rust::packets=off` leaves out the raw packets
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+        )
+        .init();
 
     // let mut nsm = NamespaceManager::new();
     // nsm.create_namespace("/").unwrap();
@@ -27,12 +41,42 @@ async fn main() -> std::io::Result<()> {
     //         .service(web::resource("/ws").route(web::get().to(ws_index)))
     // });
 
-    let app_data = SocketIoServer::get_app_data();
-    HttpServer::new(move || {
-        App::new().service(SocketIoServer::get_socket_io_scope("/ws", app_data.clone()))
+    // Serves Engine.IO at /socket.io (it used to be a bare websocket at /ws),
+    // the clients must send the EIO and transport query parameters
+    let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+    // a chat: each event is acknowledged with its arguments, then relayed to
+    // every socket of the namespace
+    for namespace in app_data
+        .namespace_manager
+        .write()
+        .unwrap()
+        .get_adaptors_mut()
+    {
+        namespace.on_event(|namespace, _, event_name, payload, ack| {
+            if let Some(ack) = ack {
+                ack.send(payload.clone());
+            }
+            namespace.emit_payload_with_options(&BroadcastOptions::default(), event_name, payload);
+        });
+    }
+    let data = app_data.clone();
+    let server = HttpServer::new(move || {
+        App::new()
+            .service(SocketIoServer::get_scope(data.clone()))
+            .service(SocketIoServer::get_healthz_resource(data.clone()))
+            .service(SocketIoServer::get_readyz_resource(data.clone()))
     })
+    // SIGTERM drains the sockets first, see below
+    .disable_signals()
     // start http server on 127.0.0.1:8080
     .bind("127.0.0.1:8080")?
-    .run()
-    .await
+    .run();
+
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let handle = server.clone();
+    actix_rt::spawn(async move {
+        sigterm.recv().await;
+        SocketIoServer::shutdown(app_data, handle, SHUTDOWN_DEADLINE).await;
+    });
+    server.await
 }
@@ -0,0 +1,236 @@
+//! Load generator: starts a server on a local port, connects many clients to
+//! it over websocket, spreads their sockets over rooms, then broadcasts to the
+//! rooms from the server and reports the latency of the deliveries and the
+//! throughput.
+//!
+//! ```text
+//! socketio-bench [--clients N] [--rooms N] [--messages N] [--interval MS] [--concurrency N]
+//! ```
+
+use actix_rt::time::{delay_for, timeout};
+use actix_web::{App, HttpServer};
+use futures::channel::mpsc;
+use futures::future::join_all;
+use futures::StreamExt;
+use serde_json::Value;
+use socket_io_rust::engine_io_server::transport::TransportType;
+use socket_io_rust::socket_io::server_options::ServerOptions;
+use socket_io_rust::socket_io::socket_io_server::SocketIoServer;
+use socket_io_rust::socket_io_adaptor::chat_room_manager::BroadcastOptions;
+use socket_io_rust::socket_io_client::client::Client;
+use socket_io_rust::socket_io_client::client_options::ClientOptions;
+use socket_io_rust::socket_io_client::error::ClientError;
+use socket_io_rust::socket_io_packet_codec::payload::Payload;
+use std::time::{Duration, Instant};
+
+const USAGE: &str = "\
+usage: socketio-bench [options]
+
+    --clients N      connections to open (default 1000)
+    --rooms N        rooms the sockets are spread over (default 10)
+    --messages N     broadcasts to send, to each room in turn (default 100)
+    --interval MS    delay between two broadcasts (default 10)
+    --concurrency N  connections opened at the same time (default 100)";
+
+/// How long to wait for the deliveries after the last broadcast
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug)]
+struct Args {
+    clients: usize,
+    rooms: usize,
+    messages: usize,
+    interval: Duration,
+    concurrency: usize,
+}
+
+impl Args {
+    fn parse() -> Result<Self, String> {
+        let mut args = Args {
+            clients: 1000,
+            rooms: 10,
+            messages: 100,
+            interval: Duration::from_millis(10),
+            concurrency: 100,
+        };
+        let mut iter = std::env::args().skip(1);
+        while let Some(flag) = iter.next() {
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("missing value of {}", flag))?;
+            let n: usize = value
+                .parse()
+                .map_err(|_| format!("invalid value of {}: {}", flag, value))?;
+            match flag.as_str() {
+                "--clients" => args.clients = n,
+                "--rooms" => args.rooms = n,
+                "--messages" => args.messages = n,
+                "--interval" => args.interval = Duration::from_millis(n as u64),
+                "--concurrency" => args.concurrency = n,
+                _ => return Err(format!("unknown option {}", flag)),
+            }
+        }
+        if args.clients == 0 || args.rooms == 0 || args.concurrency == 0 {
+            return Err("--clients, --rooms and --concurrency must not be 0".to_string());
+        }
+        Ok(args)
+    }
+}
+
+/// Connects a client to the root namespace. Each `bench` event it receives
+/// carries when it was broadcast, in microseconds since `epoch`, and its
+/// latency is sent to `latencies`.
+async fn connect(
+    url: &str,
+    epoch: Instant,
+    latencies: mpsc::UnboundedSender<Duration>,
+) -> Result<Client, ClientError> {
+    let options = ClientOptions::builder()
+        .transports(vec![TransportType::Websocket])
+        .reconnection(false)
+        .build();
+    let client = Client::connect(url, options).await?;
+    let socket = client.socket("/");
+    socket.on("bench", move |payload, _| {
+        let sent = match &payload {
+            Payload::Text(args) => args.first().and_then(Value::as_str),
+            Payload::Binary(_) => None,
+        };
+        if let Some(sent) = sent.and_then(|sent| sent.parse().ok()) {
+            let latency = epoch.elapsed() - Duration::from_micros(sent);
+            let _ = latencies.unbounded_send(latency);
+        }
+    });
+    socket.connect(None).await?;
+    Ok(client)
+}
+
+/// Get the value below which the given fraction of the sorted values are
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::default();
+    }
+    sorted[((sorted.len() - 1) as f64 * p).round() as usize]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[actix_rt::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    if std::env::args().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+    let server_data = app_data.clone();
+    let server =
+        HttpServer::new(move || App::new().service(SocketIoServer::get_scope(server_data.clone())))
+            .disable_signals()
+            .bind("127.0.0.1:0")?;
+    let url = format!("http://{}", server.addrs()[0]);
+    let server = server.run();
+
+    // connect the clients, `concurrency` at a time
+    let epoch = Instant::now();
+    let (latencies, mut received) = mpsc::unbounded();
+    let mut clients = Vec::with_capacity(args.clients);
+    while clients.len() < args.clients {
+        let batch = args.concurrency.min(args.clients - clients.len());
+        let connecting = (0..batch).map(|_| connect(&url, epoch, latencies.clone()));
+        for client in join_all(connecting).await {
+            clients.push(client?);
+        }
+    }
+    let connect_time = epoch.elapsed();
+    println!(
+        "clients: {} connected in {:.2}s ({:.0}/s)",
+        clients.len(),
+        connect_time.as_secs_f64(),
+        clients.len() as f64 / connect_time.as_secs_f64()
+    );
+
+    // spread the sockets over the rooms
+    let rooms: Vec<String> = (0..args.rooms).map(|i| format!("room{}", i)).collect();
+    let mut room_sizes = vec![0; args.rooms];
+    {
+        let mut namespace_manager = app_data.namespace_manager.write().unwrap();
+        let namespace = namespace_manager.get_adaptor_mut("/").unwrap();
+        let mut sids = namespace.get_matching_sids(&BroadcastOptions::default());
+        sids.sort();
+        for (i, sid) in sids.iter().enumerate() {
+            namespace.join_room(sid, &rooms[i % args.rooms]);
+            room_sizes[i % args.rooms] += 1;
+        }
+    }
+    println!(
+        "rooms: {}, of {} to {} sockets",
+        args.rooms,
+        room_sizes.iter().min().unwrap(),
+        room_sizes.iter().max().unwrap()
+    );
+
+    // broadcast to each room in turn
+    let started = Instant::now();
+    let mut expected = 0;
+    for i in 0..args.messages {
+        let sent = epoch.elapsed().as_micros().to_string();
+        {
+            let namespace_manager = app_data.namespace_manager.read().unwrap();
+            let namespace = namespace_manager.get_adaptor("/").unwrap();
+            namespace.emit_to(&rooms[i % args.rooms], "bench", &sent);
+        }
+        expected += room_sizes[i % args.rooms];
+        delay_for(args.interval).await;
+    }
+
+    let deadline = Instant::now() + DRAIN_TIMEOUT;
+    let mut samples = Vec::with_capacity(expected);
+    let mut finished = started;
+    while samples.len() < expected {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        match timeout(remaining, received.next()).await {
+            Ok(Some(latency)) => {
+                samples.push(latency);
+                finished = Instant::now();
+            }
+            _ => break,
+        }
+    }
+    samples.sort();
+
+    let elapsed = finished - started;
+    println!(
+        "broadcasts: {}, deliveries: {}/{}",
+        args.messages,
+        samples.len(),
+        expected
+    );
+    println!(
+        "latency: p50 {:.2}ms, p90 {:.2}ms, p99 {:.2}ms, max {:.2}ms",
+        millis(percentile(&samples, 0.5)),
+        millis(percentile(&samples, 0.9)),
+        millis(percentile(&samples, 0.99)),
+        millis(samples.last().copied().unwrap_or_default())
+    );
+    println!(
+        "throughput: {:.0} deliveries/s over {:.2}s",
+        samples.len() as f64 / elapsed.as_secs_f64(),
+        elapsed.as_secs_f64()
+    );
+
+    for client in clients.iter() {
+        client.close();
+    }
+    server.stop(false).await;
+    Ok(())
+}
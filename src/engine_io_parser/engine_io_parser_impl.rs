@@ -1,6 +1,6 @@
 use crate::engine_io_parser::errors::ParserError;
 use crate::engine_io_parser::packet::{Packet, PacketType};
-use crate::engine_io_parser::EngineIoParser;
+use crate::engine_io_parser::{EngineIoParser, RECORD_SEPARATOR};
 use std::convert::TryFrom;
 
 /// Implementation of the [`EngineIoParser`] trait
@@ -32,11 +32,120 @@ impl EngineIoParser for EngineIOParserImpl {
         }
         Ok(Packet::new(packet_type, packet_data))
     }
+
+    fn encode_packet_str(packet: Packet) -> Result<String, ParserError> {
+        if packet.binary {
+            return Ok(format!("b{}", base64::encode(&packet.data)));
+        }
+        let data = String::from_utf8(packet.data).map_err(|_| ParserError::InvalidUtf8)?;
+        let packet_type: u8 = packet.packet_type.into();
+        Ok(format!("{}{}", packet_type, data))
+    }
+
+    fn decode_packet_str(data: &str) -> Result<Packet, ParserError> {
+        if let Some(binary) = data.strip_prefix('b') {
+            let data = base64::decode(binary).map_err(|_| ParserError::InvalidBase64)?;
+            return Ok(Packet::binary(data));
+        }
+        let first = data.chars().next().ok_or(ParserError::EmptyPacket)?;
+        let packet_type = first
+            .to_digit(10)
+            .ok_or(ParserError::InvalidPacketType(first as u8))?;
+        let packet_type = PacketType::try_from(packet_type as u8)?;
+        Ok(Packet::new(packet_type, data.as_bytes()[1..].to_vec()))
+    }
+
+    fn encode_payload(packets: Vec<Packet>) -> Result<String, ParserError> {
+        let encoded = packets
+            .into_iter()
+            .map(EngineIOParserImpl::encode_packet_str)
+            .collect::<Result<Vec<String>, ParserError>>()?;
+        Ok(encoded.join(&RECORD_SEPARATOR.to_string()))
+    }
+
+    fn decode_payload(data: &str) -> Result<Vec<Packet>, ParserError> {
+        EngineIOParserImpl::decode_payload_limited(data, usize::MAX)
+    }
+
+    fn decode_payload_limited(data: &str, max_packets: usize) -> Result<Vec<Packet>, ParserError> {
+        let mut packets = Vec::new();
+        for encoded in data.split(RECORD_SEPARATOR) {
+            if packets.len() == max_packets {
+                return Err(ParserError::TooManyPackets(max_packets));
+            }
+            packets.push(EngineIOParserImpl::decode_packet_str(encoded)?);
+        }
+        Ok(packets)
+    }
+
+    fn encode_payload_v3(packets: Vec<Packet>) -> Result<String, ParserError> {
+        let mut payload = String::new();
+        for packet in packets {
+            let encoded = if packet.binary {
+                let packet_type: u8 = packet.packet_type.into();
+                format!("b{}{}", packet_type, base64::encode(&packet.data))
+            } else {
+                EngineIOParserImpl::encode_packet_str(packet)?
+            };
+            // lengths are counted in UTF-16 code units, like javascript does
+            payload.push_str(&encoded.encode_utf16().count().to_string());
+            payload.push(':');
+            payload.push_str(&encoded);
+        }
+        Ok(payload)
+    }
+
+    fn decode_payload_v3(data: &str, max_packets: usize) -> Result<Vec<Packet>, ParserError> {
+        let mut packets = Vec::new();
+        let mut rest = data;
+        while !rest.is_empty() {
+            if packets.len() == max_packets {
+                return Err(ParserError::TooManyPackets(max_packets));
+            }
+            let colon = rest.find(':').ok_or(ParserError::InvalidLength)?;
+            let length: usize = rest[..colon]
+                .parse()
+                .map_err(|_| ParserError::InvalidLength)?;
+            rest = &rest[colon + 1..];
+
+            let mut end = 0;
+            let mut units = 0;
+            for c in rest.chars() {
+                if units == length {
+                    break;
+                }
+                units += c.len_utf16();
+                end += c.len_utf8();
+            }
+            if units != length || length == 0 {
+                return Err(ParserError::InvalidLength);
+            }
+            let encoded = &rest[..end];
+            rest = &rest[end..];
+
+            let packet = match encoded.strip_prefix('b') {
+                Some(binary) => decode_base64_packet(binary)?,
+                None => EngineIOParserImpl::decode_packet_str(encoded)?,
+            };
+            packets.push(packet);
+        }
+        Ok(packets)
+    }
+}
+
+/// Decodes a binary packet sent as text: <packet type id><base64 data>
+fn decode_base64_packet(data: &str) -> Result<Packet, ParserError> {
+    let packet = EngineIOParserImpl::decode_packet_str(data)?;
+    let data = base64::decode(&packet.data).map_err(|_| ParserError::InvalidBase64)?;
+    let mut packet = Packet::new(packet.packet_type, data);
+    packet.binary = true;
+    Ok(packet)
 }
 
 #[cfg(test)]
 mod tests {
     use crate::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    use crate::engine_io_parser::errors::ParserError;
     use crate::engine_io_parser::packet::{Packet, PacketType};
     use crate::engine_io_parser::EngineIoParser;
 
@@ -56,4 +165,106 @@ mod tests {
         let expected = Packet::new(PacketType::Open, vec![1, 2, 3]);
         assert_eq!(expected, packet);
     }
+
+    #[test]
+    fn encode_packet_str_test() {
+        let packet = Packet::new(PacketType::Pong, b"probe".to_vec());
+        let res = EngineIOParserImpl::encode_packet_str(packet).unwrap();
+        assert_eq!("3probe", res)
+    }
+
+    #[test]
+    fn decode_packet_str_test() {
+        let packet = EngineIOParserImpl::decode_packet_str("42[\"hello\"]").unwrap();
+        let expected = Packet::new(PacketType::Message, b"2[\"hello\"]".to_vec());
+        assert_eq!(expected, packet);
+        assert_eq!(
+            EngineIOParserImpl::decode_packet_str(""),
+            Err(ParserError::EmptyPacket)
+        );
+        assert_eq!(
+            EngineIOParserImpl::decode_packet_str("9"),
+            Err(ParserError::InvalidPacketType(9))
+        );
+    }
+
+    #[test]
+    fn payload_test() {
+        let packets = vec![
+            Packet::new(PacketType::Open, b"{}".to_vec()),
+            Packet::new(PacketType::Noop, Vec::new()),
+        ];
+        let encoded = EngineIOParserImpl::encode_payload(packets).unwrap();
+        assert_eq!("0{}\x1e6", encoded);
+
+        let decoded = EngineIOParserImpl::decode_payload(&encoded).unwrap();
+        let expected = vec![
+            Packet::new(PacketType::Open, b"{}".to_vec()),
+            Packet::new(PacketType::Noop, Vec::new()),
+        ];
+        assert_eq!(expected, decoded);
+        assert_eq!(
+            EngineIOParserImpl::decode_payload("4a\x1e"),
+            Err(ParserError::EmptyPacket)
+        );
+    }
+
+    #[test]
+    fn decode_payload_limited_test() {
+        let decoded = EngineIOParserImpl::decode_payload_limited("4a\x1e4b", 2).unwrap();
+        assert_eq!(2, decoded.len());
+        assert_eq!(
+            EngineIOParserImpl::decode_payload_limited("4a\x1e4b\x1e4c", 2),
+            Err(ParserError::TooManyPackets(2))
+        );
+    }
+
+    #[test]
+    fn binary_packet_str_test() {
+        let encoded = EngineIOParserImpl::encode_packet_str(Packet::binary(vec![1, 2, 3])).unwrap();
+        assert_eq!("bAQID", encoded);
+        let decoded = EngineIOParserImpl::decode_packet_str(&encoded).unwrap();
+        assert_eq!(Packet::binary(vec![1, 2, 3]), decoded);
+        assert_eq!(
+            EngineIOParserImpl::decode_packet_str("b!!"),
+            Err(ParserError::InvalidBase64)
+        );
+
+        let encoded = EngineIOParserImpl::encode_payload_v3(vec![Packet::binary(vec![1, 2, 3])]);
+        assert_eq!("6:b4AQID", encoded.unwrap());
+    }
+
+    #[test]
+    fn payload_v3_test() {
+        let packets = vec![
+            Packet::new(PacketType::Message, "héllo €".as_bytes().to_vec()),
+            Packet::new(PacketType::Noop, Vec::new()),
+        ];
+        let encoded = EngineIOParserImpl::encode_payload_v3(packets).unwrap();
+        assert_eq!("8:4héllo €1:6", encoded);
+
+        let decoded = EngineIOParserImpl::decode_payload_v3(&encoded, 10).unwrap();
+        let expected = vec![
+            Packet::new(PacketType::Message, "héllo €".as_bytes().to_vec()),
+            Packet::new(PacketType::Noop, Vec::new()),
+        ];
+        assert_eq!(expected, decoded);
+
+        assert_eq!(
+            EngineIOParserImpl::decode_payload_v3("9:4hello", 10),
+            Err(ParserError::InvalidLength)
+        );
+        assert_eq!(
+            EngineIOParserImpl::decode_payload_v3("4hello", 10),
+            Err(ParserError::InvalidLength)
+        );
+        assert_eq!(
+            EngineIOParserImpl::decode_payload_v3("2:4a2:4b", 1),
+            Err(ParserError::TooManyPackets(1))
+        );
+        assert_eq!(
+            EngineIOParserImpl::decode_payload_v3("4:b4!!", 10),
+            Err(ParserError::InvalidBase64)
+        );
+    }
 }
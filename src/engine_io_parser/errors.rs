@@ -6,4 +6,12 @@ pub enum ParserError {
     InvalidPacketType(u8),
     #[error("Empty Packet")]
     EmptyPacket,
+    #[error("Invalid UTF-8 Data")]
+    InvalidUtf8,
+    #[error("Payload has more than {0} packets")]
+    TooManyPackets(usize),
+    #[error("Invalid Packet Length")]
+    InvalidLength,
+    #[error("Invalid Base64 Data")]
+    InvalidBase64,
 }
@@ -34,6 +34,131 @@ pub trait EngineIoParser {
     /// assert_eq!(expected, packet)
     /// ```
     fn decode_packet(data: &[u8]) -> Result<Packet, ParserError>;
+
+    /// Encodes a packet for a text based transport. <packet type id as char><data>
+    /// Binary messages are base64 encoded, prefixed by 'b'
+    /// ```rust
+    /// # use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    /// # use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+    /// # use socket_io_rust::engine_io_parser::EngineIoParser;
+    ///
+    /// let packet = Packet::new(PacketType::Message, b"hello".to_vec());
+    /// let res = EngineIOParserImpl::encode_packet_str(packet).unwrap();
+    /// assert_eq!("4hello", res)
+    /// ```
+    fn encode_packet_str(packet: Packet) -> Result<String, ParserError>;
+
+    /// Decodes a packet received by a text based transport
+    /// ```rust
+    /// # use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    /// # use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+    /// # use socket_io_rust::engine_io_parser::EngineIoParser;
+    ///
+    /// let packet = EngineIOParserImpl::decode_packet_str("2probe").unwrap();
+    /// let expected = Packet::new(PacketType::Ping, b"probe".to_vec());
+    /// assert_eq!(expected, packet)
+    /// ```
+    fn decode_packet_str(data: &str) -> Result<Packet, ParserError>;
+
+    /// Encodes packets as a payload for the polling transport. Packets are
+    /// separated by the record separator `\x1e`
+    /// ```rust
+    /// # use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    /// # use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+    /// # use socket_io_rust::engine_io_parser::EngineIoParser;
+    ///
+    /// let packets = vec![
+    ///     Packet::new(PacketType::Message, b"hello".to_vec()),
+    ///     Packet::new(PacketType::Ping, Vec::new()),
+    /// ];
+    /// let res = EngineIOParserImpl::encode_payload(packets).unwrap();
+    /// assert_eq!("4hello\x1e2", res)
+    /// ```
+    fn encode_payload(packets: Vec<Packet>) -> Result<String, ParserError>;
+
+    /// Decodes a payload received by the polling transport
+    /// ```rust
+    /// # use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    /// # use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+    /// # use socket_io_rust::engine_io_parser::EngineIoParser;
+    ///
+    /// let packets = EngineIOParserImpl::decode_payload("4hello\x1e3").unwrap();
+    /// let expected = vec![
+    ///     Packet::new(PacketType::Message, b"hello".to_vec()),
+    ///     Packet::new(PacketType::Pong, Vec::new()),
+    /// ];
+    /// assert_eq!(expected, packets)
+    /// ```
+    fn decode_payload(data: &str) -> Result<Vec<Packet>, ParserError>;
+
+    /// Decodes a payload received by the polling transport, failing when it
+    /// has more than `max_packets` packets
+    /// ```rust
+    /// # use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    /// # use socket_io_rust::engine_io_parser::errors::ParserError;
+    /// # use socket_io_rust::engine_io_parser::EngineIoParser;
+    ///
+    /// let res = EngineIOParserImpl::decode_payload_limited("4a\x1e4b\x1e4c", 2);
+    /// assert_eq!(Err(ParserError::TooManyPackets(2)), res)
+    /// ```
+    fn decode_payload_limited(data: &str, max_packets: usize) -> Result<Vec<Packet>, ParserError>;
+
+    /// Encodes packets as a payload for the polling transport of the protocol
+    /// v3. Each packet is prefixed by its length and ':'
+    /// ```rust
+    /// # use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    /// # use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+    /// # use socket_io_rust::engine_io_parser::EngineIoParser;
+    ///
+    /// let packets = vec![
+    ///     Packet::new(PacketType::Message, b"hello".to_vec()),
+    ///     Packet::new(PacketType::Ping, Vec::new()),
+    /// ];
+    /// let res = EngineIOParserImpl::encode_payload_v3(packets).unwrap();
+    /// assert_eq!("6:4hello1:2", res)
+    /// ```
+    fn encode_payload_v3(packets: Vec<Packet>) -> Result<String, ParserError>;
+
+    /// Decodes a payload received by the polling transport of the protocol
+    /// v3, failing when it has more than `max_packets` packets. Binary
+    /// packets are base64 encoded, prefixed by 'b'
+    /// ```rust
+    /// # use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+    /// # use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+    /// # use socket_io_rust::engine_io_parser::EngineIoParser;
+    ///
+    /// let packets = EngineIOParserImpl::decode_payload_v3("6:4hello6:b4AQID", 10).unwrap();
+    /// let expected = vec![
+    ///     Packet::new(PacketType::Message, b"hello".to_vec()),
+    ///     Packet::binary(vec![1, 2, 3]),
+    /// ];
+    /// assert_eq!(expected, packets)
+    /// ```
+    fn decode_payload_v3(data: &str, max_packets: usize) -> Result<Vec<Packet>, ParserError>;
+}
+
+/// Separates the packets of a payload
+pub const RECORD_SEPARATOR: char = '\x1e';
+
+/// The revisions of the Engine.IO protocol, chosen by the client with the
+/// `EIO` query parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtocolVersion {
+    /// Used by socket.io-client 2.x: the client pings the server and polling
+    /// payloads are length prefixed
+    V3,
+    V4,
+}
+
+impl ProtocolVersion {
+    /// Get the version from the `EIO` query parameter
+    pub fn from_query(eio: &str) -> Option<Self> {
+        match eio {
+            "3" => Some(ProtocolVersion::V3),
+            "4" => Some(ProtocolVersion::V4),
+            _ => None,
+        }
+    }
 }
 
 pub mod engine_io_parser_impl;
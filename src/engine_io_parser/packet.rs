@@ -45,9 +45,9 @@ pub enum PacketType {
     Noop = 6,
 }
 
-impl Into<u8> for PacketType {
-    fn into(self) -> u8 {
-        self as u8
+impl From<PacketType> for u8 {
+    fn from(packet_type: PacketType) -> Self {
+        packet_type as u8
     }
 }
 
@@ -74,10 +74,26 @@ impl TryFrom<u8> for PacketType {
 pub struct Packet {
     pub packet_type: PacketType,
     pub data: Vec<u8>,
+    /// Whether the data is binary. Binary messages are sent in binary frames
+    /// by websocket, and base64 encoded by polling.
+    pub binary: bool,
 }
 
 impl Packet {
     pub fn new(packet_type: PacketType, data: Vec<u8>) -> Self {
-        Packet { packet_type, data }
+        Packet {
+            packet_type,
+            data,
+            binary: false,
+        }
+    }
+
+    /// Creates a binary message
+    pub fn binary(data: Vec<u8>) -> Self {
+        Packet {
+            packet_type: PacketType::Message,
+            data,
+            binary: true,
+        }
     }
 }
@@ -0,0 +1,97 @@
+use actix_web::http::StatusCode;
+use actix_web::{HttpResponse, ResponseError};
+use serde_json::json;
+use thiserror::Error;
+
+/// The error codes of the Engine.IO protocol, sent to the client when a
+/// request is rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EngineIoErrorCode {
+    UnknownTransport = 0,
+    UnknownSid = 1,
+    BadHandshakeMethod = 2,
+    BadRequest = 3,
+    Forbidden = 4,
+    UnsupportedProtocolVersion = 5,
+}
+
+impl EngineIoErrorCode {
+    /// Get the default message of the code
+    pub fn message(self) -> &'static str {
+        match self {
+            EngineIoErrorCode::UnknownTransport => "Transport unknown",
+            EngineIoErrorCode::UnknownSid => "Session ID unknown",
+            EngineIoErrorCode::BadHandshakeMethod => "Bad handshake method",
+            EngineIoErrorCode::BadRequest => "Bad request",
+            EngineIoErrorCode::Forbidden => "Forbidden",
+            EngineIoErrorCode::UnsupportedProtocolVersion => "Unsupported protocol version",
+        }
+    }
+}
+
+impl From<EngineIoErrorCode> for u8 {
+    fn from(code: EngineIoErrorCode) -> Self {
+        code as u8
+    }
+}
+
+/// A rejected Engine.IO request. The client receives the code and the message
+/// as a JSON body.
+#[derive(Error, Debug, Clone, PartialEq)]
+#[error("Engine IO Error {code:?}: {message}")]
+pub struct EngineIoError {
+    pub code: EngineIoErrorCode,
+    pub message: String,
+}
+
+impl EngineIoError {
+    pub fn new(code: EngineIoErrorCode) -> Self {
+        Self::with_message(code, code.message())
+    }
+
+    pub fn with_message(code: EngineIoErrorCode, message: &str) -> Self {
+        Self {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<EngineIoErrorCode> for EngineIoError {
+    fn from(code: EngineIoErrorCode) -> Self {
+        EngineIoError::new(code)
+    }
+}
+
+impl ResponseError for EngineIoError {
+    fn status_code(&self) -> StatusCode {
+        match self.code {
+            EngineIoErrorCode::Forbidden => StatusCode::FORBIDDEN,
+            _ => StatusCode::BAD_REQUEST,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status_code()).json(json!({
+            "code": u8::from(self.code),
+            "message": self.message,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_server::error::{EngineIoError, EngineIoErrorCode};
+    use actix_web::ResponseError;
+
+    #[test]
+    fn error_response_test() {
+        let err = EngineIoError::new(EngineIoErrorCode::UnknownSid);
+        assert_eq!(err.message, "Session ID unknown");
+        assert_eq!(err.error_response().status(), 400);
+
+        let err = EngineIoError::with_message(EngineIoErrorCode::Forbidden, "IP not allowed");
+        assert_eq!(err.error_response().status(), 403);
+        assert_eq!(u8::from(err.code), 4);
+    }
+}
@@ -1,30 +1,101 @@
 use crate::engine_io_parser::packet::Packet;
-use std::collections::HashMap;
-use std::fmt::Error;
-
-pub enum TransportReadyState {
-    OPENING,
-    OPEN,
-    CLOSED,
-    PAUSED,
+use futures::channel::mpsc;
+use futures::future::LocalBoxFuture;
+use std::{fmt, io};
+use thiserror::Error;
+
+/// The transports a client can use to reach the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TransportType {
+    Polling,
+    /// The polling variant for browsers without cross-origin XHR, chosen
+    /// with the `j` query parameter
+    JsonpPolling,
+    Websocket,
+    /// A transport of a third party, by name, registered with
+    /// [`ServerOptionsBuilder::custom_transport`](../../socket_io/server_options/struct.ServerOptionsBuilder.html#method.custom_transport)
+    Custom(&'static str),
+}
+
+impl TransportType {
+    /// Get the built-in transport from the `transport` query parameter
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "polling" => Some(TransportType::Polling),
+            "websocket" => Some(TransportType::Websocket),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TransportType::Polling | TransportType::JsonpPolling => "polling",
+            TransportType::Websocket => "websocket",
+            TransportType::Custom(name) => name,
+        }
+    }
+}
+
+/// Why a transport failed
+#[derive(Debug, Error)]
+pub enum TransportError {
+    #[error("Transport is not writable")]
+    NotWritable,
+    #[error("Transport is closed")]
+    Closed,
+    #[error("Invalid data received: {0}")]
+    InvalidData(String),
+    #[error("Transport IO error: {0}")]
+    Io(#[from] io::Error),
 }
 
-pub struct TransportOptions {
-    pub hostname: String,
-    pub path: String,
-    pub port: usize,
-    pub policy_port: usize,
-    pub timestamp_param: String,
-    pub secure: bool,
-    pub timestamp_requests: bool,
-    pub query: HashMap<String, String>,
+/// What a transport reports to the session it carries
+#[derive(Debug)]
+pub enum TransportEvent {
+    /// A packet was received from the client
+    Packet(Packet),
+    /// The transport was not writable and is again, so that the session can
+    /// send the packets it buffered
+    Drain,
+    /// The client closed the transport
+    Close,
+    /// The transport failed, it must not be used anymore
+    Error(TransportError),
 }
 
-pub trait Transport {
-    fn open(&mut self);
-    fn close(&mut self);
-    fn send(&mut self, packets: Vec<Packet>);
-    fn on_open(&mut self);
-    fn on_data(&mut self, data: [u8]);
-    fn on_error(&mut self, err: Error);
+/// The receiving half of the events of a transport, given to the session
+/// along with the transport
+pub type TransportEvents = mpsc::UnboundedReceiver<TransportEvent>;
+
+/// A transport carrying the packets of an Engine.IO session, between the
+/// session and the client.
+///
+/// The transport reports what it receives through its [`TransportEvents`].
+/// The session sends packets only while the transport is writable, and waits
+/// for a [`TransportEvent::Drain`] otherwise. Binary packets are handed to
+/// the transport as they are, a transport that cannot carry binary data
+/// encodes them in base64 itself.
+///
+/// The futures of a transport are driven by the session, on its own thread.
+/// The server hands its transports over to the sessions, which may run on
+/// another thread, and thus needs them to be `Send`.
+///
+/// [`TransportEvents`]: type.TransportEvents.html
+/// [`TransportEvent::Drain`]: enum.TransportEvent.html#variant.Drain
+pub trait Transport: fmt::Debug {
+    /// The name of the transport, as in the `transport` query parameter
+    fn name(&self) -> &'static str;
+
+    /// Whether packets can be sent right away
+    fn writable(&self) -> bool;
+
+    /// Sends packets, which may be compressed when `compress` is true
+    fn send(
+        &mut self,
+        packets: Vec<Packet>,
+        compress: bool,
+    ) -> LocalBoxFuture<'static, Result<(), TransportError>>;
+
+    /// Closes the transport
+    fn close(&mut self) -> LocalBoxFuture<'static, Result<(), TransportError>>;
 }
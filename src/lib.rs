@@ -3,7 +3,14 @@ pub mod engine_io_server;
 pub mod random_id_generator;
 pub mod socket_io;
 pub mod socket_io_adaptor;
+pub mod socket_io_admin;
+pub mod socket_io_client;
+pub mod socket_io_connection;
+pub mod socket_io_metrics;
 pub mod socket_io_packet_codec;
+pub mod socket_io_polling;
+#[cfg(any(test, feature = "test-util"))]
+pub mod socket_io_testing;
 pub mod socket_io_websocket;
 
 #[cfg(test)]
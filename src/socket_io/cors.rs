@@ -0,0 +1,172 @@
+use crate::socket_io::server_options::{CorsOptions, CorsOrigin};
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse};
+
+/// Adds the Cross-Origin Resource Sharing headers to a response of the engine
+/// endpoint. Nothing is added when the request has no allowed origin.
+pub(crate) fn add_cors_headers(options: &CorsOptions, req: &HttpRequest, res: &mut HttpResponse) {
+    let origin = match req.headers().get(header::ORIGIN) {
+        Some(origin) => origin.clone(),
+        None => return,
+    };
+    let headers = res.headers_mut();
+
+    if let CorsOrigin::Any = options.origin {
+        if !options.credentials {
+            headers.insert(
+                header::ACCESS_CONTROL_ALLOW_ORIGIN,
+                HeaderValue::from_static("*"),
+            );
+            return add_credentials(options, headers);
+        }
+    }
+
+    let allowed = origin
+        .to_str()
+        .map(|o| options.origin.is_allowed(o))
+        .unwrap_or(false);
+    // the response depends on the origin, caches must know it
+    headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    if allowed {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+        add_credentials(options, headers);
+    }
+}
+
+fn add_credentials(options: &CorsOptions, headers: &mut HeaderMap) {
+    if options.credentials {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+}
+
+/// Answers a preflight request, telling the browser which methods and headers
+/// it may use
+pub(crate) fn preflight_response(options: &CorsOptions, req: &HttpRequest) -> HttpResponse {
+    let mut res = HttpResponse::NoContent().finish();
+    add_cors_headers(options, req, &mut res);
+    if !res
+        .headers()
+        .contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+    {
+        return res;
+    }
+
+    let headers = res.headers_mut();
+    if let Ok(methods) = HeaderValue::from_str(&options.methods.join(",")) {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, methods);
+    }
+    let allowed_headers = if options.allowed_headers.is_empty() {
+        req.headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .cloned()
+    } else {
+        HeaderValue::from_str(&options.allowed_headers.join(",")).ok()
+    };
+    if let Some(allowed_headers) = allowed_headers {
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, allowed_headers);
+    }
+    if let Some(max_age) = options.max_age {
+        headers.insert(
+            header::ACCESS_CONTROL_MAX_AGE,
+            HeaderValue::from(max_age.as_secs()),
+        );
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io::cors::{add_cors_headers, preflight_response};
+    use crate::socket_io::server_options::{CorsOptions, CorsOrigin};
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+    use actix_web::HttpResponse;
+
+    fn header_of(res: &HttpResponse, name: header::HeaderName) -> Option<&str> {
+        res.headers().get(name).map(|v| v.to_str().unwrap())
+    }
+
+    #[test]
+    fn add_cors_headers_test_any_origin() {
+        let req = TestRequest::default()
+            .header(header::ORIGIN, "http://example.com")
+            .to_http_request();
+        let mut res = HttpResponse::Ok().finish();
+        add_cors_headers(&CorsOptions::default(), &req, &mut res);
+        assert_eq!(
+            header_of(&res, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("*")
+        );
+
+        let options = CorsOptions {
+            credentials: true,
+            ..Default::default()
+        };
+        let mut res = HttpResponse::Ok().finish();
+        add_cors_headers(&options, &req, &mut res);
+        assert_eq!(
+            header_of(&res, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("http://example.com")
+        );
+        assert_eq!(
+            header_of(&res, header::ACCESS_CONTROL_ALLOW_CREDENTIALS),
+            Some("true")
+        );
+    }
+
+    #[test]
+    fn add_cors_headers_test_listed_origins() {
+        let options = CorsOptions {
+            origin: CorsOrigin::List(vec![String::from("http://example.com")]),
+            ..Default::default()
+        };
+        let req = TestRequest::default()
+            .header(header::ORIGIN, "http://example.com")
+            .to_http_request();
+        let mut res = HttpResponse::Ok().finish();
+        add_cors_headers(&options, &req, &mut res);
+        assert_eq!(
+            header_of(&res, header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some("http://example.com")
+        );
+        assert_eq!(header_of(&res, header::VARY), Some("Origin"));
+
+        let req = TestRequest::default()
+            .header(header::ORIGIN, "http://evil.com")
+            .to_http_request();
+        let mut res = HttpResponse::Ok().finish();
+        add_cors_headers(&options, &req, &mut res);
+        assert_eq!(header_of(&res, header::ACCESS_CONTROL_ALLOW_ORIGIN), None);
+    }
+
+    #[test]
+    fn preflight_response_test() {
+        let options = CorsOptions {
+            origin: CorsOrigin::dynamic(|origin| origin.ends_with(".example.com")),
+            ..Default::default()
+        };
+        let req = TestRequest::default()
+            .header(header::ORIGIN, "http://app.example.com")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-token")
+            .to_http_request();
+        let res = preflight_response(&options, &req);
+        assert_eq!(res.status(), 204);
+        assert_eq!(
+            header_of(&res, header::ACCESS_CONTROL_ALLOW_METHODS),
+            Some("GET,POST")
+        );
+        assert_eq!(
+            header_of(&res, header::ACCESS_CONTROL_ALLOW_HEADERS),
+            Some("x-token")
+        );
+
+        let req = TestRequest::default()
+            .header(header::ORIGIN, "http://example.org")
+            .to_http_request();
+        let res = preflight_response(&options, &req);
+        assert_eq!(header_of(&res, header::ACCESS_CONTROL_ALLOW_METHODS), None);
+    }
+}
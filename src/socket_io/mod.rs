@@ -1,2 +1,5 @@
+mod cors;
+pub mod server_options;
+#[allow(clippy::module_inception)]
+pub mod socket_io;
 pub mod socket_io_server;
-pub mod socket_io;
\ No newline at end of file
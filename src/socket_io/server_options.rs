@@ -0,0 +1,633 @@
+use crate::engine_io_parser::ProtocolVersion;
+use crate::engine_io_server::error::EngineIoError;
+use crate::engine_io_server::transport::{Transport, TransportEvents, TransportType};
+use crate::socket_io_adaptor::session_store::ConnectionStateRecoveryOptions;
+use crate::socket_io_admin::admin_ui::AdminUiOptions;
+use crate::socket_io_metrics::metrics::{Direction, Metrics};
+use crate::socket_io_packet_codec::default_packet_codec::DefaultPacketCodec;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use crate::socket_io_packet_codec::{
+    PacketCodecError, SocketIoPacketCodec, SocketIoPacketCodecMono, SocketIoProtocolVersion,
+};
+use actix_web::cookie::{Cookie, SameSite};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use futures::future::{FutureExt, LocalBoxFuture};
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Options of the per-message deflate extension of the websocket transport
+#[derive(Debug, Clone)]
+pub struct PerMessageDeflateOptions {
+    /// Messages smaller than this many bytes are not compressed
+    pub threshold: usize,
+}
+
+impl Default for PerMessageDeflateOptions {
+    fn default() -> Self {
+        Self { threshold: 1024 }
+    }
+}
+
+/// Options of the compression of the polling responses
+#[derive(Debug, Clone)]
+pub struct HttpCompressionOptions {
+    /// Responses smaller than this many bytes are not compressed
+    pub threshold: usize,
+}
+
+impl Default for HttpCompressionOptions {
+    fn default() -> Self {
+        Self { threshold: 1024 }
+    }
+}
+
+/// Options of the cookie carrying the session id, set on the handshake so that
+/// sticky-session load balancers can route on it
+#[derive(Debug, Clone)]
+pub struct CookieOptions {
+    pub name: String,
+    pub path: String,
+    pub http_only: bool,
+    pub same_site: Option<SameSite>,
+    pub secure: bool,
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CookieOptions {
+    fn default() -> Self {
+        Self {
+            name: String::from("io"),
+            path: String::from("/"),
+            http_only: true,
+            same_site: Some(SameSite::Lax),
+            secure: false,
+            max_age: None,
+        }
+    }
+}
+
+impl CookieOptions {
+    /// Get the cookie carrying the given session id
+    pub fn to_cookie(&self, sid: &str) -> Cookie<'static> {
+        let mut cookie = Cookie::build(self.name.clone(), sid.to_string())
+            .path(self.path.clone())
+            .http_only(self.http_only)
+            .secure(self.secure);
+        if let Some(same_site) = self.same_site {
+            cookie = cookie.same_site(same_site);
+        }
+        if let Some(max_age) = self.max_age {
+            cookie = cookie.max_age(max_age.as_secs() as i64);
+        }
+        cookie.finish()
+    }
+}
+
+/// The origins allowed to reach the server from a browser
+#[derive(Clone)]
+pub enum CorsOrigin {
+    /// Any origin is allowed
+    Any,
+    /// Only the listed origins are allowed
+    List(Vec<String>),
+    /// The callback decides whether the origin is allowed
+    Dynamic(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl CorsOrigin {
+    pub fn dynamic<F>(callback: F) -> Self
+    where
+        F: Fn(&str) -> bool + Send + Sync + 'static,
+    {
+        CorsOrigin::Dynamic(Arc::new(callback))
+    }
+
+    pub fn is_allowed(&self, origin: &str) -> bool {
+        match self {
+            CorsOrigin::Any => true,
+            CorsOrigin::List(origins) => origins.iter().any(|o| o == origin),
+            CorsOrigin::Dynamic(callback) => callback(origin),
+        }
+    }
+}
+
+impl fmt::Debug for CorsOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorsOrigin::Any => write!(f, "Any"),
+            CorsOrigin::List(origins) => f.debug_tuple("List").field(origins).finish(),
+            CorsOrigin::Dynamic(_) => write!(f, "Dynamic"),
+        }
+    }
+}
+
+/// Options of the Cross-Origin Resource Sharing headers
+#[derive(Debug, Clone)]
+pub struct CorsOptions {
+    pub origin: CorsOrigin,
+    /// The methods allowed by preflight requests
+    pub methods: Vec<String>,
+    /// The headers allowed by preflight requests, the requested ones are
+    /// allowed when empty
+    pub allowed_headers: Vec<String>,
+    pub credentials: bool,
+    /// How long the result of a preflight request may be cached
+    pub max_age: Option<Duration>,
+}
+
+impl Default for CorsOptions {
+    fn default() -> Self {
+        Self {
+            origin: CorsOrigin::Any,
+            methods: vec![String::from("GET"), String::from("POST")],
+            allowed_headers: Vec::new(),
+            credentials: false,
+            max_age: None,
+        }
+    }
+}
+
+/// Decides whether a handshake request is accepted, before the session is
+/// created
+#[derive(Clone)]
+pub struct AllowRequest(
+    Arc<dyn Fn(HttpRequest) -> LocalBoxFuture<'static, Result<(), EngineIoError>> + Send + Sync>,
+);
+
+impl AllowRequest {
+    pub(crate) fn call(
+        &self,
+        req: HttpRequest,
+    ) -> LocalBoxFuture<'static, Result<(), EngineIoError>> {
+        (self.0)(req)
+    }
+}
+
+impl fmt::Debug for AllowRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AllowRequest")
+    }
+}
+
+/// A transport started for the request of a client: the response to the
+/// request, the transport and its events
+pub type StartedTransport = (HttpResponse, Box<dyn Transport + Send>, TransportEvents);
+
+type StartTransport = dyn Fn(&HttpRequest, web::Payload, ProtocolVersion) -> Result<StartedTransport, Error>
+    + Send
+    + Sync;
+
+/// A transport of a third party, registered with
+/// [`ServerOptionsBuilder::custom_transport`](struct.ServerOptionsBuilder.html#method.custom_transport)
+#[derive(Clone)]
+pub struct CustomTransport {
+    name: &'static str,
+    start: Arc<StartTransport>,
+}
+
+impl CustomTransport {
+    /// The name of the transport, as in the `transport` query parameter
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub(crate) fn start(
+        &self,
+        req: &HttpRequest,
+        payload: web::Payload,
+        protocol: ProtocolVersion,
+    ) -> Result<StartedTransport, Error> {
+        (self.start)(req, payload, protocol)
+    }
+}
+
+impl fmt::Debug for CustomTransport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("CustomTransport").field(&self.name).finish()
+    }
+}
+
+/// The Socket.IO packet codec used by the connections
+#[derive(Clone)]
+struct SharedCodec(Arc<dyn SocketIoPacketCodecMono<Error = PacketCodecError> + Send + Sync>);
+
+impl fmt::Debug for SharedCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SharedCodec")
+    }
+}
+
+/// The codec of the server, counting the packets it encodes and decodes when
+/// the metrics are enabled
+pub(crate) struct PacketCodec<'a> {
+    codec: &'a (dyn SocketIoPacketCodecMono<Error = PacketCodecError> + Send + Sync),
+    metrics: Option<&'a Metrics>,
+}
+
+impl PacketCodec<'_> {
+    pub(crate) fn encode_to_string(
+        &self,
+        packet: &SocketIoPacket,
+    ) -> Result<String, PacketCodecError> {
+        let encoded = self.codec.encode_to_string_mono(packet)?;
+        if let Some(metrics) = self.metrics {
+            metrics.on_socket_io_packet(Direction::Out, packet.packet_type);
+        }
+        Ok(encoded)
+    }
+
+    pub(crate) fn decode(&self, data: &str) -> Result<SocketIoPacket, PacketCodecError> {
+        let packet = self.codec.decode_mon(data)?;
+        if let Some(metrics) = self.metrics {
+            metrics.on_socket_io_packet(Direction::In, packet.packet_type);
+        }
+        Ok(packet)
+    }
+
+    pub(crate) fn connect_error(
+        &self,
+        nsp: &str,
+        message: &str,
+        protocol: SocketIoProtocolVersion,
+    ) -> SocketIoPacket {
+        self.codec.connect_error_mono(nsp, message, protocol)
+    }
+}
+
+/// Options of the Socket.IO server, reaching every transport and namespace.
+/// Built with [`ServerOptions::builder`](#method.builder).
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// The path the server is reachable at
+    pub path: String,
+    /// How often the server pings the client
+    pub ping_interval: Duration,
+    /// How long the server waits for the client to answer a ping
+    pub ping_timeout: Duration,
+    /// How long an upgrade of the transport may take
+    pub upgrade_timeout: Duration,
+    /// The maximum size of a message, in bytes. Larger websocket frames and
+    /// polling requests close the connection, as do binary packets whose
+    /// attachments add up to more.
+    pub max_http_buffer_size: usize,
+    /// The maximum number of packets of a polling request
+    pub max_payload_packets: usize,
+    /// The maximum number of binary attachments of a packet, so that a client
+    /// cannot make the server wait for an unbounded number of them. Packets
+    /// with more close the connection.
+    pub max_attachments: usize,
+    /// The transports clients may use
+    pub transports: Vec<TransportType>,
+    /// The transports of third parties the server can start, which clients
+    /// may use once listed in `transports` as well
+    pub custom_transports: Vec<CustomTransport>,
+    /// Whether clients may upgrade from polling to websocket
+    pub allow_upgrades: bool,
+    /// Whether clients of the Engine.IO protocol v3 (socket.io-client 2.x)
+    /// are accepted along with the ones of the v4
+    pub allow_eio3: bool,
+    pub per_message_deflate: Option<PerMessageDeflateOptions>,
+    pub http_compression: Option<HttpCompressionOptions>,
+    pub cookie: Option<CookieOptions>,
+    pub cors: Option<CorsOptions>,
+    /// How long a client may stay connected without joining a namespace
+    pub connect_timeout: Duration,
+    pub connection_state_recovery: Option<ConnectionStateRecoveryOptions>,
+    pub allow_request: Option<AllowRequest>,
+    /// Set when the metrics are enabled, to be served by
+    /// [`SocketIoServer::get_metrics_resource`](../socket_io_server/struct.SocketIoServer.html#method.get_metrics_resource)
+    pub metrics: Option<Arc<Metrics>>,
+    /// Set to serve the `@socket.io/admin-ui` dashboard
+    pub admin_ui: Option<AdminUiOptions>,
+    codec: SharedCodec,
+}
+
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            path: String::from("/socket.io"),
+            ping_interval: Duration::from_millis(25000),
+            ping_timeout: Duration::from_millis(20000),
+            upgrade_timeout: Duration::from_millis(10000),
+            max_http_buffer_size: 1_000_000,
+            max_payload_packets: 1000,
+            max_attachments: 10,
+            transports: vec![
+                TransportType::Polling,
+                TransportType::JsonpPolling,
+                TransportType::Websocket,
+            ],
+            custom_transports: Vec::new(),
+            allow_upgrades: true,
+            allow_eio3: false,
+            per_message_deflate: None,
+            http_compression: None,
+            cookie: None,
+            cors: None,
+            connect_timeout: Duration::from_millis(45000),
+            connection_state_recovery: None,
+            allow_request: None,
+            metrics: None,
+            admin_ui: None,
+            codec: SharedCodec(Arc::new(DefaultPacketCodec)),
+        }
+    }
+}
+
+impl ServerOptions {
+    pub fn builder() -> ServerOptionsBuilder {
+        ServerOptionsBuilder::default()
+    }
+
+    /// Get the codec of the server, which counts the packets in the metrics
+    /// the options have when it is used
+    pub(crate) fn codec(&self) -> PacketCodec<'_> {
+        PacketCodec {
+            codec: &*self.codec.0,
+            metrics: self.metrics.as_deref(),
+        }
+    }
+
+    /// Sets the session cookie on a handshake response, when enabled
+    pub(crate) fn set_session_cookie(&self, res: &mut HttpResponse, sid: &str) {
+        if let Some(cookie) = self.cookie.as_ref() {
+            let _ = res.add_cookie(&cookie.to_cookie(sid));
+        }
+    }
+
+    /// Get the transport named in the `transport` query parameter, built-in
+    /// or of a third party
+    pub fn get_transport(&self, name: &str) -> Option<TransportType> {
+        TransportType::from_name(name).or_else(|| {
+            self.get_custom_transport(name)
+                .map(|transport| TransportType::Custom(transport.name))
+        })
+    }
+
+    pub(crate) fn get_custom_transport(&self, name: &str) -> Option<&CustomTransport> {
+        self.custom_transports
+            .iter()
+            .find(|transport| transport.name == name)
+    }
+
+    pub fn is_transport_allowed(&self, transport: TransportType) -> bool {
+        self.transports.contains(&transport)
+    }
+
+    /// Get the transports a client connected with the given transport can
+    /// upgrade to. Polling clients may upgrade to websocket and to the
+    /// transports of third parties.
+    pub fn get_upgrades(&self, transport: TransportType) -> Vec<TransportType> {
+        if !self.allow_upgrades || transport != TransportType::Polling {
+            return Vec::new();
+        }
+        self.transports
+            .iter()
+            .filter(|&&t| matches!(t, TransportType::Websocket | TransportType::Custom(_)))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Builds [`ServerOptions`](struct.ServerOptions.html), starting from the
+/// defaults
+/// ```rust
+/// # use socket_io_rust::socket_io::server_options::ServerOptions;
+/// # use std::time::Duration;
+/// let options = ServerOptions::builder()
+///     .path("/ws")
+///     .ping_interval(Duration::from_secs(10))
+///     .build();
+/// assert_eq!(options.path, "/ws");
+/// ```
+#[derive(Debug, Default)]
+pub struct ServerOptionsBuilder {
+    options: ServerOptions,
+}
+
+impl ServerOptionsBuilder {
+    pub fn path(mut self, path: &str) -> Self {
+        self.options.path = path.to_string();
+        self
+    }
+
+    pub fn ping_interval(mut self, ping_interval: Duration) -> Self {
+        self.options.ping_interval = ping_interval;
+        self
+    }
+
+    pub fn ping_timeout(mut self, ping_timeout: Duration) -> Self {
+        self.options.ping_timeout = ping_timeout;
+        self
+    }
+
+    pub fn upgrade_timeout(mut self, upgrade_timeout: Duration) -> Self {
+        self.options.upgrade_timeout = upgrade_timeout;
+        self
+    }
+
+    pub fn max_http_buffer_size(mut self, max_http_buffer_size: usize) -> Self {
+        self.options.max_http_buffer_size = max_http_buffer_size;
+        self
+    }
+
+    pub fn max_payload_packets(mut self, max_payload_packets: usize) -> Self {
+        self.options.max_payload_packets = max_payload_packets;
+        self
+    }
+
+    pub fn max_attachments(mut self, max_attachments: usize) -> Self {
+        self.options.max_attachments = max_attachments;
+        self
+    }
+
+    pub fn transports(mut self, transports: Vec<TransportType>) -> Self {
+        self.options.transports = transports;
+        self
+    }
+
+    /// Serves a transport of a third party to the clients whose `transport`
+    /// query parameter is `name`, and allows it. `start` answers the request
+    /// of the client with the transport, on which a new session starts, or to
+    /// which the session of the `sid` query parameter upgrades.
+    pub fn custom_transport<F>(mut self, name: &'static str, start: F) -> Self
+    where
+        F: Fn(&HttpRequest, web::Payload, ProtocolVersion) -> Result<StartedTransport, Error>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.options.transports.push(TransportType::Custom(name));
+        self.options.custom_transports.push(CustomTransport {
+            name,
+            start: Arc::new(start),
+        });
+        self
+    }
+
+    pub fn allow_upgrades(mut self, allow_upgrades: bool) -> Self {
+        self.options.allow_upgrades = allow_upgrades;
+        self
+    }
+
+    pub fn allow_eio3(mut self, allow_eio3: bool) -> Self {
+        self.options.allow_eio3 = allow_eio3;
+        self
+    }
+
+    pub fn per_message_deflate(mut self, per_message_deflate: PerMessageDeflateOptions) -> Self {
+        self.options.per_message_deflate = Some(per_message_deflate);
+        self
+    }
+
+    pub fn http_compression(mut self, http_compression: HttpCompressionOptions) -> Self {
+        self.options.http_compression = Some(http_compression);
+        self
+    }
+
+    pub fn cookie(mut self, cookie: CookieOptions) -> Self {
+        self.options.cookie = Some(cookie);
+        self
+    }
+
+    pub fn cors(mut self, cors: CorsOptions) -> Self {
+        self.options.cors = Some(cors);
+        self
+    }
+
+    pub fn connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.options.connect_timeout = connect_timeout;
+        self
+    }
+
+    pub fn connection_state_recovery(mut self, options: ConnectionStateRecoveryOptions) -> Self {
+        self.options.connection_state_recovery = Some(options);
+        self
+    }
+
+    /// Sets the hook deciding whether a handshake request is accepted. A
+    /// rejected request is answered with the code and message of the error.
+    /// ```rust
+    /// # use socket_io_rust::engine_io_server::error::{EngineIoError, EngineIoErrorCode};
+    /// # use socket_io_rust::socket_io::server_options::ServerOptions;
+    /// let options = ServerOptions::builder()
+    ///     .allow_request(|req| async move {
+    ///         match req.peer_addr() {
+    ///             Some(addr) if addr.ip().is_loopback() => Ok(()),
+    ///             _ => Err(EngineIoError::new(EngineIoErrorCode::Forbidden)),
+    ///         }
+    ///     })
+    ///     .build();
+    /// ```
+    pub fn allow_request<F, Fut>(mut self, allow_request: F) -> Self
+    where
+        F: Fn(HttpRequest) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), EngineIoError>> + 'static,
+    {
+        self.options.allow_request = Some(AllowRequest(Arc::new(move |req| {
+            allow_request(req).boxed_local()
+        })));
+        self
+    }
+
+    /// Sets the codec encoding and decoding the Socket.IO packets
+    pub fn codec<C>(mut self, codec: C) -> Self
+    where
+        C: SocketIoPacketCodec<Error = PacketCodecError> + Send + Sync + 'static,
+    {
+        self.options.codec = SharedCodec(Arc::new(codec));
+        self
+    }
+
+    /// Enables the metrics of the server, in the Prometheus text format
+    pub fn metrics(mut self, enabled: bool) -> Self {
+        self.options.metrics = if enabled {
+            Some(Arc::new(Metrics::new()))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Serves the `@socket.io/admin-ui` dashboard on the namespace of the
+    /// options
+    pub fn admin_ui(mut self, options: AdminUiOptions) -> Self {
+        self.options.admin_ui = Some(options);
+        self
+    }
+
+    pub fn build(self) -> ServerOptions {
+        self.options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_server::transport::TransportType;
+    use crate::socket_io::server_options::{CookieOptions, ServerOptions};
+    use crate::socket_io_metrics::metrics::Metrics;
+    use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+    use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn get_upgrades_test() {
+        let options = ServerOptions::default();
+        assert_eq!(
+            options.get_upgrades(TransportType::Polling),
+            vec![TransportType::Websocket]
+        );
+        assert!(options.get_upgrades(TransportType::Websocket).is_empty());
+
+        let options = ServerOptions::builder().allow_upgrades(false).build();
+        assert!(options.get_upgrades(TransportType::Polling).is_empty());
+
+        let options = ServerOptions::builder()
+            .transports(vec![TransportType::Polling])
+            .build();
+        assert!(options.get_upgrades(TransportType::Polling).is_empty());
+        assert!(!options.is_transport_allowed(TransportType::Websocket));
+    }
+
+    #[test]
+    fn to_cookie_test() {
+        let options = CookieOptions {
+            max_age: Some(Duration::from_secs(60)),
+            ..Default::default()
+        };
+        assert_eq!(
+            options.to_cookie("abc").to_string(),
+            "io=abc; HttpOnly; SameSite=Lax; Path=/; Max-Age=60"
+        );
+    }
+
+    #[test]
+    fn codec_metrics_test() {
+        // the metrics are counted however they are set
+        let options = ServerOptions {
+            metrics: Some(Arc::new(Metrics::new())),
+            ..Default::default()
+        };
+        let packet = SocketIoPacket {
+            packet_type: SocketIoPacketType::Connect,
+            nsp: "/".to_string(),
+            id: None,
+            data: None,
+        };
+        let encoded = options.codec().encode_to_string(&packet).unwrap();
+        options.codec().decode(&encoded).unwrap();
+
+        let rendered = options.metrics.as_ref().unwrap().render();
+        for line in [
+            "socketio_packets_total{direction=\"out\",type=\"connect\"} 1",
+            "socketio_packets_total{direction=\"in\",type=\"connect\"} 1",
+        ]
+        .iter()
+        {
+            assert!(rendered.lines().any(|l| l == *line), "{} is missing", line);
+        }
+    }
+}
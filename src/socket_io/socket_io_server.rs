@@ -1,32 +1,856 @@
+use crate::engine_io_parser::ProtocolVersion;
+use crate::engine_io_server::error::{EngineIoError, EngineIoErrorCode};
+use crate::engine_io_server::transport::{Transport, TransportType};
+use crate::socket_io::cors;
+use crate::socket_io::server_options::ServerOptions;
 use crate::socket_io_adaptor::namespace_manager::NamespaceManager;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use crate::socket_io_connection::transport_message::AttachTransport;
+use crate::socket_io_polling::socket_io_polling_impl::{PollingFormat, SocketIoPolling};
 use crate::socket_io_websocket::app_state::AppState;
 use crate::socket_io_websocket::socket_io_websocket_impl::SocketIoWebsocket;
+use actix::Addr;
+use actix_web::dev::Server;
+use actix_web::http::Method;
 use actix_web::web::Data;
-use actix_web::{web, Error, HttpRequest, HttpResponse, Scope};
-use actix_web_actors::ws;
+use actix_web::{web, Error, HttpRequest, HttpResponse, Resource, ResponseError, Scope};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// The query parameters of the Engine.IO requests
+#[derive(Debug, Deserialize)]
+struct EngineIoQuery {
+    transport: Option<String>,
+    sid: Option<String>,
+    #[serde(rename = "EIO")]
+    eio: Option<String>,
+    /// The index of the JSONP callback
+    j: Option<String>,
+}
+
+/// Adds the CORS headers to a response of the engine endpoint, when enabled
+fn with_cors(data: &AppState, r: &HttpRequest, mut res: HttpResponse) -> HttpResponse {
+    if let Some(options) = data.options.cors.as_ref() {
+        cors::add_cors_headers(options, r, &mut res);
+    }
+    res
+}
+
+impl EngineIoQuery {
+    /// Get the transport of the request, if the server allows it
+    fn get_transport(&self, options: &ServerOptions) -> Option<TransportType> {
+        let transport = match options.get_transport(self.transport.as_deref()?)? {
+            TransportType::Polling if self.j.is_some() => TransportType::JsonpPolling,
+            transport => transport,
+        };
+        if options.is_transport_allowed(transport) {
+            Some(transport)
+        } else {
+            None
+        }
+    }
+
+    /// Get the protocol version of the request, if the server accepts it
+    fn get_protocol(&self, options: &ServerOptions) -> Option<ProtocolVersion> {
+        match ProtocolVersion::from_query(self.eio.as_deref()?)? {
+            ProtocolVersion::V3 if !options.allow_eio3 => None,
+            protocol => Some(protocol),
+        }
+    }
+
+    /// Get how the payloads of a polling request are carried
+    fn get_polling_format(&self) -> Result<PollingFormat, EngineIoError> {
+        match self.j.as_deref() {
+            Some(j) => j
+                .parse()
+                .map(PollingFormat::Jsonp)
+                .map_err(|_| EngineIoErrorCode::BadRequest.into()),
+            None => Ok(PollingFormat::Xhr),
+        }
+    }
+}
+
+/// A request of the engine endpoint that passed the checks
+struct VerifiedRequest {
+    transport: TransportType,
+    protocol: ProtocolVersion,
+    format: PollingFormat,
+    /// The connection of the session, `None` for a handshake
+    connection: Option<Addr<SocketIoConnection>>,
+}
+
+/// Checks a request of the engine endpoint, counting the rejected ones when
+/// the metrics are enabled
+async fn verify(
+    data: &AppState,
+    query: &EngineIoQuery,
+    r: &HttpRequest,
+) -> Result<VerifiedRequest, EngineIoError> {
+    let verified = check(data, query, r).await;
+    if let (Err(err), Some(metrics)) = (verified.as_ref(), data.options.metrics.as_ref()) {
+        metrics.on_handshake_failure(err.code);
+    }
+    verified
+}
+
+/// Checks a request of the engine endpoint. Handshakes are refused once the
+/// server is shutting down, and must be accepted by the `allow_request` hook,
+/// if any.
+async fn check(
+    data: &AppState,
+    query: &EngineIoQuery,
+    r: &HttpRequest,
+) -> Result<VerifiedRequest, EngineIoError> {
+    let transport = query
+        .get_transport(&data.options)
+        .ok_or(EngineIoErrorCode::UnknownTransport)?;
+    let protocol = query
+        .get_protocol(&data.options)
+        .ok_or(EngineIoErrorCode::UnsupportedProtocolVersion)?;
+    let format = query.get_polling_format()?;
+
+    if let Some(sid) = query.sid.as_ref() {
+        let connection = data
+            .get_connection(sid)
+            .ok_or(EngineIoErrorCode::UnknownSid)?;
+        // the session upgrades to the transport of the request
+        let polling = matches!(
+            transport,
+            TransportType::Polling | TransportType::JsonpPolling
+        );
+        if !polling && !data.options.allow_upgrades {
+            return Err(EngineIoErrorCode::BadRequest.into());
+        }
+        return Ok(VerifiedRequest {
+            transport,
+            protocol,
+            format,
+            connection: Some(connection),
+        });
+    }
+
+    if r.method() != Method::GET {
+        return Err(EngineIoErrorCode::BadHandshakeMethod.into());
+    }
+    if !data.is_ready() {
+        return Err(EngineIoError::with_message(
+            EngineIoErrorCode::Forbidden,
+            "Server is shutting down",
+        ));
+    }
+    if let Some(allow_request) = data.options.allow_request.as_ref() {
+        allow_request.call(r.clone()).await?;
+    }
+    Ok(VerifiedRequest {
+        transport,
+        protocol,
+        format,
+        connection: None,
+    })
+}
 
 #[derive(Debug)]
 pub struct SocketIoServer;
 
 impl SocketIoServer {
+    /// Serves the Engine.IO endpoint at the `path` of the
+    /// [`ServerOptions`](../server_options/struct.ServerOptions.html),
+    /// `/socket.io` by default. Every request must carry the `EIO` and
+    /// `transport` query parameters, as the Socket.IO clients do.
+    pub fn get_scope(app_data: Data<AppState>) -> Scope {
+        let path = app_data.options.path.clone();
+        Self::get_scope_at(&path, app_data)
+    }
+
+    /// Serves the Engine.IO endpoint at `path`.
+    ///
+    /// The endpoint used to be a bare websocket, served at the given path
+    /// (`/ws` in the bundled binary). It now speaks Engine.IO over polling and
+    /// websocket, and requests without the `EIO` and `transport` query
+    /// parameters are refused.
+    #[deprecated(
+        note = "set the path with `ServerOptions::builder().path(..)` and use `get_scope`"
+    )]
     pub fn get_socket_io_scope(path: &str, app_data: Data<AppState>) -> Scope {
+        Self::get_scope_at(path, app_data)
+    }
 
-        async fn ws_index(
+    fn get_scope_at(path: &str, app_data: Data<AppState>) -> Scope {
+        async fn handle_get(
             data: web::Data<AppState>,
+            query: web::Query<EngineIoQuery>,
             r: HttpRequest,
             stream: web::Payload,
+        ) -> HttpResponse {
+            let res = get(&data, &query, &r, stream)
+                .await
+                .unwrap_or_else(HttpResponse::from_error);
+            with_cors(&data, &r, res)
+        }
+
+        async fn get(
+            data: &web::Data<AppState>,
+            query: &EngineIoQuery,
+            r: &HttpRequest,
+            stream: web::Payload,
         ) -> Result<HttpResponse, Error> {
-            ws::start(SocketIoWebsocket::new(data, "/"), &r, stream)
+            let verified = match verify(data, query, r).await {
+                Ok(verified) => verified,
+                Err(err) => return Ok(err.error_response()),
+            };
+
+            let (protocol, format) = (verified.protocol, verified.format);
+            let (mut res, transport, events) = match verified.transport {
+                TransportType::Polling | TransportType::JsonpPolling => {
+                    return Ok(match verified.connection {
+                        Some(connection) => {
+                            SocketIoPolling::poll(data, r, protocol, format, connection).await
+                        }
+                        None => SocketIoPolling::handshake(data.clone(), r, protocol, format).await,
+                    });
+                }
+                TransportType::Websocket => {
+                    let (res, transport, events) =
+                        SocketIoWebsocket::start(&data.options, protocol, r, stream)?;
+                    let transport: Box<dyn Transport + Send> = Box::new(transport);
+                    (res, transport, events)
+                }
+                TransportType::Custom(name) => {
+                    // the transport was found by its name to verify the request
+                    let custom = data.options.get_custom_transport(name).unwrap();
+                    custom.start(r, stream, protocol)?
+                }
+            };
+            match verified.connection {
+                Some(connection) => connection.do_send(AttachTransport { transport, events }),
+                None => {
+                    let (sid, _) = SocketIoConnection::open(
+                        data.clone(),
+                        transport,
+                        events,
+                        protocol,
+                        r.peer_addr(),
+                    );
+                    data.options.set_session_cookie(&mut res, &sid);
+                }
+            }
+            Ok(res)
+        }
+
+        async fn handle_post(
+            data: web::Data<AppState>,
+            query: web::Query<EngineIoQuery>,
+            r: HttpRequest,
+            payload: web::Payload,
+        ) -> HttpResponse {
+            let res = match verify(&data, &query, &r).await {
+                Ok(VerifiedRequest {
+                    transport: TransportType::Polling,
+                    protocol,
+                    format,
+                    connection: Some(connection),
+                })
+                | Ok(VerifiedRequest {
+                    transport: TransportType::JsonpPolling,
+                    protocol,
+                    format,
+                    connection: Some(connection),
+                }) => SocketIoPolling::post(&data, protocol, format, connection, payload).await,
+                Ok(_) => EngineIoError::new(EngineIoErrorCode::BadRequest).error_response(),
+                Err(err) => err.error_response(),
+            };
+            with_cors(&data, &r, res)
+        }
+
+        /// Answers preflight requests, or 404 when CORS is disabled
+        async fn handle_preflight(data: web::Data<AppState>, r: HttpRequest) -> HttpResponse {
+            match data.options.cors.as_ref() {
+                Some(options) => cors::preflight_response(options, &r),
+                None => HttpResponse::NotFound().finish(),
+            }
         }
 
-        web::scope(path)
-            .app_data(app_data.clone())
-            .service(web::resource("").route(web::get().to(ws_index)))
+        web::scope(path).app_data(app_data).service(
+            web::resource(vec!["", "/"])
+                .route(web::get().to(handle_get))
+                .route(web::post().to(handle_post))
+                .route(web::method(Method::OPTIONS).to(handle_preflight)),
+        )
+    }
+
+    /// Serves the metrics of the server at `/metrics`, in the Prometheus text
+    /// format. Answers 404 unless they are enabled in the
+    /// [`ServerOptions`](../server_options/struct.ServerOptions.html).
+    /// ```rust
+    /// # use actix_web::App;
+    /// # use socket_io_rust::socket_io::server_options::ServerOptions;
+    /// # use socket_io_rust::socket_io::socket_io_server::SocketIoServer;
+    /// let app_data = SocketIoServer::get_app_data(ServerOptions::builder().metrics(true).build());
+    /// let app = App::new()
+    ///     .service(SocketIoServer::get_scope(app_data.clone()))
+    ///     .service(SocketIoServer::get_metrics_resource(app_data));
+    /// ```
+    pub fn get_metrics_resource(app_data: Data<AppState>) -> Resource {
+        async fn handle_metrics(data: web::Data<AppState>) -> HttpResponse {
+            match data.options.metrics.as_ref() {
+                Some(metrics) => HttpResponse::Ok()
+                    .content_type("text/plain; version=0.0.4; charset=utf-8")
+                    .body(metrics.render()),
+                None => HttpResponse::NotFound().finish(),
+            }
+        }
+
+        web::resource("/metrics")
+            .app_data(app_data)
+            .route(web::get().to(handle_metrics))
+    }
+
+    /// Serves the liveness probe at `/healthz`, answering 200 as long as the
+    /// server handles requests
+    pub fn get_healthz_resource(app_data: Data<AppState>) -> Resource {
+        async fn handle_healthz() -> HttpResponse {
+            HttpResponse::Ok().content_type("text/plain").body("ok")
+        }
+
+        web::resource("/healthz")
+            .app_data(app_data)
+            .route(web::get().to(handle_healthz))
+    }
+
+    /// Serves the readiness probe at `/readyz`, answering 503 once the server
+    /// is shutting down so that no new clients are routed to it
+    pub fn get_readyz_resource(app_data: Data<AppState>) -> Resource {
+        async fn handle_readyz(data: web::Data<AppState>) -> HttpResponse {
+            if data.is_ready() {
+                HttpResponse::Ok().content_type("text/plain").body("ready")
+            } else {
+                HttpResponse::ServiceUnavailable()
+                    .content_type("text/plain")
+                    .body("shutting down")
+            }
+        }
+
+        web::resource("/readyz")
+            .app_data(app_data)
+            .route(web::get().to(handle_readyz))
+    }
+
+    /// Shuts the Socket.IO server down as
+    /// [`AppState::shutdown`](../../socket_io_websocket/app_state/struct.AppState.html#method.shutdown)
+    /// does, then stops the HTTP server, letting the requests being handled
+    /// finish.
+    /// ```rust,no_run
+    /// # use actix_web::{App, HttpServer};
+    /// # use socket_io_rust::socket_io::server_options::ServerOptions;
+    /// # use socket_io_rust::socket_io::socket_io_server::SocketIoServer;
+    /// # use std::time::Duration;
+    /// # async fn run() -> std::io::Result<()> {
+    /// let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+    /// let data = app_data.clone();
+    /// let server = HttpServer::new(move || {
+    ///     App::new().service(SocketIoServer::get_scope(data.clone()))
+    /// })
+    /// .disable_signals()
+    /// .bind("127.0.0.1:8080")?
+    /// .run();
+    /// let handle = server.clone();
+    /// actix_rt::spawn(async move {
+    ///     let _ = actix_rt::signal::ctrl_c().await;
+    ///     SocketIoServer::shutdown(app_data, handle, Duration::from_secs(10)).await;
+    /// });
+    /// server.await
+    /// # }
+    /// ```
+    pub async fn shutdown(app_data: Data<AppState>, server: Server, deadline: Duration) {
+        app_data.shutdown(deadline).await;
+        server.stop(true).await;
     }
 
-    pub fn get_app_data() -> Data<AppState> {
+    pub fn get_app_data(options: ServerOptions) -> Data<AppState> {
         let nsm = NamespaceManager::new();
-        let app_state = AppState::new(nsm);
+        let app_state = AppState::with_options(nsm, options);
         web::Data::new(app_state)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_parser::packet::{Packet, PacketType};
+    use crate::engine_io_server::error::{EngineIoError, EngineIoErrorCode};
+    use crate::engine_io_server::transport::{TransportEvent, TransportType};
+    use crate::socket_io::server_options::{CookieOptions, CorsOptions, ServerOptions};
+    use crate::socket_io::socket_io_server::SocketIoServer;
+    use crate::socket_io_testing::mock_transport::MockTransport;
+    use actix_web::http::{header, Method};
+    use actix_web::{test, App, HttpResponse};
+    use futures::StreamExt;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[actix_rt::test]
+    async fn custom_transport_test() {
+        let peers = Arc::new(Mutex::new(Vec::new()));
+        let started = peers.clone();
+        let options = ServerOptions::builder()
+            .custom_transport("mock", move |_, _, _| {
+                let (transport, events, peer) = MockTransport::pair();
+                started.lock().unwrap().push(peer);
+                Ok((HttpResponse::Ok().finish(), Box::new(transport), events))
+            })
+            .build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+
+        // a session starts on the transport
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=mock")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 200);
+        let mut peer = peers.lock().unwrap().pop().unwrap();
+        let open = peer.packets.next().await.unwrap();
+        assert_eq!(open.packet_type, PacketType::Open);
+        let handshake: Value = serde_json::from_slice(&open.data).unwrap();
+        assert_eq!(handshake["upgrades"], json!([]));
+
+        // or a polling session upgrades to it
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let handshake: Value = serde_json::from_slice(&body[1..]).unwrap();
+        assert_eq!(handshake["upgrades"], json!(["websocket", "mock"]));
+        let req = test::TestRequest::get()
+            .uri(&format!(
+                "/socket.io/?EIO=4&transport=mock&sid={}",
+                handshake["sid"].as_str().unwrap()
+            ))
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 200);
+        let mut peer = peers.lock().unwrap().pop().unwrap();
+        let probe = Packet::new(PacketType::Ping, b"probe".to_vec());
+        let _ = peer.events.unbounded_send(TransportEvent::Packet(probe));
+        assert_eq!(
+            peer.packets.next().await.unwrap(),
+            Packet::new(PacketType::Pong, b"probe".to_vec())
+        );
+
+        // unless the transport is left out
+        let options = ServerOptions::builder()
+            .custom_transport("mock", |_, _, _| unreachable!())
+            .transports(vec![TransportType::Polling])
+            .build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=mock")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 400);
+    }
+
+    #[actix_rt::test]
+    async fn verify_test() {
+        let options = ServerOptions::builder()
+            .allow_request(|req| async move {
+                match req.headers().get("x-token") {
+                    Some(_) => Ok(()),
+                    None => Err(EngineIoError::with_message(
+                        EngineIoErrorCode::Forbidden,
+                        "missing token",
+                    )),
+                }
+            })
+            .build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 403);
+        let body: Value = serde_json::from_slice(&test::read_body(res).await).unwrap();
+        assert_eq!(body, json!({"code": 4, "message": "missing token"}));
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .header("x-token", "secret")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 200);
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=flash")
+            .to_request();
+        let body: Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(body, json!({"code": 0, "message": "Transport unknown"}));
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling&sid=unknown")
+            .to_request();
+        let body: Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(body, json!({"code": 1, "message": "Session ID unknown"}));
+
+        let req = test::TestRequest::post()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let body: Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(body, json!({"code": 2, "message": "Bad handshake method"}));
+    }
+
+    #[actix_rt::test]
+    #[allow(deprecated)]
+    async fn deprecated_scope_test() {
+        let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+        let mut app = test::init_service(
+            App::new().service(SocketIoServer::get_socket_io_scope("/ws", app_data)),
+        )
+        .await;
+
+        let req = test::TestRequest::get()
+            .uri("/ws/?EIO=4&transport=polling")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 200);
+
+        let req = test::TestRequest::get().uri("/ws").to_request();
+        let body: Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(body, json!({"code": 0, "message": "Transport unknown"}));
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[actix_rt::test]
+    async fn cors_test() {
+        let options = ServerOptions::builder()
+            .cors(CorsOptions::default())
+            .build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+
+        // a websocket request without the upgrade headers fails its handshake
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=websocket")
+            .header(header::ORIGIN, "http://example.com")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 400);
+        assert_eq!(
+            res.headers()
+                .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+                .unwrap(),
+            "*"
+        );
+
+        let req = test::TestRequest::with_uri("/socket.io/")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "http://example.com")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 204);
+
+        let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+        let req = test::TestRequest::with_uri("/socket.io/")
+            .method(Method::OPTIONS)
+            .header(header::ORIGIN, "http://example.com")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 404);
+    }
+
+    #[actix_rt::test]
+    async fn max_http_buffer_size_test() {
+        let options = ServerOptions::builder().max_http_buffer_size(16).build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let open: Value = serde_json::from_slice(&body[1..]).unwrap();
+        let uri = format!(
+            "/socket.io/?EIO=4&transport=polling&sid={}",
+            open["sid"].as_str().unwrap()
+        );
+
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .set_payload("4a message larger than the limit")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 413);
+
+        // the connection was closed
+        actix_rt::time::delay_for(Duration::from_millis(50)).await;
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let body: Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(body, json!({"code": 1, "message": "Session ID unknown"}));
+    }
+
+    #[actix_rt::test]
+    async fn cookie_test() {
+        let options = ServerOptions::builder()
+            .cookie(CookieOptions::default())
+            .build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        let cookie = res.response().cookies().next().unwrap().into_owned();
+        let body = test::read_body(res).await;
+        let open: Value = serde_json::from_slice(&body[1..]).unwrap();
+        assert_eq!(cookie.name(), "io");
+        assert_eq!(cookie.value(), open["sid"].as_str().unwrap());
+    }
+
+    #[actix_rt::test]
+    async fn eio3_test() {
+        let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=3&transport=polling")
+            .to_request();
+        let body: Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(
+            body,
+            json!({"code": 5, "message": "Unsupported protocol version"})
+        );
+
+        let options = ServerOptions::builder().allow_eio3(true).build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=3&transport=polling")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        let (length, packet) = body.split_at(body.find(':').unwrap());
+        assert_eq!(length.parse::<usize>().unwrap(), packet.len() - 1);
+        let open: Value = serde_json::from_str(&packet[2..]).unwrap();
+        assert!(open.get("maxPayload").is_none());
+        let uri = format!(
+            "/socket.io/?EIO=3&transport=polling&sid={}",
+            open["sid"].as_str().unwrap()
+        );
+
+        // the server answers the pings of the client, after joining it to the
+        // root namespace
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .set_payload("1:2")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 200);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(test::read_response(&mut app, req).await, "2:401:3");
+    }
+
+    #[actix_rt::test]
+    async fn implicit_connect_test() {
+        let options = ServerOptions::builder().allow_eio3(true).build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=3&transport=polling")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        let open: Value = serde_json::from_str(&body[body.find('{').unwrap()..]).unwrap();
+        let uri = format!(
+            "/socket.io/?EIO=3&transport=polling&sid={}",
+            open["sid"].as_str().unwrap()
+        );
+
+        // socket.io-client 2.x joins the root namespace without asking
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(test::read_response(&mut app, req).await, "2:40");
+
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .set_payload("8:40/admin")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 200);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(
+            test::read_response(&mut app, req).await,
+            "28:44/admin,\"Invalid namespace\""
+        );
+    }
+
+    #[actix_rt::test]
+    async fn jsonp_test() {
+        let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling&j=1")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        assert!(body.starts_with("___eio[1](\"0{") && body.ends_with("\");"));
+        let payload: String = serde_json::from_str(&body[10..body.len() - 2]).unwrap();
+        let open: Value = serde_json::from_str(&payload[1..]).unwrap();
+        let sid = open["sid"].as_str().unwrap();
+        let uri = format!("/socket.io/?EIO=4&transport=polling&j=1&sid={}", sid);
+
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .header("content-type", "application/x-www-form-urlencoded")
+            .set_payload("d=2")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 200);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        assert_eq!(
+            test::read_response(&mut app, req).await,
+            "___eio[1](\"3\");"
+        );
+
+        let options = ServerOptions::builder()
+            .transports(vec![TransportType::Polling, TransportType::Websocket])
+            .build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_scope(app_data))).await;
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling&j=1")
+            .to_request();
+        let body: Value = test::read_response_json(&mut app, req).await;
+        assert_eq!(body, json!({"code": 0, "message": "Transport unknown"}));
+    }
+
+    #[actix_rt::test]
+    async fn shutdown_test() {
+        let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+        let mut app = test::init_service(
+            App::new()
+                .service(SocketIoServer::get_scope(app_data.clone()))
+                .service(SocketIoServer::get_healthz_resource(app_data.clone()))
+                .service(SocketIoServer::get_readyz_resource(app_data.clone())),
+        )
+        .await;
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        assert_eq!(test::read_response(&mut app, req).await, "ready");
+
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let open: Value = serde_json::from_slice(&body[1..]).unwrap();
+        let uri = format!(
+            "/socket.io/?EIO=4&transport=polling&sid={}",
+            open["sid"].as_str().unwrap()
+        );
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .set_payload("40")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 200);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        test::read_response(&mut app, req).await;
+
+        // the polling client gets its DISCONNECT packet with its next request
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        let (drained, body) = futures::join!(
+            app_data.shutdown(Duration::from_secs(5)),
+            test::read_response(&mut app, req)
+        );
+        assert!(drained);
+        assert_eq!(body, "41");
+
+        let req = test::TestRequest::get().uri("/readyz").to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 503);
+        let req = test::TestRequest::get().uri("/healthz").to_request();
+        assert_eq!(test::read_response(&mut app, req).await, "ok");
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(res.status(), 403);
+        let body: Value = serde_json::from_slice(&test::read_body(res).await).unwrap();
+        assert_eq!(
+            body,
+            json!({"code": 4, "message": "Server is shutting down"})
+        );
+    }
+
+    #[actix_rt::test]
+    async fn metrics_test() {
+        let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+        let mut app =
+            test::init_service(App::new().service(SocketIoServer::get_metrics_resource(app_data)))
+                .await;
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 404);
+
+        let options = ServerOptions::builder().metrics(true).build();
+        let app_data = SocketIoServer::get_app_data(options);
+        let mut app = test::init_service(
+            App::new()
+                .service(SocketIoServer::get_scope(app_data.clone()))
+                .service(SocketIoServer::get_metrics_resource(app_data)),
+        )
+        .await;
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling&sid=unknown")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 400);
+        let req = test::TestRequest::get()
+            .uri("/socket.io/?EIO=4&transport=polling")
+            .to_request();
+        let body = test::read_response(&mut app, req).await;
+        let open: Value = serde_json::from_slice(&body[1..]).unwrap();
+        let uri = format!(
+            "/socket.io/?EIO=4&transport=polling&sid={}",
+            open["sid"].as_str().unwrap()
+        );
+        let req = test::TestRequest::post()
+            .uri(&uri)
+            .set_payload("40")
+            .to_request();
+        assert_eq!(test::call_service(&mut app, req).await.status(), 200);
+        let req = test::TestRequest::get().uri(&uri).to_request();
+        test::read_response(&mut app, req).await;
+
+        let req = test::TestRequest::get().uri("/metrics").to_request();
+        let res = test::call_service(&mut app, req).await;
+        assert_eq!(
+            res.headers().get("content-type").unwrap(),
+            "text/plain; version=0.0.4; charset=utf-8"
+        );
+        let body = test::read_body(res).await;
+        let body = std::str::from_utf8(&body).unwrap();
+        for line in [
+            "socketio_connected_sockets{namespace=\"/\"} 1",
+            "socketio_rooms{namespace=\"/\"} 1",
+            "socketio_engine_packets_total{direction=\"in\",type=\"message\"} 1",
+            "socketio_engine_packets_total{direction=\"out\",type=\"open\"} 1",
+            "socketio_engine_packets_total{direction=\"out\",type=\"message\"} 1",
+            "socketio_packets_total{direction=\"in\",type=\"connect\"} 1",
+            "socketio_packets_total{direction=\"out\",type=\"connect\"} 1",
+            "socketio_handshake_failures_total{code=\"1\"} 1",
+        ]
+        .iter()
+        {
+            assert!(body.lines().any(|l| l == *line), "{} is missing", line);
+        }
+    }
+}
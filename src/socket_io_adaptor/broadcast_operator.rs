@@ -0,0 +1,80 @@
+use crate::socket_io_adaptor::chat_room_manager::BroadcastOptions;
+use crate::socket_io_adaptor::namespace::Namespace;
+
+/// Applies an operation to every socket of a namespace that matches a set of
+/// rooms, e.g. `nsp.in_("game-42").sockets_join("spectators")`.
+///
+/// Created by [`Namespace::to`], [`Namespace::in_`] and [`Namespace::except`].
+///
+/// Only the sockets of the local adapter are reached: there is no adapter
+/// shared between servers yet, so the sockets connected to other servers are
+/// left out.
+///
+/// [`Namespace::to`]: ../namespace/struct.Namespace.html#method.to
+/// [`Namespace::in_`]: ../namespace/struct.Namespace.html#method.in_
+/// [`Namespace::except`]: ../namespace/struct.Namespace.html#method.except
+#[derive(Debug)]
+pub struct BroadcastOperator<'a> {
+    namespace: &'a mut Namespace,
+    opts: BroadcastOptions,
+}
+
+impl<'a> BroadcastOperator<'a> {
+    pub fn new(namespace: &'a mut Namespace) -> Self {
+        Self {
+            namespace,
+            opts: BroadcastOptions::default(),
+        }
+    }
+
+    /// Targets the sockets in the given room as well
+    pub fn to(mut self, room_id: &str) -> Self {
+        self.opts.rooms.insert(room_id.to_string());
+        self
+    }
+
+    /// Alias of [`to`](#method.to)
+    pub fn in_(self, room_id: &str) -> Self {
+        self.to(room_id)
+    }
+
+    /// Excludes the sockets in the given room
+    pub fn except(mut self, room_id: &str) -> Self {
+        self.opts.except.insert(room_id.to_string());
+        self
+    }
+
+    /// Sets whether the emitted packets may be compressed, when compression
+    /// is enabled. They may by default.
+    pub fn compress(mut self, compress: bool) -> Self {
+        self.opts.flags.compress = compress;
+        self
+    }
+
+    /// Emits an event to the matching sockets
+    pub fn emit(self, event_name: &str, message: &str) {
+        self.namespace
+            .emit_with_options(&self.opts, event_name, message);
+    }
+
+    /// Gets the ids of the matching sockets
+    pub fn fetch_sockets(self) -> Vec<String> {
+        self.namespace.get_matching_sids(&self.opts)
+    }
+
+    /// Makes the matching sockets join the given room
+    pub fn sockets_join(self, room_id: &str) {
+        self.namespace.add_sockets(&self.opts, vec![room_id]);
+    }
+
+    /// Makes the matching sockets leave the given room
+    pub fn sockets_leave(self, room_id: &str) {
+        self.namespace.del_sockets(&self.opts, vec![room_id]);
+    }
+
+    /// Disconnects the matching sockets from the namespace. When `close` is
+    /// true the underlying connection is closed as well.
+    pub fn disconnect_sockets(self, close: bool) {
+        self.namespace.disconnect_sockets(&self.opts, close);
+    }
+}
@@ -6,12 +6,8 @@ struct Room {
 }
 
 impl Room {
-    pub fn new() -> Self {
-        Self::default()
-    }
-
-    pub fn add(&mut self, sid: &str) {
-        self.sockets.insert(sid.to_string());
+    pub fn add(&mut self, sid: &str) -> bool {
+        self.sockets.insert(sid.to_string())
     }
 
     pub fn delete(&mut self, sid: &str) -> bool {
@@ -23,21 +19,90 @@ impl Room {
     }
 }
 
+/// A change in the lifecycle of a room.
+///
+/// A room is created right before its first socket joins it and deleted right
+/// after its last socket leaves it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoomEvent {
+    CreateRoom { room_id: String },
+    DeleteRoom { room_id: String },
+    JoinRoom { room_id: String, sid: String },
+    LeaveRoom { room_id: String, sid: String },
+}
+
+/// Selects the sockets a broadcast or bulk operation applies to.
+///
+/// An empty `rooms` set selects every socket of the adapter.
+#[derive(Default, Debug, Clone)]
+pub struct BroadcastOptions {
+    pub rooms: HashSet<String>,
+    pub except: HashSet<String>,
+    pub flags: BroadcastFlags,
+}
+
+/// How the packets of a broadcast are sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BroadcastFlags {
+    /// Whether the packets may be compressed, when compression is enabled
+    pub compress: bool,
+}
+
+impl Default for BroadcastFlags {
+    fn default() -> Self {
+        Self { compress: true }
+    }
+}
+
 #[derive(Default, Debug)]
 pub struct ChatRoomManager {
     rooms: HashMap<String, Room>,
     sids: HashMap<String, HashSet<String>>,
+    /// Room events that have not been taken by `take_events` yet
+    events: Vec<RoomEvent>,
+    /// Whether the room events are recorded, which they are not until a
+    /// consumer takes them
+    record_events: bool,
 }
 
 impl ChatRoomManager {
     pub fn new() -> Self {
         Self::default()
     }
-    fn remove_id_from_room(rooms: &mut HashMap<String, Room>, id: &str, room_id: &str) {
+
+    /// Records the room events from now on, for `take_events` to take them.
+    /// The consumer must take them regularly, as they are kept until then.
+    pub fn enable_events(&mut self) {
+        self.record_events = true;
+    }
+
+    fn push_event(events: &mut Vec<RoomEvent>, record_events: bool, event: RoomEvent) {
+        if record_events {
+            events.push(event);
+        }
+    }
+
+    fn remove_id_from_room(
+        rooms: &mut HashMap<String, Room>,
+        events: &mut Vec<RoomEvent>,
+        record_events: bool,
+        id: &str,
+        room_id: &str,
+    ) {
         if let Some(room) = rooms.get_mut(room_id) {
-            room.delete(id);
+            if room.delete(id) {
+                let event = RoomEvent::LeaveRoom {
+                    room_id: room_id.to_string(),
+                    sid: id.to_string(),
+                };
+                Self::push_event(events, record_events, event);
+            }
             if room.length() == 0 {
                 rooms.remove(room_id);
+                let event = RoomEvent::DeleteRoom {
+                    room_id: room_id.to_string(),
+                };
+                Self::push_event(events, record_events, event);
             }
         }
     }
@@ -48,14 +113,24 @@ impl ChatRoomManager {
 
     pub fn add_all(&mut self, id: &str, room_ids: Vec<&str>) {
         for room_id in room_ids {
-            let sid = self.sids.entry(id.to_string()).or_insert_with(HashSet::new);
+            let sid = self.sids.entry(id.to_string()).or_default();
             sid.insert(room_id.to_string());
 
-            let room = self
-                .rooms
-                .entry(room_id.to_string())
-                .or_insert_with(Room::new);
-            room.add(id);
+            if !self.rooms.contains_key(room_id) {
+                self.rooms.insert(room_id.to_string(), Room::default());
+                let event = RoomEvent::CreateRoom {
+                    room_id: room_id.to_string(),
+                };
+                Self::push_event(&mut self.events, self.record_events, event);
+            }
+            let room = self.rooms.get_mut(room_id).unwrap();
+            if room.add(id) {
+                let event = RoomEvent::JoinRoom {
+                    room_id: room_id.to_string(),
+                    sid: id.to_string(),
+                };
+                Self::push_event(&mut self.events, self.record_events, event);
+            }
         }
     }
 
@@ -64,19 +139,37 @@ impl ChatRoomManager {
             room_map.remove(room_id);
         }
 
-        ChatRoomManager::remove_id_from_room(&mut self.rooms, id, room_id);
+        ChatRoomManager::remove_id_from_room(
+            &mut self.rooms,
+            &mut self.events,
+            self.record_events,
+            id,
+            room_id,
+        );
     }
 
     pub fn delete_all(&mut self, id: &str) {
         if let Some(rooms) = self.sids.get(id) {
             for room_id in rooms {
-                ChatRoomManager::remove_id_from_room(&mut self.rooms, id, room_id);
+                ChatRoomManager::remove_id_from_room(
+                    &mut self.rooms,
+                    &mut self.events,
+                    self.record_events,
+                    id,
+                    room_id,
+                );
             }
         }
 
         self.sids.remove(id);
     }
 
+    /// Take the room events recorded since the last call, in the order they
+    /// happened. None are recorded until `enable_events` is called.
+    pub fn take_events(&mut self) -> Vec<RoomEvent> {
+        std::mem::take(&mut self.events)
+    }
+
     /// Get a set of socket id from the given rooms
     pub fn get_all_sids_from_rooms(&self, room_ids: Vec<&str>) -> Vec<String> {
         let mut sid_set = HashSet::new();
@@ -91,6 +184,39 @@ impl ChatRoomManager {
         sid_set.into_iter().collect()
     }
 
+    /// Get the socket ids selected by the given options
+    pub fn get_matching_sids(&self, opts: &BroadcastOptions) -> Vec<String> {
+        let mut sids = if opts.rooms.is_empty() {
+            self.get_all_sids()
+        } else {
+            self.get_all_sids_from_rooms(opts.rooms.iter().map(|s| s.as_str()).collect())
+        };
+        if !opts.except.is_empty() {
+            let excluded: HashSet<String> = self
+                .get_all_sids_from_rooms(opts.except.iter().map(|s| s.as_str()).collect())
+                .into_iter()
+                .collect();
+            sids.retain(|sid| !excluded.contains(sid));
+        }
+        sids
+    }
+
+    /// Make the matching sockets join the given rooms
+    pub fn add_sockets(&mut self, opts: &BroadcastOptions, room_ids: Vec<&str>) {
+        for sid in self.get_matching_sids(opts) {
+            self.add_all(&sid, room_ids.clone());
+        }
+    }
+
+    /// Make the matching sockets leave the given rooms
+    pub fn del_sockets(&mut self, opts: &BroadcastOptions, room_ids: Vec<&str>) {
+        for sid in self.get_matching_sids(opts) {
+            for &room_id in room_ids.iter() {
+                self.delete(&sid, room_id);
+            }
+        }
+    }
+
     /// Get a set of all socket in this adapter
     pub fn get_all_sids(&self) -> Vec<String> {
         // todo check socket connected
@@ -113,7 +239,9 @@ impl ChatRoomManager {
 
 #[cfg(test)]
 mod socket_io_adaptor_tests {
-    use crate::socket_io_adaptor::chat_room_manager::ChatRoomManager;
+    use crate::socket_io_adaptor::chat_room_manager::{
+        BroadcastOptions, ChatRoomManager, RoomEvent,
+    };
 
     fn set_up() -> ChatRoomManager {
         let mut adaptor = ChatRoomManager::new();
@@ -210,4 +338,85 @@ mod socket_io_adaptor_tests {
             ]
         );
     }
+
+    #[test]
+    pub fn test_get_matching_sids() {
+        let adaptor = set_up();
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.insert(String::from("room1"));
+        opts.rooms.insert(String::from("room3"));
+        opts.except.insert(String::from("room2"));
+
+        let mut sids = adaptor.get_matching_sids(&opts);
+        sids.sort();
+        assert_eq!(sids, vec![String::from("sid2"), String::from("sid3")]);
+    }
+
+    #[test]
+    pub fn test_add_sockets() {
+        let mut adaptor = set_up();
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.insert(String::from("room3"));
+        adaptor.add_sockets(&opts, vec!["room4"]);
+
+        let mut sids = adaptor.get_all_sids_from_rooms(vec!["room4"]);
+        sids.sort();
+        assert_eq!(sids, vec![String::from("sid2"), String::from("sid3")]);
+        assert!(adaptor.sids.get("sid3").unwrap().contains("room4"));
+    }
+
+    #[test]
+    pub fn test_del_sockets() {
+        let mut adaptor = set_up();
+        adaptor.del_sockets(&BroadcastOptions::default(), vec!["room1"]);
+
+        assert!(!adaptor.rooms.contains_key("room1"));
+        assert!(!adaptor.sids.get("sid1").unwrap().contains("room1"));
+        assert!(adaptor.sids.get("sid1").unwrap().contains("room2"));
+    }
+
+    #[test]
+    pub fn test_room_events() {
+        let mut adaptor = ChatRoomManager::new();
+        // nothing is recorded without a consumer
+        adaptor.add("sid0", "room0");
+        assert!(adaptor.take_events().is_empty());
+
+        adaptor.enable_events();
+        adaptor.add("sid1", "sid1");
+        adaptor.add("sid1", "room1");
+        adaptor.add("sid1", "room1");
+        adaptor.add("sid2", "room1");
+        adaptor.delete("sid1", "room1");
+        adaptor.delete_all("sid2");
+
+        let join = |room_id: &str, sid: &str| RoomEvent::JoinRoom {
+            room_id: room_id.to_string(),
+            sid: sid.to_string(),
+        };
+        let leave = |room_id: &str, sid: &str| RoomEvent::LeaveRoom {
+            room_id: room_id.to_string(),
+            sid: sid.to_string(),
+        };
+        let create = |room_id: &str| RoomEvent::CreateRoom {
+            room_id: room_id.to_string(),
+        };
+        let delete = |room_id: &str| RoomEvent::DeleteRoom {
+            room_id: room_id.to_string(),
+        };
+        assert_eq!(
+            adaptor.take_events(),
+            vec![
+                create("sid1"),
+                join("sid1", "sid1"),
+                create("room1"),
+                join("room1", "sid1"),
+                join("room1", "sid2"),
+                leave("room1", "sid1"),
+                leave("room1", "sid2"),
+                delete("room1"),
+            ]
+        );
+        assert!(adaptor.take_events().is_empty());
+    }
 }
@@ -0,0 +1,84 @@
+use std::fmt;
+
+/// Why a socket left a namespace
+/// https://socket.io/docs/v4/server-socket-instance/#disconnect
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisconnectReason {
+    /// The connection was closed, by the client or the network
+    TransportClose,
+    /// The connection encountered an error
+    TransportError,
+    /// The client did not answer a ping in time
+    PingTimeout,
+    /// The server disconnected the socket from the namespace
+    ServerNamespaceDisconnect,
+    /// The server closed the connection while disconnecting another socket
+    /// of it, from another namespace
+    ForcedClose,
+    /// The client disconnected the socket from the namespace
+    ClientNamespaceDisconnect,
+    /// The client sent a packet that could not be decoded
+    ParseError,
+    /// The server is shutting down
+    ServerShuttingDown,
+}
+
+impl DisconnectReason {
+    /// Whether the socket may come back, in which case its state is kept when
+    /// the connection state recovery is enabled
+    pub fn is_recoverable(self) -> bool {
+        match self {
+            DisconnectReason::TransportClose
+            | DisconnectReason::TransportError
+            | DisconnectReason::PingTimeout => true,
+            DisconnectReason::ServerNamespaceDisconnect
+            | DisconnectReason::ForcedClose
+            | DisconnectReason::ClientNamespaceDisconnect
+            | DisconnectReason::ParseError
+            | DisconnectReason::ServerShuttingDown => false,
+        }
+    }
+}
+
+impl fmt::Display for DisconnectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            DisconnectReason::TransportClose => "transport close",
+            DisconnectReason::TransportError => "transport error",
+            DisconnectReason::PingTimeout => "ping timeout",
+            DisconnectReason::ServerNamespaceDisconnect => "server namespace disconnect",
+            DisconnectReason::ForcedClose => "forced close",
+            DisconnectReason::ClientNamespaceDisconnect => "client namespace disconnect",
+            DisconnectReason::ParseError => "parse error",
+            DisconnectReason::ServerShuttingDown => "server shutting down",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_adaptor::disconnect_reason::DisconnectReason;
+
+    #[test]
+    fn display_test() {
+        assert_eq!(DisconnectReason::PingTimeout.to_string(), "ping timeout");
+        assert_eq!(
+            DisconnectReason::ClientNamespaceDisconnect.to_string(),
+            "client namespace disconnect"
+        );
+        assert_eq!(
+            DisconnectReason::ServerShuttingDown.to_string(),
+            "server shutting down"
+        );
+        assert_eq!(DisconnectReason::ForcedClose.to_string(), "forced close");
+    }
+
+    #[test]
+    fn is_recoverable_test() {
+        assert!(DisconnectReason::TransportClose.is_recoverable());
+        assert!(!DisconnectReason::ServerNamespaceDisconnect.is_recoverable());
+        assert!(!DisconnectReason::ServerShuttingDown.is_recoverable());
+        assert!(!DisconnectReason::ForcedClose.is_recoverable());
+    }
+}
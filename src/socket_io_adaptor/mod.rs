@@ -1,4 +1,8 @@
+pub mod broadcast_operator;
 pub mod chat_room_manager;
+pub mod disconnect_reason;
 pub mod namespace;
 pub mod namespace_manager;
+pub mod session_store;
 pub mod socket_id_manager;
+pub mod socket_ref;
@@ -1,8 +1,23 @@
-use crate::socket_io_adaptor::chat_room_manager::ChatRoomManager;
+use crate::socket_io_adaptor::broadcast_operator::BroadcastOperator;
+use crate::socket_io_adaptor::chat_room_manager::{
+    BroadcastFlags, BroadcastOptions, ChatRoomManager, RoomEvent,
+};
+use crate::socket_io_adaptor::disconnect_reason::DisconnectReason;
+use crate::socket_io_adaptor::session_store::{
+    ConnectionStateRecoveryOptions, RecoveredSession, RecoveryError, SessionStore,
+};
 use crate::socket_io_adaptor::socket_id_manager::SocketIdManager;
-use crate::socket_io_websocket::socket_io_websocket_impl::SocketIoWebsocket;
-use crate::socket_io_websocket::socket_message::SocketMessage;
+use crate::socket_io_adaptor::socket_ref::SocketRef;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use crate::socket_io_connection::socket_message::{AckSender, DisconnectMessage, SocketMessage};
+use crate::socket_io_metrics::metrics::NamespaceMetrics;
+use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use crate::socket_io_packet_codec::payload::Payload;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
 use actix::Addr;
+use serde_json::Value;
+use std::fmt;
+use std::sync::{Arc, Mutex};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -43,40 +58,470 @@ impl NamespaceName {
     }
 }
 
-#[derive(Default, Debug)]
+type RoomEventListener = dyn Fn(&RoomEvent) + Send + Sync;
+type DisconnectingListener = dyn Fn(SocketRef, DisconnectReason) + Send + Sync;
+type DisconnectListener = dyn Fn(&str, DisconnectReason) + Send + Sync;
+type EventListener = dyn Fn(&Namespace, &str, &str, Payload, Option<AckSender>) + Send + Sync;
+
+/// Subscribers of one kind of namespace events
+struct Listeners<L: ?Sized>(Vec<Arc<L>>);
+
+impl<L: ?Sized> Default for Listeners<L> {
+    fn default() -> Self {
+        Self(Vec::new())
+    }
+}
+
+impl<L: ?Sized> fmt::Debug for Listeners<L> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Listeners({})", self.0.len())
+    }
+}
+
+/// A socket that left a namespace, for the disconnecting and disconnect
+/// listeners of the namespace to be told once the namespace is unlocked, so
+/// that they may use it
+pub struct DisconnectNotice {
+    /// The socket as it was before leaving its rooms
+    socket: SocketRef,
+    reason: DisconnectReason,
+    disconnecting_listeners: Vec<Arc<DisconnectingListener>>,
+    disconnect_listeners: Vec<Arc<DisconnectListener>>,
+}
+
+impl DisconnectNotice {
+    /// Calls the disconnecting listeners, then the disconnect ones
+    pub fn notify(self) {
+        for listener in self.disconnecting_listeners.iter() {
+            listener(self.socket.clone(), self.reason);
+        }
+        for listener in self.disconnect_listeners.iter() {
+            listener(self.socket.id(), self.reason);
+        }
+    }
+}
+
+impl fmt::Debug for DisconnectNotice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DisconnectNotice")
+            .field("socket", &self.socket)
+            .field("reason", &self.reason)
+            .finish()
+    }
+}
+
+#[derive(Debug)]
 pub struct Namespace {
     name: NamespaceName,
     chat_room_manager: ChatRoomManager,
     socket_id_manager: SocketIdManager,
+    room_event_listeners: Listeners<RoomEventListener>,
+    disconnecting_listeners: Listeners<DisconnectingListener>,
+    disconnect_listeners: Listeners<DisconnectListener>,
+    event_listeners: Listeners<EventListener>,
+    /// Sockets that left, whose listeners have not been told yet
+    disconnect_notices: Vec<DisconnectNotice>,
+    /// Only set when the connection state recovery is enabled. Broadcasting
+    /// only borrows the namespace, hence the lock.
+    session_store: Option<Mutex<SessionStore>>,
+    /// Only set when the metrics of the server are enabled
+    metrics: Option<Arc<NamespaceMetrics>>,
+}
+
+impl Default for Namespace {
+    fn default() -> Self {
+        Self::new(NamespaceName::default())
+    }
 }
 
 impl Namespace {
     pub fn new(name: NamespaceName) -> Self {
+        // the events are dispatched to the listeners after each change
+        let mut chat_room_manager = ChatRoomManager::new();
+        chat_room_manager.enable_events();
         Self {
             name,
-            chat_room_manager: ChatRoomManager::new(),
+            chat_room_manager,
             socket_id_manager: SocketIdManager::new(),
+            room_event_listeners: Listeners::default(),
+            disconnecting_listeners: Listeners::default(),
+            disconnect_listeners: Listeners::default(),
+            event_listeners: Listeners::default(),
+            disconnect_notices: Vec::new(),
+            session_store: None,
+            metrics: None,
         }
     }
 
-    pub fn add_socket(&mut self, id: &str, socket_addr: Addr<SocketIoWebsocket>) {
+    /// Keeps the state of temporarily disconnected sockets so that they can
+    /// be restored when their clients reconnect
+    pub fn enable_connection_state_recovery(&mut self, options: ConnectionStateRecoveryOptions) {
+        self.session_store = Some(Mutex::new(SessionStore::new(options)));
+    }
+
+    pub fn is_connection_state_recovery_enabled(&self) -> bool {
+        self.session_store.is_some()
+    }
+
+    /// Reports the sockets, rooms and broadcasts of this namespace to the
+    /// given metrics
+    pub fn enable_metrics(&mut self, metrics: Arc<NamespaceMetrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Subscribes to the room lifecycle events (create-room, delete-room,
+    /// join-room and leave-room) of this namespace, including the ones of the
+    /// room named after each socket id. Listeners are called in the order the
+    /// events happened, while the namespace is still borrowed.
+    pub fn on_room_event<F>(&mut self, listener: F)
+    where
+        F: Fn(&RoomEvent) + Send + Sync + 'static,
+    {
+        self.room_event_listeners.0.push(Arc::new(listener));
+    }
+
+    /// Subscribes to the sockets leaving this namespace. Listeners are called
+    /// with the reason and the socket as it was before leaving its rooms.
+    ///
+    /// Like the disconnect listeners, they are called once the namespace
+    /// manager of the server is unlocked, so that they may use it. A
+    /// namespace used on its own must
+    /// [`take_disconnect_notices`](#method.take_disconnect_notices) for them
+    /// to be called.
+    pub fn on_disconnecting<F>(&mut self, listener: F)
+    where
+        F: Fn(SocketRef, DisconnectReason) + Send + Sync + 'static,
+    {
+        self.disconnecting_listeners.0.push(Arc::new(listener));
+    }
+
+    /// Subscribes to the sockets leaving this namespace. Listeners are called
+    /// with the socket id and the reason, after the disconnecting listeners.
+    pub fn on_disconnect<F>(&mut self, listener: F)
+    where
+        F: Fn(&str, DisconnectReason) + Send + Sync + 'static,
+    {
+        self.disconnect_listeners.0.push(Arc::new(listener));
+    }
+
+    /// Subscribes to the events the clients emit on this namespace.
+    /// Listeners are called with the namespace, the socket id, the event name
+    /// and its arguments, with an [`AckSender`] when the client asks for an
+    /// acknowledgement. They are called while the namespace is still
+    /// borrowed, and may broadcast from it.
+    ///
+    /// [`AckSender`]: ../../socket_io_connection/socket_message/struct.AckSender.html
+    pub fn on_event<F>(&mut self, listener: F)
+    where
+        F: Fn(&Namespace, &str, &str, Payload, Option<AckSender>) + Send + Sync + 'static,
+    {
+        self.event_listeners.0.push(Arc::new(listener));
+    }
+
+    /// Gives an event of a socket to the event listeners. The acknowledgement
+    /// is given to the first of them.
+    pub(crate) fn dispatch_event(
+        &self,
+        id: &str,
+        event_name: &str,
+        payload: Payload,
+        mut ack: Option<AckSender>,
+    ) {
+        for listener in self.event_listeners.0.iter() {
+            listener(self, id, event_name, payload.clone(), ack.take());
+        }
+    }
+
+    /// Take the sockets that left since the last call, whose listeners are
+    /// told by notifying them
+    pub fn take_disconnect_notices(&mut self) -> Vec<DisconnectNotice> {
+        std::mem::take(&mut self.disconnect_notices)
+    }
+
+    fn dispatch_room_events(&mut self) {
+        for event in self.chat_room_manager.take_events() {
+            if let Some(metrics) = self.metrics.as_ref() {
+                match event {
+                    RoomEvent::CreateRoom { .. } => metrics.add_rooms(1),
+                    RoomEvent::DeleteRoom { .. } => metrics.add_rooms(-1),
+                    _ => {}
+                }
+            }
+            for listener in self.room_event_listeners.0.iter() {
+                listener(&event);
+            }
+        }
+    }
+
+    pub fn add_socket(&mut self, id: &str, socket_addr: Addr<SocketIoConnection>) {
         self.socket_id_manager.add_socket(id, socket_addr);
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.add_sockets(1);
+        }
+        if let Some(store) = self.session_store.as_mut() {
+            store.get_mut().unwrap().register(id);
+        }
         // add every socket to a default room that only has himself.
         // https://socket.io/docs/rooms-and-namespaces/
         self.chat_room_manager.add(id, id);
+        self.dispatch_room_events();
+    }
+
+    /// Restores a temporarily disconnected socket from its private session id
+    /// and the offset of the last packet its client received. The socket gets
+    /// its id, rooms and data back. Otherwise the error tells why, and the
+    /// socket is to be added afresh.
+    pub fn restore_socket(
+        &mut self,
+        pid: &str,
+        offset: &str,
+        socket_addr: Addr<SocketIoConnection>,
+    ) -> Result<RecoveredSession, RecoveryError> {
+        let session = self
+            .session_store
+            .as_mut()
+            .ok_or(RecoveryError::Disabled)?
+            .get_mut()
+            .unwrap()
+            .restore(pid, offset)?;
+        self.socket_id_manager.add_socket(&session.sid, socket_addr);
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.add_sockets(1);
+        }
+        self.socket_id_manager
+            .set_socket_data(&session.sid, session.data.clone());
+        let rooms = session.rooms.iter().map(|s| s.as_str()).collect();
+        self.chat_room_manager.add_all(&session.sid, rooms);
+        self.dispatch_room_events();
+        Ok(session)
+    }
+
+    /// Get the private session id of a socket, which its client uses to be
+    /// restored after a temporary disconnection
+    pub fn get_private_id(&self, id: &str) -> Option<String> {
+        let store = self.session_store.as_ref()?.lock().unwrap();
+        store.get_private_id(id).map(|s| s.to_string())
+    }
+
+    pub fn get_socket_data(&self, id: &str) -> Option<&Value> {
+        self.socket_id_manager.get_socket_data(id)
+    }
+
+    pub fn set_socket_data(&mut self, id: &str, data: Value) {
+        self.socket_id_manager.set_socket_data(id, data);
     }
 
     pub fn join_room(&mut self, id: &str, room_id: &str) {
         self.chat_room_manager.add(id, room_id);
+        self.dispatch_room_events();
     }
 
     pub fn leave_room(&mut self, id: &str, room_id: &str) {
         self.chat_room_manager.delete(id, room_id);
+        self.dispatch_room_events();
     }
 
-    pub fn remove_socket(&mut self, id: &str) {
+    /// Removes a socket from the namespace. When the connection state
+    /// recovery is enabled and the reason is recoverable, the state of the
+    /// socket is kept so that it can be restored.
+    pub fn remove_socket(&mut self, id: &str, reason: DisconnectReason) {
+        if self.socket_id_manager.get_socket_addr(id).is_none() {
+            return;
+        }
+        if !self.disconnecting_listeners.0.is_empty() || !self.disconnect_listeners.0.is_empty() {
+            self.disconnect_notices.push(DisconnectNotice {
+                socket: SocketRef::new(self, id),
+                reason,
+                disconnecting_listeners: self.disconnecting_listeners.0.clone(),
+                disconnect_listeners: self.disconnect_listeners.0.clone(),
+            });
+        }
+
+        if let Some(store) = self.session_store.as_mut() {
+            let store = store.get_mut().unwrap();
+            if reason.is_recoverable() {
+                let rooms = self.chat_room_manager.get_socket_rooms(id);
+                let data = self
+                    .socket_id_manager
+                    .get_socket_data(id)
+                    .cloned()
+                    .unwrap_or(Value::Null);
+                store.persist(id, rooms, data);
+            } else {
+                store.forget(id);
+            }
+        }
         self.socket_id_manager.remove_socket_by_id(id);
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.add_sockets(-1);
+        }
         self.chat_room_manager.delete_all(id);
+        self.dispatch_room_events();
+    }
+
+    /// Get the rooms a socket is in, including the one named after its id
+    pub fn get_socket_rooms(&self, id: &str) -> Vec<String> {
+        self.chat_room_manager.get_socket_rooms(id)
+    }
+
+    /// Selects the sockets in the given room for a broadcast or bulk operation
+    pub fn to(&mut self, room_id: &str) -> BroadcastOperator<'_> {
+        BroadcastOperator::new(self).to(room_id)
+    }
+
+    /// Alias of [`to`](#method.to)
+    pub fn in_(&mut self, room_id: &str) -> BroadcastOperator<'_> {
+        BroadcastOperator::new(self).in_(room_id)
+    }
+
+    /// Selects every socket except the ones in the given room
+    pub fn except(&mut self, room_id: &str) -> BroadcastOperator<'_> {
+        BroadcastOperator::new(self).except(room_id)
+    }
+
+    pub fn get_matching_sids(&self, opts: &BroadcastOptions) -> Vec<String> {
+        self.chat_room_manager.get_matching_sids(opts)
+    }
+
+    pub fn add_sockets(&mut self, opts: &BroadcastOptions, room_ids: Vec<&str>) {
+        self.chat_room_manager.add_sockets(opts, room_ids);
+        self.dispatch_room_events();
+    }
+
+    pub fn del_sockets(&mut self, opts: &BroadcastOptions, room_ids: Vec<&str>) {
+        self.chat_room_manager.del_sockets(opts, room_ids);
+        self.dispatch_room_events();
+    }
+
+    /// Removes the matching sockets from the namespace and asks their
+    /// connections to disconnect. Only the sockets connected to this server
+    /// are matched.
+    pub fn disconnect_sockets(&mut self, opts: &BroadcastOptions, close: bool) {
+        for sid in self.chat_room_manager.get_matching_sids(opts) {
+            self.disconnect_socket(&sid, close);
+        }
+    }
+
+    /// Disconnects a socket from the namespace: its client receives a
+    /// DISCONNECT packet and the socket leaves its rooms. The underlying
+    /// connection is closed when `close` is true or when the socket was its
+    /// last namespace.
+    pub fn disconnect_socket(&mut self, id: &str, close: bool) {
+        if let Some(addr) = self.socket_id_manager.get_socket_addr(id) {
+            addr.do_send(DisconnectMessage {
+                nsp: self.get_name().to_string(),
+                close,
+            });
+        }
+        self.remove_socket(id, DisconnectReason::ServerNamespaceDisconnect);
+    }
+
+    pub fn emit_with_options(&self, opts: &BroadcastOptions, event_name: &str, message: &str) {
+        let mut packet = SocketIoPacket {
+            packet_type: SocketIoPacketType::Event,
+            nsp: self.get_name().to_string(),
+            id: None,
+            data: Some(Value::Array(vec![
+                Value::String(event_name.to_string()),
+                Value::String(message.to_string()),
+            ])),
+        };
+        if let Some(store) = self.session_store.as_ref() {
+            store.lock().unwrap().record(opts, &mut packet);
+        }
+        self.broadcast(opts, packet, Vec::new());
+    }
+
+    /// Emits an event with the given arguments, as a BINARY_EVENT when they
+    /// are binary. Binary events are not kept for the connection state
+    /// recovery.
+    pub fn emit_payload_with_options(
+        &self,
+        opts: &BroadcastOptions,
+        event_name: &str,
+        payload: Payload,
+    ) {
+        let (args, attachments) = payload.into_args();
+        let mut data = vec![Value::String(event_name.to_string())];
+        data.extend(args);
+        let packet_type = if attachments.is_empty() {
+            SocketIoPacketType::Event
+        } else {
+            SocketIoPacketType::BinaryEvent
+        };
+        let mut packet = SocketIoPacket {
+            packet_type,
+            nsp: self.get_name().to_string(),
+            id: None,
+            data: Some(Value::Array(data)),
+        };
+        if let Some(store) = self
+            .session_store
+            .as_ref()
+            .filter(|_| attachments.is_empty())
+        {
+            store.lock().unwrap().record(opts, &mut packet);
+        }
+        self.broadcast(opts, packet, attachments);
+    }
+
+    fn broadcast(
+        &self,
+        opts: &BroadcastOptions,
+        packet: SocketIoPacket,
+        attachments: Vec<Vec<u8>>,
+    ) {
+        let sids = self.chat_room_manager.get_matching_sids(opts);
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.on_broadcast(sids.len());
+        }
+        for sid in sids {
+            if let Some(addr) = self.socket_id_manager.get_socket_addr(&sid) {
+                addr.do_send(SocketMessage {
+                    packet: packet.clone(),
+                    attachments: attachments.clone(),
+                    compress: opts.flags.compress,
+                    ack: None,
+                })
+            }
+        }
+    }
+
+    /// Emits an event to a socket, asking its client to acknowledge it. The
+    /// callback is given the arguments of the acknowledgement, or `None` when
+    /// the socket leaves first. Returns false, without calling the callback,
+    /// when there is no such socket. Like a broadcast, the packet may be
+    /// compressed unless `flags` say otherwise.
+    pub fn emit_with_ack<F>(
+        &self,
+        id: &str,
+        flags: &BroadcastFlags,
+        event_name: &str,
+        message: &str,
+        callback: F,
+    ) -> bool
+    where
+        F: FnOnce(Option<Payload>) + Send + 'static,
+    {
+        let addr = match self.socket_id_manager.get_socket_addr(id) {
+            Some(addr) => addr,
+            None => return false,
+        };
+        addr.do_send(SocketMessage {
+            packet: SocketIoPacket {
+                packet_type: SocketIoPacketType::Event,
+                nsp: self.get_name().to_string(),
+                id: None,
+                data: Some(Value::Array(vec![
+                    Value::String(event_name.to_string()),
+                    Value::String(message.to_string()),
+                ])),
+            },
+            attachments: Vec::new(),
+            compress: flags.compress,
+            ack: Some(Box::new(callback)),
+        });
+        true
     }
 
     pub fn emit_to(&self, room: &str, event_name: &str, message: &str) {
@@ -84,25 +529,49 @@ impl Namespace {
     }
 
     pub fn emit_to_rooms(&self, rooms: Vec<&str>, event_name: &str, message: &str) {
-        let sid_set = self.chat_room_manager.get_all_sids_from_rooms(rooms);
-
-        for sid in sid_set {
-            // todo check sid in manager
-            let addr = self.socket_id_manager.get_socket_addr(&sid).unwrap();
-            addr.do_send(SocketMessage {
-                event_name: event_name.to_string(),
-                content: message.to_string(),
-            })
-        }
+        let opts = BroadcastOptions {
+            rooms: rooms.into_iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        self.emit_with_options(&opts, event_name, message);
     }
 
     pub fn emit_to_all(&self, event_name: &str, message: &str) {
-        let rooms = self.chat_room_manager.get_all_rooms();
-        let rooms = rooms.iter().map(|s| s.as_str()).collect();
-        self.emit_to_rooms(rooms, event_name, message);
+        self.emit_with_options(&BroadcastOptions::default(), event_name, message);
     }
 
     pub fn get_name(&self) -> &str {
         &self.name.name
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_adaptor::chat_room_manager::RoomEvent;
+    use crate::socket_io_adaptor::namespace::Namespace;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    pub fn default_room_events_test() {
+        let mut namespace = Namespace::default();
+        assert_eq!(namespace.get_name(), "/");
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        namespace.on_room_event(move |event| recorded.lock().unwrap().push(event.clone()));
+        namespace.join_room("sid1", "room1");
+
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                RoomEvent::CreateRoom {
+                    room_id: "room1".to_string(),
+                },
+                RoomEvent::JoinRoom {
+                    room_id: "room1".to_string(),
+                    sid: "sid1".to_string(),
+                },
+            ]
+        );
+    }
+}
@@ -1,15 +1,28 @@
-use crate::socket_io_adaptor::namespace::{Namespace, NamespaceName, NamespaceNameError};
+use crate::socket_io_adaptor::namespace::{
+    DisconnectNotice, Namespace, NamespaceName, NamespaceNameError,
+};
+use crate::socket_io_adaptor::session_store::ConnectionStateRecoveryOptions;
+use crate::socket_io_admin::admin_ui::AdminUi;
+use crate::socket_io_metrics::metrics::Metrics;
 use std::collections::HashMap;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, LockResult, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
 #[derive(Debug)]
 pub struct NamespaceManager {
     nsp_adaptor_map: HashMap<String, Namespace>,
+    connection_state_recovery: Option<ConnectionStateRecoveryOptions>,
+    metrics: Option<Arc<Metrics>>,
+    admin_ui: Option<Arc<AdminUi>>,
 }
 
 impl Default for NamespaceManager {
     fn default() -> Self {
         let mut myself = Self {
             nsp_adaptor_map: HashMap::new(),
+            connection_state_recovery: None,
+            metrics: None,
+            admin_ui: None,
         };
         // create default namespace
         myself.create_namespace("/").unwrap();
@@ -24,11 +37,47 @@ impl NamespaceManager {
 
     pub fn create_namespace(&mut self, nsp: &str) -> Result<(), NamespaceNameError> {
         let name = NamespaceName::try_new(nsp)?;
-        let namespace = Namespace::new(name);
+        let mut namespace = Namespace::new(name);
+        if let Some(options) = self.connection_state_recovery.as_ref() {
+            namespace.enable_connection_state_recovery(options.clone());
+        }
+        if let Some(metrics) = self.metrics.as_ref() {
+            namespace.enable_metrics(metrics.namespace(nsp));
+        }
+        if let Some(admin_ui) = self.admin_ui.as_ref() {
+            admin_ui.instrument(&mut namespace);
+        }
         self.nsp_adaptor_map.insert(nsp.to_string(), namespace);
         Ok(())
     }
 
+    /// Enables the connection state recovery on every namespace, including
+    /// the ones created afterwards
+    pub fn enable_connection_state_recovery(&mut self, options: ConnectionStateRecoveryOptions) {
+        for namespace in self.nsp_adaptor_map.values_mut() {
+            namespace.enable_connection_state_recovery(options.clone());
+        }
+        self.connection_state_recovery = Some(options);
+    }
+
+    /// Reports the sockets, rooms and broadcasts of every namespace to the
+    /// metrics, including the namespaces created afterwards
+    pub fn enable_metrics(&mut self, metrics: Arc<Metrics>) {
+        for (nsp, namespace) in self.nsp_adaptor_map.iter_mut() {
+            namespace.enable_metrics(metrics.namespace(nsp));
+        }
+        self.metrics = Some(metrics);
+    }
+
+    /// Reports the sockets of every namespace to the admin UI, including the
+    /// namespaces created afterwards
+    pub fn enable_admin_ui(&mut self, admin_ui: Arc<AdminUi>) {
+        for namespace in self.nsp_adaptor_map.values_mut() {
+            admin_ui.instrument(namespace);
+        }
+        self.admin_ui = Some(admin_ui);
+    }
+
     pub fn get_adaptor_mut(&mut self, nsp: &str) -> Option<&mut Namespace> {
         self.nsp_adaptor_map.get_mut(nsp)
     }
@@ -36,6 +85,81 @@ impl NamespaceManager {
     pub fn get_adaptor(&self, nsp: &str) -> Option<&Namespace> {
         self.nsp_adaptor_map.get(nsp)
     }
+
+    /// Get every namespace, in no particular order
+    pub fn get_adaptors(&self) -> impl Iterator<Item = &Namespace> {
+        self.nsp_adaptor_map.values()
+    }
+
+    /// Take the sockets that left any namespace since the last call
+    pub fn take_disconnect_notices(&mut self) -> Vec<DisconnectNotice> {
+        self.nsp_adaptor_map
+            .values_mut()
+            .flat_map(|namespace| namespace.take_disconnect_notices())
+            .collect()
+    }
+
+    /// Get every namespace, in no particular order
+    pub fn get_adaptors_mut(&mut self) -> impl Iterator<Item = &mut Namespace> {
+        self.nsp_adaptor_map.values_mut()
+    }
+}
+
+/// The lock of the namespace manager of a server. Once a write guard is
+/// dropped, the listeners of the sockets that left a namespace meanwhile are
+/// called, with the lock released so that they may take it again.
+#[derive(Debug, Default)]
+pub struct NamespaceManagerLock(RwLock<NamespaceManager>);
+
+impl NamespaceManagerLock {
+    pub fn new(namespace_manager: NamespaceManager) -> Self {
+        Self(RwLock::new(namespace_manager))
+    }
+
+    pub fn read(&self) -> LockResult<RwLockReadGuard<'_, NamespaceManager>> {
+        self.0.read()
+    }
+
+    pub fn write(&self) -> LockResult<NamespaceManagerWriteGuard<'_>> {
+        match self.0.write() {
+            Ok(guard) => Ok(NamespaceManagerWriteGuard(Some(guard))),
+            Err(poisoned) => Err(PoisonError::new(NamespaceManagerWriteGuard(Some(
+                poisoned.into_inner(),
+            )))),
+        }
+    }
+}
+
+/// Write access to the namespace manager, see
+/// [`NamespaceManagerLock`](struct.NamespaceManagerLock.html)
+#[derive(Debug)]
+pub struct NamespaceManagerWriteGuard<'a>(Option<RwLockWriteGuard<'a, NamespaceManager>>);
+
+impl Deref for NamespaceManagerWriteGuard<'_> {
+    type Target = NamespaceManager;
+
+    fn deref(&self) -> &NamespaceManager {
+        self.0.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for NamespaceManagerWriteGuard<'_> {
+    fn deref_mut(&mut self) -> &mut NamespaceManager {
+        self.0.as_mut().unwrap()
+    }
+}
+
+impl Drop for NamespaceManagerWriteGuard<'_> {
+    fn drop(&mut self) {
+        let notices = match self.0.as_mut() {
+            Some(guard) => guard.take_disconnect_notices(),
+            None => return,
+        };
+        self.0 = None;
+        for notice in notices {
+            notice.notify();
+        }
+    }
 }
 
 #[cfg(test)]
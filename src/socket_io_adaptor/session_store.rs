@@ -0,0 +1,310 @@
+use crate::random_id_generator::RandomIdGenerator;
+use crate::socket_io_adaptor::chat_room_manager::BroadcastOptions;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+/// Configuration of the connection state recovery
+/// https://socket.io/docs/v4/connection-state-recovery
+#[derive(Debug, Clone)]
+pub struct ConnectionStateRecoveryOptions {
+    /// How long a disconnected socket can be restored
+    pub max_disconnection_duration: Duration,
+    /// How many broadcast packets are kept to be replayed
+    pub max_buffered_packets: usize,
+}
+
+impl Default for ConnectionStateRecoveryOptions {
+    fn default() -> Self {
+        Self {
+            max_disconnection_duration: Duration::from_secs(2 * 60),
+            max_buffered_packets: 1000,
+        }
+    }
+}
+
+/// The state of a socket that was restored after a temporary disconnection
+#[derive(Debug, PartialEq)]
+pub struct RecoveredSession {
+    pub sid: String,
+    pub pid: String,
+    pub rooms: Vec<String>,
+    pub data: Value,
+    /// The packets broadcast to the socket after the offset sent by the client
+    pub missed_packets: Vec<SocketIoPacket>,
+}
+
+/// Why a socket could not be restored, in which case it starts afresh
+#[derive(Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryError {
+    #[error("connection state recovery is disabled")]
+    Disabled,
+    #[error("the session is unknown or expired")]
+    UnknownSession,
+    #[error("the packets following the offset are no longer buffered")]
+    UnknownOffset,
+}
+
+#[derive(Debug)]
+struct PersistedSession {
+    sid: String,
+    rooms: Vec<String>,
+    data: Value,
+    disconnected_at: Instant,
+}
+
+#[derive(Debug)]
+struct BufferedPacket {
+    offset: String,
+    emitted_at: Instant,
+    opts: BroadcastOptions,
+    packet: SocketIoPacket,
+}
+
+impl BufferedPacket {
+    fn is_sent_to(&self, rooms: &HashSet<&str>) -> bool {
+        let included = self.opts.rooms.is_empty()
+            || self.opts.rooms.iter().any(|r| rooms.contains(r.as_str()));
+        let excluded = self.opts.except.iter().any(|r| rooms.contains(r.as_str()));
+        included && !excluded
+    }
+}
+
+/// Keeps the state of disconnected sockets and the recently broadcast packets
+/// so that a socket can be restored when its client reconnects in time.
+#[derive(Debug)]
+pub struct SessionStore {
+    options: ConnectionStateRecoveryOptions,
+    /// Private session id of each connected socket
+    pids: HashMap<String, String>,
+    /// Disconnected sockets, by private session id
+    sessions: HashMap<String, PersistedSession>,
+    packets: VecDeque<BufferedPacket>,
+    next_offset: u64,
+}
+
+impl SessionStore {
+    pub fn new(options: ConnectionStateRecoveryOptions) -> Self {
+        Self {
+            options,
+            pids: HashMap::new(),
+            sessions: HashMap::new(),
+            packets: VecDeque::new(),
+            next_offset: 0,
+        }
+    }
+
+    /// Creates the private session id of a newly connected socket
+    pub fn register(&mut self, sid: &str) -> String {
+        let pid = RandomIdGenerator::get_random_uuid();
+        self.pids.insert(sid.to_string(), pid.clone());
+        pid
+    }
+
+    /// Get the private session id of a connected socket
+    pub fn get_private_id(&self, sid: &str) -> Option<&str> {
+        self.pids.get(sid).map(|s| s.as_str())
+    }
+
+    /// Keeps the state of a socket that was temporarily disconnected
+    pub fn persist(&mut self, sid: &str, rooms: Vec<String>, data: Value) {
+        if let Some(pid) = self.pids.remove(sid) {
+            self.sessions.insert(
+                pid,
+                PersistedSession {
+                    sid: sid.to_string(),
+                    rooms,
+                    data,
+                    disconnected_at: Instant::now(),
+                },
+            );
+        }
+    }
+
+    /// Drops a socket that left for good
+    pub fn forget(&mut self, sid: &str) {
+        self.pids.remove(sid);
+    }
+
+    /// Assigns an offset to a broadcast packet and keeps it to be replayed.
+    /// The offset is appended to the arguments of the event.
+    pub fn record(&mut self, opts: &BroadcastOptions, packet: &mut SocketIoPacket) {
+        let offset = self.next_offset.to_string();
+        self.next_offset += 1;
+        if let Some(Value::Array(args)) = packet.data.as_mut() {
+            args.push(Value::String(offset.clone()));
+        }
+
+        self.packets.push_back(BufferedPacket {
+            offset,
+            emitted_at: Instant::now(),
+            opts: opts.clone(),
+            packet: packet.clone(),
+        });
+        while self.packets.len() > self.options.max_buffered_packets {
+            self.packets.pop_front();
+        }
+        self.prune();
+    }
+
+    /// Restores a disconnected socket from its private session id and the
+    /// offset of the last packet its client received.
+    ///
+    /// Fails when the session is unknown or expired, or when the packets
+    /// following the offset are no longer buffered. The session cannot be
+    /// restored afterwards.
+    pub fn restore(&mut self, pid: &str, offset: &str) -> Result<RecoveredSession, RecoveryError> {
+        self.prune();
+        let session = self
+            .sessions
+            .remove(pid)
+            .ok_or(RecoveryError::UnknownSession)?;
+        let index = self
+            .packets
+            .iter()
+            .position(|p| p.offset == offset)
+            .ok_or(RecoveryError::UnknownOffset)?;
+
+        let rooms: HashSet<&str> = session.rooms.iter().map(|s| s.as_str()).collect();
+        let missed_packets = self
+            .packets
+            .iter()
+            .skip(index + 1)
+            .filter(|p| p.is_sent_to(&rooms))
+            .map(|p| p.packet.clone())
+            .collect();
+
+        self.pids.insert(session.sid.clone(), pid.to_string());
+        Ok(RecoveredSession {
+            sid: session.sid,
+            pid: pid.to_string(),
+            rooms: session.rooms,
+            data: session.data,
+            missed_packets,
+        })
+    }
+
+    /// Drops the expired sessions and packets
+    fn prune(&mut self) {
+        let max_duration = self.options.max_disconnection_duration;
+        self.sessions
+            .retain(|_, session| session.disconnected_at.elapsed() <= max_duration);
+        while let Some(packet) = self.packets.front() {
+            if packet.emitted_at.elapsed() <= max_duration {
+                break;
+            }
+            self.packets.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_adaptor::chat_room_manager::BroadcastOptions;
+    use crate::socket_io_adaptor::session_store::{
+        ConnectionStateRecoveryOptions, RecoveryError, SessionStore,
+    };
+    use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+    use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+    use serde_json::json;
+    use std::time::Duration;
+
+    fn event(content: &str) -> SocketIoPacket {
+        SocketIoPacket {
+            packet_type: SocketIoPacketType::Event,
+            nsp: String::from("/"),
+            id: None,
+            data: Some(json!(["hello", content])),
+        }
+    }
+
+    fn to_room(room: &str) -> BroadcastOptions {
+        let mut opts = BroadcastOptions::default();
+        opts.rooms.insert(room.to_string());
+        opts
+    }
+
+    #[test]
+    fn record_test() {
+        let mut store = SessionStore::new(ConnectionStateRecoveryOptions::default());
+        let mut packet = event("a");
+        store.record(&BroadcastOptions::default(), &mut packet);
+        assert_eq!(packet.data, Some(json!(["hello", "a", "0"])));
+    }
+
+    #[test]
+    fn restore_test() {
+        let mut store = SessionStore::new(ConnectionStateRecoveryOptions::default());
+        let pid = store.register("sid1");
+        assert_eq!(store.get_private_id("sid1"), Some(pid.as_str()));
+
+        store.record(&BroadcastOptions::default(), &mut event("a"));
+        store.persist("sid1", vec![String::from("room1")], json!({"name": "John"}));
+        assert_eq!(store.get_private_id("sid1"), None);
+
+        store.record(&to_room("room1"), &mut event("b"));
+        store.record(&to_room("room2"), &mut event("c"));
+        let mut except = BroadcastOptions::default();
+        except.except.insert(String::from("room1"));
+        store.record(&except, &mut event("d"));
+
+        let session = store.restore(&pid, "0").unwrap();
+        assert_eq!(session.sid, "sid1");
+        assert_eq!(session.rooms, vec![String::from("room1")]);
+        assert_eq!(session.data, json!({"name": "John"}));
+        let mut expected = event("b");
+        expected.data = Some(json!(["hello", "b", "1"]));
+        assert_eq!(session.missed_packets, vec![expected]);
+        assert_eq!(store.get_private_id("sid1"), Some(pid.as_str()));
+    }
+
+    #[test]
+    fn restore_test_unknown_offset() {
+        let mut store = SessionStore::new(ConnectionStateRecoveryOptions {
+            max_disconnection_duration: Duration::from_secs(60),
+            max_buffered_packets: 1,
+        });
+        let pid = store.register("sid1");
+        store.record(&BroadcastOptions::default(), &mut event("a"));
+        store.persist("sid1", Vec::new(), json!(null));
+        store.record(&BroadcastOptions::default(), &mut event("b"));
+
+        // the packet following "0" may have been missed, the session is lost
+        assert_eq!(store.restore(&pid, "0"), Err(RecoveryError::UnknownOffset));
+        assert_eq!(store.restore(&pid, "1"), Err(RecoveryError::UnknownSession));
+
+        let pid = store.register("sid2");
+        store.persist("sid2", Vec::new(), json!(null));
+        assert_eq!(
+            store.restore(&pid, "unknown"),
+            Err(RecoveryError::UnknownOffset)
+        );
+    }
+
+    #[test]
+    fn restore_test_expired() {
+        let mut store = SessionStore::new(ConnectionStateRecoveryOptions {
+            max_disconnection_duration: Duration::from_secs(0),
+            max_buffered_packets: 10,
+        });
+        let pid = store.register("sid1");
+        store.record(&BroadcastOptions::default(), &mut event("a"));
+        store.persist("sid1", Vec::new(), json!(null));
+        std::thread::sleep(Duration::from_millis(5));
+
+        assert_eq!(store.restore(&pid, "0"), Err(RecoveryError::UnknownSession));
+    }
+
+    #[test]
+    fn forget_test() {
+        let mut store = SessionStore::new(ConnectionStateRecoveryOptions::default());
+        let pid = store.register("sid1");
+        store.record(&BroadcastOptions::default(), &mut event("a"));
+        store.forget("sid1");
+        store.persist("sid1", Vec::new(), json!(null));
+
+        assert_eq!(store.restore(&pid, "0"), Err(RecoveryError::UnknownSession));
+    }
+}
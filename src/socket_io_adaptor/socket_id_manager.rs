@@ -1,10 +1,13 @@
-use crate::socket_io_websocket::socket_io_websocket_impl::SocketIoWebsocket;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
 use actix::Addr;
+use serde_json::Value;
 use std::collections::HashMap;
 
 #[derive(Default, Debug)]
 pub struct SocketIdManager {
-    id_socket_map: HashMap<String, Addr<SocketIoWebsocket>>,
+    id_socket_map: HashMap<String, Addr<SocketIoConnection>>,
+    /// Arbitrary data attached to each socket
+    id_data_map: HashMap<String, Value>,
 }
 
 impl SocketIdManager {
@@ -12,15 +15,24 @@ impl SocketIdManager {
         Self::default()
     }
 
-    pub fn add_socket(&mut self, id: &str, socket_addr: Addr<SocketIoWebsocket>) {
+    pub fn add_socket(&mut self, id: &str, socket_addr: Addr<SocketIoConnection>) {
         self.id_socket_map.insert(id.to_string(), socket_addr);
     }
 
-    pub fn get_socket_addr(&self, id: &str) -> Option<&Addr<SocketIoWebsocket>> {
+    pub fn get_socket_addr(&self, id: &str) -> Option<&Addr<SocketIoConnection>> {
         self.id_socket_map.get(id)
     }
 
-    pub fn remove_socket_by_id(&mut self, id: &str) -> Option<Addr<SocketIoWebsocket>> {
+    pub fn remove_socket_by_id(&mut self, id: &str) -> Option<Addr<SocketIoConnection>> {
+        self.id_data_map.remove(id);
         self.id_socket_map.remove(id)
     }
+
+    pub fn get_socket_data(&self, id: &str) -> Option<&Value> {
+        self.id_data_map.get(id)
+    }
+
+    pub fn set_socket_data(&mut self, id: &str, data: Value) {
+        self.id_data_map.insert(id.to_string(), data);
+    }
 }
@@ -0,0 +1,43 @@
+use crate::socket_io_adaptor::namespace::Namespace;
+use serde_json::Value;
+
+/// A snapshot of a socket connected to a namespace, given to the namespace
+/// listeners. It does not borrow the namespace, so that listeners may run
+/// once it is unlocked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketRef {
+    id: String,
+    nsp: String,
+    rooms: Vec<String>,
+    data: Option<Value>,
+}
+
+impl SocketRef {
+    pub fn new(namespace: &Namespace, id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            nsp: namespace.get_name().to_string(),
+            rooms: namespace.get_socket_rooms(id),
+            data: namespace.get_socket_data(id).cloned(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get the name of the namespace of the socket
+    pub fn nsp(&self) -> &str {
+        &self.nsp
+    }
+
+    /// Get the rooms the socket is in, including the one named after its id
+    pub fn rooms(&self) -> &[String] {
+        &self.rooms
+    }
+
+    /// Get the data attached to the socket
+    pub fn data(&self) -> Option<&Value> {
+        self.data.as_ref()
+    }
+}
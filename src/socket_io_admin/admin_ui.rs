@@ -0,0 +1,488 @@
+use crate::engine_io_server::transport::TransportType;
+use crate::socket_io_adaptor::chat_room_manager::{BroadcastOptions, RoomEvent};
+use crate::socket_io_adaptor::namespace::Namespace;
+use crate::socket_io_adaptor::namespace_manager::NamespaceManager;
+use crate::socket_io_adaptor::socket_ref::SocketRef;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use crate::socket_io_connection::socket_message::SocketMessage;
+use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use crate::socket_io_websocket::app_state::AppState;
+use actix::Addr;
+use actix_rt::time::delay_for;
+use actix_web::http::header::HttpDate;
+use actix_web::web;
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// The features of the dashboard the server supports
+const SUPPORTED_FEATURES: [&str; 6] = [
+    "JOIN",
+    "LEAVE",
+    "DISCONNECT",
+    "MJOIN",
+    "MLEAVE",
+    "MDISCONNECT",
+];
+
+/// The credentials the dashboard must send to join the admin namespace
+#[derive(Debug, Clone)]
+pub struct BasicAuth {
+    pub username: String,
+    /// The bcrypt hash of the password, as the dashboard documentation has
+    /// it generated
+    pub password_hash: String,
+}
+
+/// Options of the server side of the `@socket.io/admin-ui` dashboard
+#[derive(Debug, Clone)]
+pub struct AdminUiOptions {
+    /// The namespace the dashboard connects to
+    pub namespace: String,
+    /// Anyone may connect to the admin namespace when `None`
+    pub auth: Option<BasicAuth>,
+    /// Identifies the server on the dashboard, its hostname and process id
+    /// by default
+    pub server_id: Option<String>,
+    /// How often the server stats are sent
+    pub server_stats_interval: Duration,
+}
+
+impl Default for AdminUiOptions {
+    fn default() -> Self {
+        Self {
+            namespace: String::from("/admin"),
+            auth: None,
+            server_id: None,
+            server_stats_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// What the connection of a socket knows about its client, shown on the
+/// dashboard
+#[derive(Debug, Clone)]
+pub struct SocketDetails {
+    /// The Engine.IO session id
+    pub client_id: String,
+    pub transport: &'static str,
+    pub address: Option<String>,
+    pub time: SystemTime,
+    /// The auth payload of the CONNECT packet
+    pub auth: Value,
+}
+
+/// Milliseconds since the epoch, which the dashboard reads as a date
+fn timestamp(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// The server side of the `@socket.io/admin-ui` dashboard.
+///
+/// The dashboard connects to the admin namespace. It is sent the config of
+/// the server, a snapshot of the sockets and their rooms, then the changes:
+/// sockets connecting, joining and leaving rooms, and disconnecting, along
+/// with the server stats every interval. It may make sockets join or leave a
+/// room, or disconnect them.
+#[derive(Debug)]
+pub struct AdminUi {
+    options: AdminUiOptions,
+    server_id: String,
+    started: Instant,
+    /// The sockets of the dashboards, by socket id
+    admins: RwLock<HashMap<String, Addr<SocketIoConnection>>>,
+    /// The instrumented sockets, by namespace and socket id
+    sockets: RwLock<HashMap<(String, String), SocketDetails>>,
+    /// Set while the server stats are being sent
+    sending_stats: AtomicBool,
+}
+
+impl AdminUi {
+    pub fn new(options: AdminUiOptions) -> Self {
+        let server_id = options.server_id.clone().unwrap_or_else(|| {
+            let hostname = std::env::var("HOSTNAME").unwrap_or_else(|_| "localhost".to_string());
+            format!("{}#{}", hostname, std::process::id())
+        });
+        Self {
+            options,
+            server_id,
+            started: Instant::now(),
+            admins: RwLock::new(HashMap::new()),
+            sockets: RwLock::new(HashMap::new()),
+            sending_stats: AtomicBool::new(false),
+        }
+    }
+
+    /// Get the name of the admin namespace
+    pub fn namespace(&self) -> &str {
+        &self.options.namespace
+    }
+
+    /// Whether the auth payload of a CONNECT packet to the admin namespace
+    /// carries the credentials
+    pub fn authenticate(&self, auth: &Value) -> bool {
+        let expected = match self.options.auth.as_ref() {
+            Some(expected) => expected,
+            None => return true,
+        };
+        let username = auth.get("username").and_then(Value::as_str);
+        let password = auth.get("password").and_then(Value::as_str);
+        match (username, password) {
+            (Some(username), Some(password)) if username == expected.username => {
+                bcrypt::verify(password, &expected.password_hash).unwrap_or(false)
+            }
+            _ => false,
+        }
+    }
+
+    /// Reports the room changes and disconnections of a namespace to the
+    /// dashboards. The admin namespace only tells which dashboards leave.
+    pub fn instrument(self: &Arc<Self>, namespace: &mut Namespace) {
+        let admin_ui = self.clone();
+        if namespace.get_name() == self.namespace() {
+            namespace.on_disconnect(move |sid, _| {
+                admin_ui.admins.write().unwrap().remove(sid);
+            });
+            return;
+        }
+
+        let nsp = namespace.get_name().to_string();
+        namespace.on_room_event(move |event| {
+            let (name, room_id, sid) = match event {
+                RoomEvent::JoinRoom { room_id, sid } => ("room_joined", room_id, sid),
+                RoomEvent::LeaveRoom { room_id, sid } => ("room_left", room_id, sid),
+                _ => return,
+            };
+            let now = timestamp(SystemTime::now());
+            admin_ui.emit(
+                name,
+                vec![json!(nsp), json!(room_id), json!(sid), json!(now)],
+            );
+        });
+        let admin_ui = self.clone();
+        let nsp = namespace.get_name().to_string();
+        namespace.on_disconnect(move |sid, reason| {
+            let key = (nsp.clone(), sid.to_string());
+            admin_ui.sockets.write().unwrap().remove(&key);
+            let now = timestamp(SystemTime::now());
+            let args = vec![
+                json!(nsp),
+                json!(sid),
+                json!(reason.to_string()),
+                json!(now),
+            ];
+            admin_ui.emit("socket_disconnected", args);
+        });
+    }
+
+    /// Called once a socket joined an instrumented namespace
+    pub(crate) fn on_socket_connected(&self, socket: SocketRef, details: SocketDetails) {
+        let serialized = serialize_socket(&socket, &details);
+        let key = (socket.nsp().to_string(), socket.id().to_string());
+        self.sockets.write().unwrap().insert(key, details);
+        let now = timestamp(SystemTime::now());
+        self.emit("socket_connected", vec![serialized, json!(now)]);
+    }
+
+    /// Called once a dashboard joined the admin namespace: it is sent the
+    /// config of the server and the sockets of every other namespace
+    pub(crate) fn on_admin_connected(
+        &self,
+        namespace_manager: &NamespaceManager,
+        sid: &str,
+        addr: Addr<SocketIoConnection>,
+    ) {
+        let config = json!({ "supportedFeatures": SUPPORTED_FEATURES });
+        send(&addr, self.namespace(), "config", vec![config]);
+        let sockets = self.sockets.read().unwrap();
+        let mut snapshot = Vec::new();
+        for namespace in namespace_manager.get_adaptors() {
+            let mut sids = namespace.get_matching_sids(&BroadcastOptions::default());
+            sids.sort();
+            for id in sids.iter() {
+                let key = (namespace.get_name().to_string(), id.clone());
+                if let Some(details) = sockets.get(&key) {
+                    snapshot.push(serialize_socket(&SocketRef::new(namespace, id), details));
+                }
+            }
+        }
+        send(
+            &addr,
+            self.namespace(),
+            "all_sockets",
+            vec![Value::Array(snapshot)],
+        );
+        self.admins.write().unwrap().insert(sid.to_string(), addr);
+    }
+
+    /// Called once a connection moved to another transport
+    pub(crate) fn on_transport_upgraded(&self, client_id: &str, transport: &'static str) {
+        let mut updated = Vec::new();
+        for ((nsp, sid), details) in self.sockets.write().unwrap().iter_mut() {
+            if details.client_id == client_id {
+                details.transport = transport;
+                updated.push(json!({ "id": sid, "nsp": nsp, "transport": transport }));
+            }
+        }
+        for socket in updated {
+            self.emit("socket_updated", vec![socket]);
+        }
+    }
+
+    /// Runs a command of a dashboard: `_join` and `_leave` with the namespace,
+    /// the room and the sockets to apply to, `_disconnect` with the namespace,
+    /// whether to close the connections and the sockets to apply to. The
+    /// sockets are the ones of a room, such as the one named after a socket
+    /// id, or every socket of the namespace when there is none.
+    pub(crate) fn on_command(
+        &self,
+        namespace_manager: &mut NamespaceManager,
+        event: &str,
+        args: &[Value],
+    ) {
+        let nsp = match args.first().and_then(Value::as_str) {
+            Some(nsp) if nsp != self.namespace() => nsp,
+            _ => return,
+        };
+        let namespace = match namespace_manager.get_adaptor_mut(nsp) {
+            Some(namespace) => namespace,
+            None => return,
+        };
+        let opts = |filter: Option<&Value>| BroadcastOptions {
+            rooms: filter
+                .and_then(Value::as_str)
+                .map(|room| room.to_string())
+                .into_iter()
+                .collect::<HashSet<_>>(),
+            ..Default::default()
+        };
+        match (event, args.get(1)) {
+            ("_join", Some(Value::String(room))) => {
+                namespace.add_sockets(&opts(args.get(2)), vec![room]);
+            }
+            ("_leave", Some(Value::String(room))) => {
+                namespace.del_sockets(&opts(args.get(2)), vec![room]);
+            }
+            ("_disconnect", close) => {
+                let close = close.and_then(Value::as_bool).unwrap_or(false);
+                namespace.disconnect_sockets(&opts(args.get(2)), close);
+            }
+            _ => {}
+        }
+    }
+
+    /// Sends the server stats to the dashboards every interval, until none is
+    /// left
+    pub(crate) fn start_server_stats(data: web::Data<AppState>) {
+        let admin_ui = match data.admin_ui.as_ref() {
+            Some(admin_ui) if !admin_ui.sending_stats.swap(true, Ordering::SeqCst) => {
+                admin_ui.clone()
+            }
+            _ => return,
+        };
+        actix::spawn(async move {
+            loop {
+                if admin_ui.admins.read().unwrap().is_empty() {
+                    admin_ui.sending_stats.store(false, Ordering::SeqCst);
+                    return;
+                }
+                admin_ui.emit("server_stats", vec![admin_ui.server_stats(&data)]);
+                delay_for(admin_ui.options.server_stats_interval).await;
+            }
+        });
+    }
+
+    fn server_stats(&self, data: &AppState) -> Value {
+        let namespaces: Vec<Value> = {
+            let namespace_manager = data.namespace_manager.read().unwrap();
+            let mut namespaces: Vec<&Namespace> = namespace_manager.get_adaptors().collect();
+            namespaces.sort_by_key(|namespace| namespace.get_name());
+            namespaces
+                .into_iter()
+                .map(|namespace| {
+                    let sockets = namespace.get_matching_sids(&BroadcastOptions::default());
+                    json!({ "name": namespace.get_name(), "socketsCount": sockets.len() })
+                })
+                .collect()
+        };
+        let sockets = self.sockets.read().unwrap();
+        let polling = TransportType::Polling.name();
+        let polling_clients: HashSet<&str> = sockets
+            .values()
+            .filter(|details| details.transport == polling)
+            .map(|details| details.client_id.as_str())
+            .collect();
+        json!({
+            "serverId": self.server_id,
+            "hostname": std::env::var("HOSTNAME").unwrap_or_default(),
+            "pid": std::process::id(),
+            "uptime": self.started.elapsed().as_secs_f64(),
+            "clientsCount": data.connections.read().unwrap().len(),
+            "pollingClientsCount": polling_clients.len(),
+            "aggregatedEvents": [],
+            "namespaces": namespaces,
+        })
+    }
+
+    /// Emits an event to every dashboard
+    fn emit(&self, event: &str, args: Vec<Value>) {
+        for addr in self.admins.read().unwrap().values() {
+            send(addr, self.namespace(), event, args.clone());
+        }
+    }
+}
+
+fn send(addr: &Addr<SocketIoConnection>, nsp: &str, event: &str, args: Vec<Value>) {
+    let mut data = vec![Value::String(event.to_string())];
+    data.extend(args);
+    addr.do_send(SocketMessage {
+        packet: SocketIoPacket {
+            packet_type: SocketIoPacketType::Event,
+            nsp: nsp.to_string(),
+            id: None,
+            data: Some(Value::Array(data)),
+        },
+        attachments: Vec::new(),
+        compress: true,
+        ack: None,
+    });
+}
+
+/// Get a socket as the dashboard shows it
+fn serialize_socket(socket: &SocketRef, details: &SocketDetails) -> Value {
+    json!({
+        "id": socket.id(),
+        "clientId": details.client_id,
+        "transport": details.transport,
+        "nsp": socket.nsp(),
+        "data": socket.data().cloned().unwrap_or(Value::Null),
+        "handshake": {
+            "address": details.address,
+            "headers": {},
+            "time": HttpDate::from(details.time).to_string(),
+            "issued": timestamp(details.time),
+            "query": {},
+            "auth": details.auth,
+        },
+        "rooms": socket.rooms(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io::server_options::ServerOptions;
+    use crate::socket_io_admin::admin_ui::{AdminUiOptions, BasicAuth};
+    use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+    use crate::socket_io_testing::test_server::TestServer;
+    use crate::socket_io_testing::virtual_client::VirtualClient;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+
+    /// Waits for an event of the admin namespace, skipping the other ones
+    async fn next_event(client: &mut VirtualClient, name: &str) -> Vec<Value> {
+        loop {
+            let packet = client.recv_socket_io().await.unwrap();
+            assert_eq!(packet.packet_type, SocketIoPacketType::Event);
+            assert_eq!(packet.nsp, "/admin");
+            let mut args = match packet.data {
+                Some(Value::Array(args)) => args,
+                data => panic!("unexpected event data {:?}", data),
+            };
+            if args.remove(0) == json!(name) {
+                return args;
+            }
+        }
+    }
+
+    #[actix_rt::test]
+    async fn admin_ui_test() {
+        let options = ServerOptions::builder()
+            .admin_ui(AdminUiOptions {
+                auth: Some(BasicAuth {
+                    username: "admin".to_string(),
+                    password_hash: bcrypt::hash("secret", 4).unwrap(),
+                }),
+                server_id: Some("server-1".to_string()),
+                server_stats_interval: Duration::from_secs(3600),
+                ..Default::default()
+            })
+            .build();
+        let server = TestServer::with_options(options);
+        let mut client = server.connect().await;
+        let sid = client.connect("/", None).await.unwrap();
+
+        let mut admin = server.connect().await;
+        let invalid = Err(json!({ "message": "invalid credentials" }));
+        assert_eq!(admin.connect("/admin", None).await, invalid);
+        let auth = json!({ "type": "basic", "username": "admin", "password": "wrong" });
+        assert_eq!(admin.connect("/admin", Some(auth)).await, invalid);
+        let auth = json!({ "type": "basic", "username": "admin", "password": "secret" });
+        assert!(admin.connect("/admin", Some(auth)).await.is_ok());
+
+        let config = next_event(&mut admin, "config").await;
+        assert_eq!(config[0]["supportedFeatures"][0], json!("JOIN"));
+        let sockets = next_event(&mut admin, "all_sockets").await;
+        let sockets = sockets[0].as_array().unwrap();
+        assert_eq!(sockets.len(), 1);
+        assert_eq!(sockets[0]["id"], json!(sid));
+        assert_eq!(sockets[0]["clientId"], json!(client.sid()));
+        assert_eq!(sockets[0]["transport"], json!("mock"));
+        assert_eq!(sockets[0]["rooms"], json!([sid]));
+        let stats = next_event(&mut admin, "server_stats").await;
+        assert_eq!(stats[0]["serverId"], json!("server-1"));
+        assert_eq!(stats[0]["clientsCount"], json!(2));
+        assert_eq!(
+            stats[0]["namespaces"],
+            json!([
+                { "name": "/", "socketsCount": 1 },
+                { "name": "/admin", "socketsCount": 1 },
+            ])
+        );
+
+        let mut other = server.connect().await;
+        let other_sid = other.connect("/", None).await.unwrap();
+        let connected = next_event(&mut admin, "socket_connected").await;
+        assert_eq!(connected[0]["id"], json!(other_sid));
+        assert_eq!(connected[0]["nsp"], json!("/"));
+
+        // the dashboard makes the sockets of a room join or leave another one
+        admin.emit(
+            "/admin",
+            "_join",
+            vec![json!("/"), json!("room1"), json!(sid)],
+        );
+        let joined = next_event(&mut admin, "room_joined").await;
+        assert_eq!(joined[..3], [json!("/"), json!("room1"), json!(sid)]);
+        assert!(server.rooms("/", &sid).contains(&"room1".to_string()));
+        admin.emit("/admin", "_leave", vec![json!("/"), json!("room1")]);
+        let left = next_event(&mut admin, "room_left").await;
+        assert_eq!(left[..3], [json!("/"), json!("room1"), json!(sid)]);
+
+        // and disconnects them
+        admin.emit(
+            "/admin",
+            "_disconnect",
+            vec![json!("/"), json!(false), json!(other_sid)],
+        );
+        let disconnected = next_event(&mut admin, "socket_disconnected").await;
+        assert_eq!(
+            disconnected[..3],
+            [
+                json!("/"),
+                json!(other_sid),
+                json!("server namespace disconnect")
+            ]
+        );
+        assert_eq!(server.sockets("/", None), vec![sid]);
+
+        // commands on the admin namespace itself are ignored
+        admin.emit("/admin", "_disconnect", vec![json!("/admin"), json!(true)]);
+        server.settle().await;
+        assert_eq!(server.sockets("/admin", None).len(), 1);
+    }
+}
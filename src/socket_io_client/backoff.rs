@@ -0,0 +1,83 @@
+use std::time::Duration;
+
+/// The delays between reconnection attempts, growing exponentially from
+/// `min` up to `max`. Each delay deviates randomly by up to `jitter` of
+/// itself, so that clients dropped together do not reconnect together.
+#[derive(Debug, Clone)]
+pub(crate) struct Backoff {
+    min: Duration,
+    max: Duration,
+    jitter: f64,
+    attempts: u32,
+}
+
+impl Backoff {
+    pub(crate) fn new(min: Duration, max: Duration, jitter: f64) -> Self {
+        Backoff {
+            min,
+            max,
+            jitter: jitter.clamp(0.0, 1.0),
+            attempts: 0,
+        }
+    }
+
+    /// The number of delays given since the last reset
+    pub(crate) fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Get the delay before the next attempt
+    pub(crate) fn duration(&mut self) -> Duration {
+        self.duration_with(rand::random())
+    }
+
+    /// Get the delay before the next attempt, deviating as `random`, between
+    /// 0 and 1, tells
+    fn duration_with(&mut self, random: f64) -> Duration {
+        let exponent = self.attempts.min(31) as i32;
+        self.attempts = self.attempts.saturating_add(1);
+        let mut ms = self.min.as_millis() as f64 * 2f64.powi(exponent);
+        if self.jitter > 0.0 {
+            let deviation = (random * self.jitter * ms).floor();
+            // the lowest bit of the random digit picks the direction
+            if ((random * 10.0).floor() as u32) & 1 == 0 {
+                ms -= deviation;
+            } else {
+                ms += deviation;
+            }
+        }
+        Duration::from_millis(ms.min(self.max.as_millis() as f64) as u64)
+    }
+
+    pub(crate) fn reset(&mut self) {
+        self.attempts = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_client::backoff::Backoff;
+    use std::time::Duration;
+
+    #[test]
+    fn backoff_test() {
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_secs(1), 0.0);
+        let delays: Vec<u64> = (0..5)
+            .map(|_| backoff.duration().as_millis() as u64)
+            .collect();
+        assert_eq!(vec![100, 200, 400, 800, 1000], delays);
+        assert_eq!(5, backoff.attempts());
+        backoff.reset();
+        assert_eq!(Duration::from_millis(100), backoff.duration());
+
+        let mut backoff = Backoff::new(Duration::from_millis(1000), Duration::from_secs(5), 0.5);
+        // 0.25 has an even digit and shortens the delay, 0.15 lengthens it
+        assert_eq!(Duration::from_millis(875), backoff.duration_with(0.25));
+        backoff.reset();
+        assert_eq!(Duration::from_millis(1075), backoff.duration_with(0.15));
+        for _ in 0..100 {
+            let delay = backoff.duration();
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(5));
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use crate::socket_io_client::client_connection::{
+    ClientConnection, Close, Connect, Disconnect, Emit, GetTransport, On, OnManager,
+};
+use crate::socket_io_client::client_options::ClientOptions;
+use crate::socket_io_client::error::ClientError;
+use crate::socket_io_packet_codec::payload::Payload;
+use actix::Addr;
+use futures::channel::oneshot;
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+/// A Socket.IO client: the Engine.IO connection to a server, shared by the
+/// sockets of the namespaces.
+///
+/// The client runs on the actix arbiter it is connected from.
+/// ```no_run
+/// # use socket_io_rust::socket_io_client::client::Client;
+/// # use socket_io_rust::socket_io_client::client_options::ClientOptions;
+/// # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = Client::connect("http://localhost:8080", ClientOptions::default()).await?;
+/// let socket = client.socket("/");
+/// socket.on("chat message", |payload, _| println!("{:?}", payload));
+/// socket.connect(None).await?;
+/// socket.emit("chat message", "hello")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Client {
+    connection: Addr<ClientConnection>,
+}
+
+impl Client {
+    /// Opens an Engine.IO connection to the server at `url`, with the first
+    /// of the transports of the options. Once opened, the connection is
+    /// attempted again whenever it is lost, unless the options disable
+    /// reconnection.
+    pub async fn connect(url: &str, options: ClientOptions) -> Result<Client, ClientError> {
+        let (opened, receiver) = oneshot::channel();
+        let connection = ClientConnection::start(url, options, opened);
+        match receiver.await {
+            Ok(Ok(())) => Ok(Client { connection }),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(ClientError::Closed),
+        }
+    }
+
+    /// Registers a handler of an event of the connection: `reconnect_attempt`
+    /// with the number of the attempt, `reconnect_error` with the error of a
+    /// failed attempt, `reconnect` with the number of attempts it took,
+    /// `reconnect_failed` once the attempts are exhausted, and `upgrade` with
+    /// the name of the transport the connection upgraded to.
+    pub fn on<F>(&self, event: &str, handler: F)
+    where
+        F: Fn(Payload) + Send + 'static,
+    {
+        self.connection.do_send(OnManager {
+            event: event.to_string(),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Get the name of the transport the connection is using, none while it
+    /// reconnects
+    pub async fn transport(&self) -> Result<Option<&'static str>, ClientError> {
+        self.connection
+            .send(GetTransport)
+            .await
+            .map_err(|_| ClientError::Closed)
+    }
+
+    /// Get the socket of a namespace. The socket joins the namespace once
+    /// [`ClientSocket::connect`](struct.ClientSocket.html#method.connect) is
+    /// called.
+    pub fn socket(&self, nsp: &str) -> ClientSocket {
+        ClientSocket::new(self.connection.clone(), nsp)
+    }
+
+    /// Closes the connection, leaving every namespace
+    pub fn close(&self) {
+        self.connection.do_send(Close);
+    }
+
+    /// Whether the connection is still open
+    pub fn is_connected(&self) -> bool {
+        self.connection.connected()
+    }
+}
+
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Client")
+    }
+}
+
+/// The socket of a client on a namespace
+#[derive(Clone)]
+pub struct ClientSocket {
+    connection: Addr<ClientConnection>,
+    nsp: String,
+}
+
+impl ClientSocket {
+    pub(crate) fn new(connection: Addr<ClientConnection>, nsp: &str) -> Self {
+        ClientSocket {
+            connection,
+            nsp: nsp.to_string(),
+        }
+    }
+
+    /// Get the namespace of the socket
+    pub fn nsp(&self) -> &str {
+        &self.nsp
+    }
+
+    /// Registers a handler of an event. The reserved events `connect`,
+    /// `connect_error` and `disconnect` report the state of the socket.
+    pub fn on<F>(&self, event: &str, handler: F)
+    where
+        F: Fn(Payload, ClientSocket) + Send + 'static,
+    {
+        self.connection.do_send(On {
+            nsp: self.nsp.clone(),
+            event: event.to_string(),
+            handler: Box::new(handler),
+        });
+    }
+
+    /// Joins the namespace, sending the `auth` data to the server. Fails with
+    /// the data of the error when the server refuses.
+    pub async fn connect(&self, auth: Option<Value>) -> Result<(), ClientError> {
+        let (result, receiver) = oneshot::channel();
+        self.send(Connect {
+            nsp: self.nsp.clone(),
+            auth,
+            result,
+        })?;
+        receiver.await.unwrap_or(Err(ClientError::Closed))
+    }
+
+    /// Emits an event. While the socket is not connected, the event is
+    /// buffered until it is.
+    pub fn emit<P: Into<Payload>>(&self, event: &str, payload: P) -> Result<(), ClientError> {
+        self.send(Emit {
+            nsp: self.nsp.clone(),
+            event: event.to_string(),
+            payload: payload.into(),
+            ack: None,
+        })
+    }
+
+    /// Emits an event and waits for the server to acknowledge it, with the
+    /// arguments of the acknowledgement
+    pub async fn emit_with_ack<P: Into<Payload>>(
+        &self,
+        event: &str,
+        payload: P,
+        timeout: Duration,
+    ) -> Result<Payload, ClientError> {
+        let (result, receiver) = oneshot::channel();
+        self.send(Emit {
+            nsp: self.nsp.clone(),
+            event: event.to_string(),
+            payload: payload.into(),
+            ack: Some((result, timeout)),
+        })?;
+        receiver.await.unwrap_or(Err(ClientError::Closed))
+    }
+
+    /// Leaves the namespace
+    pub fn disconnect(&self) {
+        self.connection.do_send(Disconnect {
+            nsp: self.nsp.clone(),
+        });
+    }
+
+    fn send<M>(&self, msg: M) -> Result<(), ClientError>
+    where
+        M: actix::Message<Result = ()> + Send + 'static,
+        ClientConnection: actix::Handler<M>,
+    {
+        if !self.connection.connected() {
+            return Err(ClientError::Closed);
+        }
+        self.connection.do_send(msg);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ClientSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ClientSocket")
+            .field("nsp", &self.nsp)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_server::transport::TransportType;
+    use crate::socket_io::server_options::ServerOptions;
+    use crate::socket_io::socket_io_server::SocketIoServer;
+    use crate::socket_io_client::client::Client;
+    use crate::socket_io_client::client_options::ClientOptions;
+    use crate::socket_io_client::error::ClientError;
+    use crate::socket_io_packet_codec::payload::Payload;
+    use crate::socket_io_testing::test_server::relay_events;
+    use crate::socket_io_websocket::app_state::AppState;
+    use actix_rt::System;
+    use actix_web::dev::Server;
+    use actix_web::web::Data;
+    use actix_web::{test, App, HttpServer};
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+    use serde_json::json;
+    use std::net::TcpListener;
+    use std::time::Duration;
+
+    /// Starts a server relaying the events of its clients
+    fn start_server() -> test::TestServer {
+        let app_data = relaying_app_data();
+        test::start(move || App::new().service(SocketIoServer::get_scope(app_data.clone())))
+    }
+
+    /// Starts a server listening on its own system and thread, so that it
+    /// can be killed, with its connections, and started again on the same
+    /// address
+    fn start_server_on(listener: TcpListener) -> (Server, System) {
+        let (started, system) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let system = System::new("socket-io-server");
+            let app_data = relaying_app_data();
+            let server = HttpServer::new(move || {
+                App::new().service(SocketIoServer::get_scope(app_data.clone()))
+            })
+            .listen(listener)
+            .unwrap()
+            .workers(1)
+            .disable_signals()
+            .run();
+            let _ = started.send((server, System::current()));
+            system.run()
+        });
+        system.recv().unwrap()
+    }
+
+    fn relaying_app_data() -> Data<AppState> {
+        let app_data = SocketIoServer::get_app_data(ServerOptions::default());
+        for namespace in app_data
+            .namespace_manager
+            .write()
+            .unwrap()
+            .get_adaptors_mut()
+        {
+            relay_events(namespace);
+        }
+        app_data
+    }
+
+    async fn kill_server((server, system): (Server, System)) {
+        server.stop(false).await;
+        system.stop();
+    }
+
+    fn reconnecting_options() -> ClientOptions {
+        ClientOptions::builder()
+            .transports(vec![TransportType::Websocket])
+            .reconnection_delay(Duration::from_millis(50))
+            .reconnection_delay_max(Duration::from_millis(200))
+            .randomization_factor(0.0)
+            .build()
+    }
+
+    /// Connects to the root namespace and checks that the server echoes a
+    /// message
+    async fn echo(transports: Vec<TransportType>) {
+        let server = start_server();
+        let upgradable = transports.first() == Some(&TransportType::Polling)
+            && transports.contains(&TransportType::Websocket);
+        let options = ClientOptions::builder().transports(transports).build();
+        let client = Client::connect(&server.url(""), options).await.unwrap();
+        let (upgraded, mut upgrades) = mpsc::unbounded();
+        client.on("upgrade", move |payload| {
+            let _ = upgraded.unbounded_send(payload);
+        });
+        let socket = client.socket("/");
+        let (messages, mut received) = mpsc::unbounded();
+        socket.on("chat message", move |payload, _| {
+            let _ = messages.unbounded_send(payload);
+        });
+        socket.connect(None).await.unwrap();
+
+        socket.emit("chat message", "hello").unwrap();
+        assert_eq!(Some(Payload::from("hello")), received.next().await);
+        // once upgraded, the handler being registered before the transport
+        // is asked for
+        if upgradable {
+            if client.transport().await.unwrap() != Some("websocket") {
+                assert_eq!(Some(Payload::from("websocket")), upgrades.next().await);
+            }
+            assert_eq!(Some("websocket"), client.transport().await.unwrap());
+        }
+        socket.emit("chat message", "again").unwrap();
+        assert_eq!(Some(Payload::from("again")), received.next().await);
+        client.close();
+    }
+
+    #[actix_rt::test]
+    async fn websocket_test() {
+        echo(vec![TransportType::Websocket]).await;
+    }
+
+    #[actix_rt::test]
+    async fn polling_test() {
+        echo(vec![TransportType::Polling]).await;
+        echo(vec![TransportType::Polling, TransportType::Websocket]).await;
+    }
+
+    #[actix_rt::test]
+    async fn connect_error_test() {
+        let server = start_server();
+        let client = Client::connect(&server.url(""), ClientOptions::default())
+            .await
+            .unwrap();
+        match client
+            .socket("/admin")
+            .connect(Some(json!({ "token": "abc" })))
+            .await
+        {
+            Err(ClientError::ConnectError(data)) => {
+                assert_eq!(data, json!({ "message": "Invalid namespace" }))
+            }
+            res => panic!("unexpected result {:?}", res),
+        }
+
+        let socket = client.socket("/");
+        let ack = socket
+            .emit_with_ack("chat message", "hello", Duration::from_millis(50))
+            .await;
+        assert!(matches!(ack, Err(ClientError::Timeout)));
+    }
+
+    #[actix_rt::test]
+    async fn reconnect_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let url = format!("http://{}", addr);
+        let server = start_server_on(listener);
+        let client = Client::connect(&url, reconnecting_options()).await.unwrap();
+        let (events, mut received) = mpsc::unbounded();
+        for event in &["reconnect_attempt", "reconnect"] {
+            let events = events.clone();
+            let event = event.to_string();
+            client.on(&event.clone(), move |payload| {
+                let _ = events.unbounded_send((event.clone(), payload));
+            });
+        }
+        let socket = client.socket("/");
+        for event in &["connect", "disconnect", "chat message"] {
+            let events = events.clone();
+            let event = event.to_string();
+            socket.on(&event.clone(), move |payload, _| {
+                let _ = events.unbounded_send((event.clone(), payload));
+            });
+        }
+        socket.connect(None).await.unwrap();
+        assert_eq!("connect", received.next().await.unwrap().0);
+
+        kill_server(server).await;
+        let (event, reason) = received.next().await.unwrap();
+        assert_eq!("disconnect", event);
+        assert_ne!(Payload::from("io client disconnect"), reason);
+        // buffered until the socket joins the namespace again
+        socket.emit("chat message", "offline").unwrap();
+        let (event, attempt) = received.next().await.unwrap();
+        assert_eq!("reconnect_attempt", event);
+        assert_eq!(Payload::from(json!(1)), attempt);
+
+        let server = start_server_on(TcpListener::bind(addr).unwrap());
+        let mut reconnected = Vec::new();
+        while let Some((event, payload)) = received.next().await {
+            match event.as_str() {
+                "reconnect_attempt" => continue,
+                "chat message" => {
+                    assert_eq!(Payload::from("offline"), payload);
+                    break;
+                }
+                _ => reconnected.push(event),
+            }
+        }
+        assert_eq!(vec!["reconnect", "connect"], reconnected);
+        assert!(client.is_connected());
+        client.close();
+        kill_server(server).await;
+    }
+
+    #[actix_rt::test]
+    async fn reconnect_failed_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = start_server_on(listener);
+        let options = ClientOptions {
+            reconnection_attempts: Some(2),
+            emit_buffer_size: Some(1),
+            ..reconnecting_options()
+        };
+        let client = Client::connect(&url, options).await.unwrap();
+        let (events, mut received) = mpsc::unbounded();
+        for event in &["reconnect_attempt", "reconnect_failed"] {
+            let events = events.clone();
+            let event = event.to_string();
+            client.on(&event.clone(), move |_| {
+                let _ = events.unbounded_send(event.clone());
+            });
+        }
+        // the events end once the stopped connection drops its handlers
+        drop(events);
+        let socket = client.socket("/");
+        socket.connect(None).await.unwrap();
+
+        kill_server(server).await;
+        let first = received.next().await;
+        socket.emit("chat message", "buffered").unwrap();
+        let dropped = socket
+            .emit_with_ack("chat message", "dropped", Duration::from_secs(1))
+            .await;
+        assert!(matches!(dropped, Err(ClientError::BufferFull)));
+        let rest: Vec<String> = received.collect().await;
+        let mut events = vec![first.unwrap()];
+        events.extend(rest);
+        assert_eq!(
+            vec!["reconnect_attempt", "reconnect_attempt", "reconnect_failed"],
+            events
+        );
+        assert!(!client.is_connected());
+    }
+}
@@ -0,0 +1,911 @@
+use crate::engine_io_parser::packet::{Packet, PacketType};
+use crate::engine_io_server::transport::{
+    Transport, TransportError, TransportEvent, TransportEvents, TransportType,
+};
+use crate::socket_io_client::backoff::Backoff;
+use crate::socket_io_client::client::ClientSocket;
+use crate::socket_io_client::client_options::ClientOptions;
+use crate::socket_io_client::error::ClientError;
+use crate::socket_io_client::polling_transport::{PollingHandle, PollingTransport};
+use crate::socket_io_client::websocket_transport::WebsocketTransport;
+use crate::socket_io_packet_codec::attachments;
+use crate::socket_io_packet_codec::default_packet_codec::DefaultPacketCodec;
+use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use crate::socket_io_packet_codec::payload::Payload;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use crate::socket_io_packet_codec::SocketIoPacketCodec;
+use actix::fut::{ActorFuture, WrapFuture};
+use actix::{
+    Actor, ActorContext, Addr, AsyncContext, Context, Handler, Message, SpawnHandle, StreamHandler,
+};
+use futures::channel::oneshot;
+use futures::future::LocalBoxFuture;
+use futures::{FutureExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Handles an event received on a namespace
+pub(crate) type EventHandler = Box<dyn Fn(Payload, ClientSocket) + Send>;
+
+/// Handles an event of the manager
+pub(crate) type ManagerHandler = Box<dyn Fn(Payload) + Send>;
+
+/// Resolves a connection to a namespace
+pub(crate) type ConnectResult = oneshot::Sender<Result<(), ClientError>>;
+
+/// Resolves an event waiting for its acknowledgement
+pub(crate) type AckResult = oneshot::Sender<Result<Payload, ClientError>>;
+
+/// Why the connection was closed, given to the `disconnect` handlers
+pub(crate) mod close_reason {
+    pub const CLIENT_DISCONNECT: &str = "io client disconnect";
+    pub const SERVER_DISCONNECT: &str = "io server disconnect";
+    pub const PING_TIMEOUT: &str = "ping timeout";
+    pub const TRANSPORT_CLOSE: &str = "transport close";
+    pub const TRANSPORT_ERROR: &str = "transport error";
+    pub const PARSE_ERROR: &str = "parse error";
+}
+
+/// The state of the client on a namespace
+#[derive(Default)]
+struct NamespaceState {
+    handlers: HashMap<String, Vec<EventHandler>>,
+    /// Whether the client asked to join the namespace, and joins it again
+    /// once reconnected
+    active: bool,
+    /// The data sent when joining the namespace
+    auth: Option<Value>,
+    connected: bool,
+    /// Resolved once the server answers the connection request
+    connecting: Option<ConnectResult>,
+    /// Events emitted while the socket is not connected, with their
+    /// attachments
+    send_buffer: VecDeque<(SocketIoPacket, Vec<Vec<u8>>)>,
+}
+
+/// The Engine.IO connection of a client and the namespaces multiplexed on
+/// it. It mirrors the connection of the server: it starts with either
+/// polling or websocket, and a polling connection upgrades to websocket
+/// when the server allows it.
+///
+/// Once the connection is lost, new connections are attempted with an
+/// exponential backoff, and the namespaces are joined again.
+pub(crate) struct ClientConnection {
+    /// The URL of the server
+    url: String,
+    options: ClientOptions,
+    /// Engine.IO session id, known once the open packet is received
+    sid: Option<String>,
+    /// Server must ping within the ping interval and timeout, otherwise the
+    /// connection is lost
+    hb: Instant,
+    /// Checks the heartbeat of the current session
+    heartbeat: Option<SpawnHandle>,
+    /// Resolved once the first open packet is received
+    opened: Option<ConnectResult>,
+    /// The transport carrying the packets, none while reconnecting
+    transport: Option<Box<dyn Transport>>,
+    /// Identifies the events of the current transport
+    transport_id: usize,
+    /// The websocket the connection is upgrading to, being probed
+    upgrade: Option<(usize, Box<dyn Transport>)>,
+    /// Set once the server answered the probe. Polling is paused until its
+    /// pending requests complete, then the transports are switched.
+    upgrading: bool,
+    /// The polling transport, paused before the upgrade
+    polling: Option<PollingHandle>,
+    /// Identifies the events of the next transport
+    next_transport_id: usize,
+    /// Packets waiting for the transport to be writable
+    write_buffer: Vec<Packet>,
+    namespaces: HashMap<String, NamespaceState>,
+    handlers: HashMap<String, Vec<ManagerHandler>>,
+    /// Emitted events waiting for their acknowledgement
+    acks: HashMap<u32, AckResult>,
+    next_ack_id: u32,
+    /// A binary packet waiting for its attachments
+    incoming: Option<(SocketIoPacket, usize, Vec<Vec<u8>>)>,
+    /// The delays between reconnection attempts
+    backoff: Backoff,
+    /// Whether a reconnection is scheduled or in progress
+    reconnecting: bool,
+    /// The attempt waiting for the open packet
+    opening: Option<usize>,
+    next_attempt: usize,
+}
+
+impl Actor for ClientConnection {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.open(ctx);
+    }
+
+    fn stopped(&mut self, ctx: &mut Self::Context) {
+        if let Some(mut transport) = self.transport.take() {
+            spawn_ignored(transport.close());
+        }
+        if let Some((_, mut upgrade)) = self.upgrade.take() {
+            spawn_ignored(upgrade.close());
+        }
+        self.disconnect_namespaces(ctx, close_reason::CLIENT_DISCONNECT);
+    }
+}
+
+/// An event of one of the transports of the connection
+struct TaggedTransportEvent {
+    transport_id: usize,
+    event: TransportEvent,
+}
+
+/// Runs a transport future whose outcome does not matter
+fn spawn_ignored(fut: LocalBoxFuture<'static, Result<(), TransportError>>) {
+    actix::spawn(fut.map(|_| ()));
+}
+
+impl StreamHandler<TaggedTransportEvent> for ClientConnection {
+    fn handle(&mut self, item: TaggedTransportEvent, ctx: &mut Self::Context) {
+        let is_upgrade = |(id, _): &(usize, Box<dyn Transport>)| *id == item.transport_id;
+        if item.transport_id == self.transport_id && self.transport.is_some() {
+            match item.event {
+                TransportEvent::Packet(packet) => self.on_packet(ctx, packet),
+                TransportEvent::Drain => self.flush(ctx),
+                TransportEvent::Close => self.on_close(ctx, close_reason::TRANSPORT_CLOSE),
+                TransportEvent::Error(_) => self.on_close(ctx, close_reason::TRANSPORT_ERROR),
+            }
+        } else if self.upgrade.as_ref().is_some_and(is_upgrade) {
+            match item.event {
+                TransportEvent::Packet(packet) => self.on_upgrade_packet(ctx, packet),
+                TransportEvent::Drain => {}
+                // polling is paused once the probe succeeded, the connection
+                // cannot go on without the upgrade
+                TransportEvent::Close | TransportEvent::Error(_) if self.upgrading => {
+                    self.on_close(ctx, close_reason::TRANSPORT_ERROR)
+                }
+                // the probe failed, keep the current transport
+                TransportEvent::Close | TransportEvent::Error(_) => self.upgrade = None,
+            }
+        }
+    }
+
+    /// The transports report their closing as an event
+    fn finished(&mut self, _: &mut Self::Context) {}
+}
+
+/// Registers a handler of an event of a namespace
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct On {
+    pub nsp: String,
+    pub event: String,
+    pub handler: EventHandler,
+}
+
+/// Registers a handler of an event of the manager
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct OnManager {
+    pub event: String,
+    pub handler: ManagerHandler,
+}
+
+/// Asks the server to join a namespace
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Connect {
+    pub nsp: String,
+    pub auth: Option<Value>,
+    pub result: ConnectResult,
+}
+
+/// Emits an event on a namespace, acknowledged within the timeout when
+/// `ack` is given
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Emit {
+    pub nsp: String,
+    pub event: String,
+    pub payload: Payload,
+    pub ack: Option<(AckResult, Duration)>,
+}
+
+/// Leaves a namespace
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Disconnect {
+    pub nsp: String,
+}
+
+/// Closes the connection, without reconnecting
+#[derive(Message)]
+#[rtype(result = "()")]
+pub(crate) struct Close;
+
+/// Get the name of the transport carrying the packets, none while
+/// reconnecting
+#[derive(Message)]
+#[rtype(result = "Option<&'static str>")]
+pub(crate) struct GetTransport;
+
+impl Handler<On> for ClientConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: On, _: &mut Self::Context) -> Self::Result {
+        self.namespaces
+            .entry(msg.nsp)
+            .or_default()
+            .handlers
+            .entry(msg.event)
+            .or_default()
+            .push(msg.handler);
+    }
+}
+
+impl Handler<OnManager> for ClientConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: OnManager, _: &mut Self::Context) -> Self::Result {
+        self.handlers
+            .entry(msg.event)
+            .or_default()
+            .push(msg.handler);
+    }
+}
+
+impl Handler<Connect> for ClientConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: Connect, ctx: &mut Self::Context) -> Self::Result {
+        let namespace = self.namespaces.entry(msg.nsp.clone()).or_default();
+        if namespace.connected {
+            let _ = msg.result.send(Ok(()));
+            return;
+        }
+        namespace.active = true;
+        namespace.auth = msg.auth;
+        namespace.connecting = Some(msg.result);
+        // joined once the connection is open otherwise
+        if self.sid.is_some() {
+            self.send_connect(ctx, &msg.nsp);
+        }
+
+        let nsp = msg.nsp;
+        ctx.run_later(self.options.timeout, move |act, _| {
+            if let Some(namespace) = act.namespaces.get_mut(&nsp) {
+                if let Some(connecting) = namespace.connecting.take() {
+                    let _ = connecting.send(Err(ClientError::Timeout));
+                }
+            }
+        });
+    }
+}
+
+impl Handler<Emit> for ClientConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: Emit, ctx: &mut Self::Context) -> Self::Result {
+        let (args, buffers) = msg.payload.into_args();
+        let mut data = vec![Value::String(msg.event)];
+        data.extend(args);
+        let packet_type = if buffers.is_empty() {
+            SocketIoPacketType::Event
+        } else {
+            SocketIoPacketType::BinaryEvent
+        };
+        let mut packet = SocketIoPacket {
+            packet_type,
+            nsp: msg.nsp,
+            id: None,
+            data: Some(Value::Array(data)),
+        };
+
+        let namespace = self.namespaces.entry(packet.nsp.clone()).or_default();
+        let buffered = !namespace.connected;
+        let full = self
+            .options
+            .emit_buffer_size
+            .is_some_and(|size| namespace.send_buffer.len() >= size);
+        if buffered && full {
+            if let Some((result, _)) = msg.ack {
+                let _ = result.send(Err(ClientError::BufferFull));
+            }
+            return;
+        }
+
+        if let Some((result, timeout)) = msg.ack {
+            let id = self.next_ack_id;
+            self.next_ack_id = self.next_ack_id.wrapping_add(1);
+            self.acks.insert(id, result);
+            ctx.run_later(timeout, move |act, _| {
+                if let Some(result) = act.acks.remove(&id) {
+                    let _ = result.send(Err(ClientError::Timeout));
+                }
+            });
+            packet.id = Some(id);
+        }
+        if buffered {
+            namespace.send_buffer.push_back((packet, buffers));
+        } else {
+            self.send_socket_io_packet(ctx, &packet, buffers);
+        }
+    }
+}
+
+impl Handler<Disconnect> for ClientConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: Disconnect, ctx: &mut Self::Context) -> Self::Result {
+        let namespace = match self.namespaces.get_mut(&msg.nsp) {
+            Some(namespace) => namespace,
+            None => return,
+        };
+        namespace.active = false;
+        namespace.send_buffer.clear();
+        if !std::mem::replace(&mut namespace.connected, false) {
+            return;
+        }
+        let packet = SocketIoPacket {
+            packet_type: SocketIoPacketType::Disconnect,
+            nsp: msg.nsp.clone(),
+            id: None,
+            data: None,
+        };
+        self.send_socket_io_packet(ctx, &packet, Vec::new());
+        self.dispatch(
+            ctx,
+            &msg.nsp,
+            "disconnect",
+            close_reason::CLIENT_DISCONNECT.into(),
+        );
+    }
+}
+
+impl Handler<GetTransport> for ClientConnection {
+    type Result = Option<&'static str>;
+
+    fn handle(&mut self, _: GetTransport, _: &mut Self::Context) -> Self::Result {
+        self.transport.as_ref().map(|transport| transport.name())
+    }
+}
+
+impl Handler<Close> for ClientConnection {
+    type Result = ();
+
+    fn handle(&mut self, _: Close, ctx: &mut Self::Context) -> Self::Result {
+        ctx.stop();
+    }
+}
+
+impl ClientConnection {
+    /// Starts a connection to the server at `url`. `opened` is resolved once
+    /// the server sends the open packet, or the first attempt fails.
+    pub(crate) fn start(url: &str, options: ClientOptions, opened: ConnectResult) -> Addr<Self> {
+        let backoff = Backoff::new(
+            options.reconnection_delay,
+            options.reconnection_delay_max,
+            options.randomization_factor,
+        );
+        ClientConnection {
+            url: url.to_string(),
+            options,
+            sid: None,
+            hb: Instant::now(),
+            heartbeat: None,
+            opened: Some(opened),
+            transport: None,
+            transport_id: 0,
+            upgrade: None,
+            upgrading: false,
+            polling: None,
+            next_transport_id: 0,
+            write_buffer: Vec::new(),
+            namespaces: HashMap::new(),
+            handlers: HashMap::new(),
+            acks: HashMap::new(),
+            next_ack_id: 0,
+            incoming: None,
+            backoff,
+            reconnecting: false,
+            opening: None,
+            next_attempt: 0,
+        }
+        .start()
+    }
+
+    /// Opens a transport with the first of the transports of the options,
+    /// which must receive the open packet in time
+    fn open(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let transport = match self.options.transports.first().copied() {
+            // the transports of third parties are only served, polling stands
+            // in for them
+            Some(TransportType::Custom(_)) | None => TransportType::Polling,
+            Some(transport) => transport,
+        };
+        let url = self.options.engine_url(&self.url, transport, None);
+        let max_payload = self.options.max_payload;
+        let attempt = self.next_attempt;
+        self.next_attempt += 1;
+        self.opening = Some(attempt);
+        match transport {
+            TransportType::Websocket => {
+                let connecting =
+                    async move { WebsocketTransport::connect(&url, max_payload).await };
+                ctx.spawn(connecting.into_actor(self).map(move |result, act, ctx| {
+                    match result {
+                        // the attempt timed out meanwhile
+                        Ok((mut transport, _)) if act.opening != Some(attempt) => {
+                            spawn_ignored(transport.close())
+                        }
+                        Ok((transport, events)) => {
+                            act.set_transport(ctx, Box::new(transport), events, None)
+                        }
+                        Err(_) if act.opening != Some(attempt) => {}
+                        Err(e) => act.on_open_error(ctx, e.into()),
+                    }
+                }));
+            }
+            TransportType::Polling | TransportType::JsonpPolling | TransportType::Custom(_) => {
+                let (transport, polling, events) = PollingTransport::open(url, max_payload);
+                self.set_transport(ctx, Box::new(transport), events, Some(polling));
+            }
+        }
+        ctx.run_later(self.options.timeout, move |act, ctx| {
+            if act.opening == Some(attempt) {
+                act.on_open_error(ctx, ClientError::Timeout);
+            }
+        });
+    }
+
+    fn set_transport(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        transport: Box<dyn Transport>,
+        events: TransportEvents,
+        polling: Option<PollingHandle>,
+    ) {
+        self.transport_id = self.add_transport_events(ctx, events);
+        self.transport = Some(transport);
+        self.polling = polling;
+    }
+
+    /// Handles the events of a transport, tagged with the returned id
+    fn add_transport_events(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        events: TransportEvents,
+    ) -> usize {
+        let transport_id = self.next_transport_id;
+        self.next_transport_id += 1;
+        ctx.add_stream(events.map(move |event| TaggedTransportEvent {
+            transport_id,
+            event,
+        }));
+        transport_id
+    }
+
+    /// The transport failed before the session opened. The first attempt
+    /// reports the error, the next ones try again.
+    fn on_open_error(&mut self, ctx: &mut <Self as Actor>::Context, error: ClientError) {
+        self.opening = None;
+        self.drop_transports();
+        if let Some(opened) = self.opened.take() {
+            let _ = opened.send(Err(error));
+            ctx.stop();
+            return;
+        }
+        self.emit_manager(ctx, "reconnect_error", error.to_string().into());
+        self.reconnect(ctx);
+    }
+
+    /// The session was lost: the sockets are disconnected until the
+    /// reconnection, if enabled
+    fn on_close(&mut self, ctx: &mut <Self as Actor>::Context, reason: &'static str) {
+        if self.sid.is_none() {
+            self.on_open_error(ctx, ClientError::Closed);
+            return;
+        }
+        self.sid = None;
+        if let Some(heartbeat) = self.heartbeat.take() {
+            ctx.cancel_future(heartbeat);
+        }
+        self.drop_transports();
+        self.disconnect_namespaces(ctx, reason);
+        if self.options.reconnection {
+            self.reconnect(ctx);
+        } else {
+            ctx.stop();
+        }
+    }
+
+    /// Closes the transports and forgets what was on its way
+    fn drop_transports(&mut self) {
+        if let Some(mut transport) = self.transport.take() {
+            spawn_ignored(transport.close());
+        }
+        if let Some((_, mut upgrade)) = self.upgrade.take() {
+            spawn_ignored(upgrade.close());
+        }
+        self.upgrading = false;
+        self.polling = None;
+        self.write_buffer.clear();
+        self.incoming = None;
+    }
+
+    /// Lets the handlers of the connected namespaces know the sockets are
+    /// disconnected
+    fn disconnect_namespaces(&mut self, ctx: &mut <Self as Actor>::Context, reason: &'static str) {
+        let connected: Vec<String> = self
+            .namespaces
+            .iter_mut()
+            .filter(|(_, namespace)| namespace.connected)
+            .map(|(nsp, namespace)| {
+                namespace.connected = false;
+                nsp.clone()
+            })
+            .collect();
+        for nsp in connected {
+            self.dispatch(ctx, &nsp, "disconnect", reason.into());
+        }
+    }
+
+    /// Schedules the next reconnection attempt, or gives up once the attempts
+    /// are exhausted
+    fn reconnect(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let exhausted = self
+            .options
+            .reconnection_attempts
+            .is_some_and(|attempts| self.backoff.attempts() >= attempts);
+        if exhausted {
+            self.emit_manager(ctx, "reconnect_failed", Payload::Text(Vec::new()));
+            ctx.stop();
+            return;
+        }
+        self.reconnecting = true;
+        let delay = self.backoff.duration();
+        ctx.run_later(delay, |act, ctx| {
+            let attempt = json!(act.backoff.attempts());
+            act.emit_manager(ctx, "reconnect_attempt", attempt.into());
+            act.open(ctx);
+        });
+    }
+
+    /// Sends a packet through the current transport, once it is writable
+    fn send_packet(&mut self, ctx: &mut <Self as Actor>::Context, packet: Packet) {
+        self.write_buffer.push(packet);
+        self.flush(ctx);
+    }
+
+    /// Sends the buffered packets, if the transport is writable. Once polling
+    /// is paused for the upgrade, the packets wait for the websocket.
+    fn flush(&mut self, ctx: &mut <Self as Actor>::Context) {
+        if self.upgrading {
+            if self.polling.as_ref().is_none_or(PollingHandle::is_paused) {
+                self.finish_upgrade(ctx);
+            }
+            return;
+        }
+        let transport = match self.transport.as_mut() {
+            Some(transport) if transport.writable() && !self.write_buffer.is_empty() => transport,
+            _ => return,
+        };
+        let sent = transport.send(std::mem::take(&mut self.write_buffer), false);
+        let transport_id = self.transport_id;
+        ctx.spawn(sent.into_actor(self).map(move |result, act, ctx| {
+            if result.is_err() && act.transport_id == transport_id {
+                act.on_close(ctx, close_reason::TRANSPORT_ERROR);
+            }
+        }));
+    }
+
+    /// Sends a Socket.IO packet, followed by its binary attachments
+    fn send_socket_io_packet(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        packet: &SocketIoPacket,
+        buffers: Vec<Vec<u8>>,
+    ) {
+        let text = match DefaultPacketCodec.encode_packet_to_string(packet) {
+            Ok(text) => text,
+            Err(err) => {
+                warn!(nsp = %packet.nsp, %err, "packet not encoded");
+                // An event waiting for its acknowledgement fails instead
+                let is_event = matches!(
+                    packet.packet_type,
+                    SocketIoPacketType::Event | SocketIoPacketType::BinaryEvent
+                );
+                if let Some(result) = packet
+                    .id
+                    .filter(|_| is_event)
+                    .and_then(|id| self.acks.remove(&id))
+                {
+                    let _ = result.send(Err(ClientError::Encode(err)));
+                }
+                return;
+            }
+        };
+        self.write_buffer
+            .push(Packet::new(PacketType::Message, text.into_bytes()));
+        self.write_buffer
+            .extend(buffers.into_iter().map(Packet::binary));
+        self.flush(ctx);
+    }
+
+    /// Asks the server to join a namespace, with its auth data
+    fn send_connect(&mut self, ctx: &mut <Self as Actor>::Context, nsp: &str) {
+        let auth = self
+            .namespaces
+            .get(nsp)
+            .and_then(|namespace| namespace.auth.clone());
+        let packet = SocketIoPacket {
+            packet_type: SocketIoPacketType::Connect,
+            nsp: nsp.to_string(),
+            id: None,
+            data: auth,
+        };
+        self.send_socket_io_packet(ctx, &packet, Vec::new());
+    }
+
+    fn on_packet(&mut self, ctx: &mut <Self as Actor>::Context, packet: Packet) {
+        match packet.packet_type {
+            PacketType::Open => self.on_open(ctx, &packet.data),
+            PacketType::Ping => {
+                self.hb = Instant::now();
+                self.send_packet(ctx, Packet::new(PacketType::Pong, packet.data));
+            }
+            PacketType::Message if packet.binary => self.on_attachment(ctx, packet.data),
+            PacketType::Message => {
+                let decoded = std::str::from_utf8(&packet.data)
+                    .ok()
+                    .and_then(|data| DefaultPacketCodec.decode(data).ok());
+                match decoded {
+                    Some(packet) => self.on_socket_io_packet(ctx, packet),
+                    None => self.on_close(ctx, close_reason::PARSE_ERROR),
+                }
+            }
+            PacketType::Close => self.on_close(ctx, close_reason::TRANSPORT_CLOSE),
+            _ => {}
+        }
+    }
+
+    /// Starts the heartbeat and the upgrade, as the open packet tells, and
+    /// joins the namespaces again after a reconnection
+    fn on_open(&mut self, ctx: &mut <Self as Actor>::Context, data: &[u8]) {
+        let handshake: Value = serde_json::from_slice(data).unwrap_or(Value::Null);
+        let sid = handshake["sid"].as_str();
+        let ping_interval = handshake["pingInterval"].as_u64();
+        let ping_timeout = handshake["pingTimeout"].as_u64();
+        let (sid, ping_interval, ping_timeout) = match (sid, ping_interval, ping_timeout) {
+            (Some(sid), Some(ping_interval), Some(ping_timeout)) => (
+                sid.to_string(),
+                Duration::from_millis(ping_interval),
+                Duration::from_millis(ping_timeout),
+            ),
+            _ => {
+                let message = String::from_utf8_lossy(data).to_string();
+                self.on_open_error(ctx, ClientError::InvalidHandshake(message));
+                return;
+            }
+        };
+        self.sid = Some(sid);
+        self.opening = None;
+        self.hb = Instant::now();
+        self.heartbeat = Some(ctx.run_interval(ping_interval, move |act, ctx| {
+            if Instant::now().duration_since(act.hb) > ping_interval + ping_timeout {
+                act.on_close(ctx, close_reason::PING_TIMEOUT);
+            }
+        }));
+        if let Some(opened) = self.opened.take() {
+            let _ = opened.send(Ok(()));
+        }
+        if self.reconnecting {
+            self.reconnecting = false;
+            let attempts = json!(self.backoff.attempts());
+            self.backoff.reset();
+            self.emit_manager(ctx, "reconnect", attempts.into());
+        }
+        let active: Vec<String> = self
+            .namespaces
+            .iter()
+            .filter(|(_, namespace)| namespace.active)
+            .map(|(nsp, _)| nsp.clone())
+            .collect();
+        for nsp in active {
+            self.send_connect(ctx, &nsp);
+        }
+
+        let upgrades = handshake["upgrades"].as_array();
+        let websocket = TransportType::Websocket;
+        let upgradable = self.options.upgrade
+            && self.options.transports.contains(&websocket)
+            && upgrades.is_some_and(|upgrades| upgrades.iter().any(|u| u == websocket.name()));
+        if upgradable {
+            self.probe(ctx);
+        }
+    }
+
+    /// Opens a websocket to the session and probes it, the upgrade goes on
+    /// once the server answers the probe
+    fn probe(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let url = self
+            .options
+            .engine_url(&self.url, TransportType::Websocket, self.sid.as_deref());
+        let max_payload = self.options.max_payload;
+        let transport_id = self.transport_id;
+        let connecting = async move { WebsocketTransport::connect(&url, max_payload).await };
+        ctx.spawn(connecting.into_actor(self).map(move |result, act, ctx| {
+            // the session may already be gone
+            let (mut transport, events) = match result {
+                Ok(opened) if act.transport_id == transport_id && act.transport.is_some() => opened,
+                _ => return,
+            };
+            let probe = Packet::new(PacketType::Ping, b"probe".to_vec());
+            spawn_ignored(transport.send(vec![probe], false));
+            let upgrade_id = act.add_transport_events(ctx, events);
+            act.upgrade = Some((upgrade_id, Box::new(transport)));
+        }));
+    }
+
+    /// Handles the packets of the websocket being probed. Once the server
+    /// answered the probe, polling is paused.
+    fn on_upgrade_packet(&mut self, ctx: &mut <Self as Actor>::Context, packet: Packet) {
+        if packet.packet_type == PacketType::Pong && packet.data == b"probe" {
+            self.upgrading = true;
+            if let Some(polling) = self.polling.as_ref() {
+                polling.pause();
+            }
+            self.flush(ctx);
+        }
+    }
+
+    /// Switches to the websocket with the upgrade packet, once polling has no
+    /// request in flight
+    fn finish_upgrade(&mut self, ctx: &mut <Self as Actor>::Context) {
+        self.upgrading = false;
+        self.polling = None;
+        if let Some((transport_id, mut transport)) = self.upgrade.take() {
+            let upgrade = Packet::new(PacketType::Upgrade, Vec::new());
+            spawn_ignored(transport.send(vec![upgrade], false));
+            // dropping the polling transport stops it without closing the
+            // session
+            let name = Payload::from(transport.name());
+            self.transport = Some(transport);
+            self.transport_id = transport_id;
+            self.emit_manager(ctx, "upgrade", name);
+        }
+        self.flush(ctx);
+    }
+
+    /// Handles an attachment of the binary packet being received
+    fn on_attachment(&mut self, ctx: &mut <Self as Actor>::Context, data: Vec<u8>) {
+        let complete = match self.incoming.as_mut() {
+            Some((_, expected, buffers)) => {
+                buffers.push(data);
+                buffers.len() == *expected
+            }
+            None => false,
+        };
+        if complete {
+            if let Some((packet, _, buffers)) = self.incoming.take() {
+                self.on_message(ctx, packet, buffers);
+            }
+        }
+    }
+
+    fn on_socket_io_packet(&mut self, ctx: &mut <Self as Actor>::Context, packet: SocketIoPacket) {
+        match packet.packet_type {
+            SocketIoPacketType::Connect => self.on_connect(ctx, &packet.nsp),
+            SocketIoPacketType::Disconnect => {
+                if let Some(namespace) = self.namespaces.get_mut(&packet.nsp) {
+                    // the server does not want the client back
+                    namespace.active = false;
+                    if std::mem::replace(&mut namespace.connected, false) {
+                        let reason = close_reason::SERVER_DISCONNECT.into();
+                        self.dispatch(ctx, &packet.nsp, "disconnect", reason);
+                    }
+                }
+            }
+            SocketIoPacketType::Error => {
+                let data = packet.data.unwrap_or(Value::Null);
+                if let Some(namespace) = self.namespaces.get_mut(&packet.nsp) {
+                    namespace.active = false;
+                    if let Some(connecting) = namespace.connecting.take() {
+                        let _ = connecting.send(Err(ClientError::ConnectError(data.clone())));
+                    }
+                }
+                self.dispatch(ctx, &packet.nsp, "connect_error", data.into());
+            }
+            SocketIoPacketType::BinaryEvent | SocketIoPacketType::BinaryAck => {
+                let expected = packet
+                    .data
+                    .as_ref()
+                    .map_or(0, |data| attachments::placeholders(data).len());
+                if expected == 0 {
+                    self.on_message(ctx, packet, Vec::new());
+                } else {
+                    self.incoming = Some((packet, expected, Vec::new()));
+                }
+            }
+            SocketIoPacketType::Event | SocketIoPacketType::Ack => {
+                self.on_message(ctx, packet, Vec::new())
+            }
+        }
+    }
+
+    /// The socket joined a namespace, the events emitted meanwhile are sent
+    fn on_connect(&mut self, ctx: &mut <Self as Actor>::Context, nsp: &str) {
+        let namespace = self.namespaces.entry(nsp.to_string()).or_default();
+        namespace.connected = true;
+        if let Some(connecting) = namespace.connecting.take() {
+            let _ = connecting.send(Ok(()));
+        }
+        let buffered = std::mem::take(&mut namespace.send_buffer);
+        self.dispatch(ctx, nsp, "connect", Payload::Text(Vec::new()));
+        for (packet, buffers) in buffered {
+            self.send_socket_io_packet(ctx, &packet, buffers);
+        }
+    }
+
+    /// Handles an event or an acknowledgement, with its attachments
+    fn on_message(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        packet: SocketIoPacket,
+        buffers: Vec<Vec<u8>>,
+    ) {
+        let mut args = match packet.data {
+            Some(Value::Array(args)) => args,
+            _ => return,
+        };
+        match packet.packet_type {
+            SocketIoPacketType::Event | SocketIoPacketType::BinaryEvent => {
+                let event = match args.first() {
+                    Some(Value::String(_)) => args.remove(0),
+                    _ => return,
+                };
+                let payload = Payload::from_args(args, buffers);
+                self.dispatch(
+                    ctx,
+                    &packet.nsp,
+                    event.as_str().unwrap_or_default(),
+                    payload,
+                );
+            }
+            _ => {
+                let result = packet.id.and_then(|id| self.acks.remove(&id));
+                if let Some(result) = result {
+                    let _ = result.send(Ok(Payload::from_args(args, buffers)));
+                }
+            }
+        }
+    }
+
+    /// Calls the handlers of an event of a namespace
+    fn dispatch(
+        &self,
+        ctx: &mut <Self as Actor>::Context,
+        nsp: &str,
+        event: &str,
+        payload: Payload,
+    ) {
+        let handlers = self
+            .namespaces
+            .get(nsp)
+            .and_then(|namespace| namespace.handlers.get(event));
+        if let Some(handlers) = handlers {
+            for handler in handlers {
+                handler(payload.clone(), ClientSocket::new(ctx.address(), nsp));
+            }
+        }
+    }
+
+    /// Calls the handlers of an event of the manager
+    fn emit_manager(&self, _: &mut <Self as Actor>::Context, event: &str, payload: Payload) {
+        if let Some(handlers) = self.handlers.get(event) {
+            for handler in handlers {
+                handler(payload.clone());
+            }
+        }
+    }
+}
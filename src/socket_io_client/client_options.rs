@@ -0,0 +1,193 @@
+use crate::engine_io_server::transport::TransportType;
+use std::time::Duration;
+
+/// Options of the Socket.IO client. Built with
+/// [`ClientOptions::builder`](#method.builder).
+#[derive(Debug, Clone)]
+pub struct ClientOptions {
+    /// The path the server is reachable at
+    pub path: String,
+    /// The transports to try, the first one opens the connection
+    pub transports: Vec<TransportType>,
+    /// Whether a polling connection upgrades to websocket when the server
+    /// allows it
+    pub upgrade: bool,
+    /// Additional query parameters of the requests
+    pub query: Vec<(String, String)>,
+    /// The maximum size of a received message, in bytes
+    pub max_payload: usize,
+    /// How long the Engine.IO handshake, or joining a namespace, may take
+    pub timeout: Duration,
+    /// Whether the manager reconnects once the connection is lost
+    pub reconnection: bool,
+    /// How many reconnection attempts are made before giving up, unlimited
+    /// when `None`
+    pub reconnection_attempts: Option<u32>,
+    /// The delay before the first reconnection attempt, doubled on each
+    /// attempt
+    pub reconnection_delay: Duration,
+    /// The maximum delay between reconnection attempts
+    pub reconnection_delay_max: Duration,
+    /// How much each delay deviates randomly, between 0 and 1
+    pub randomization_factor: f64,
+    /// The maximum number of events buffered by a socket while it is not
+    /// connected, unlimited when `None`. Further events are dropped.
+    pub emit_buffer_size: Option<usize>,
+}
+
+impl Default for ClientOptions {
+    fn default() -> Self {
+        Self {
+            path: String::from("/socket.io/"),
+            transports: vec![TransportType::Polling, TransportType::Websocket],
+            upgrade: true,
+            query: Vec::new(),
+            max_payload: 1_000_000,
+            timeout: Duration::from_millis(20000),
+            reconnection: true,
+            reconnection_attempts: None,
+            reconnection_delay: Duration::from_millis(1000),
+            reconnection_delay_max: Duration::from_millis(5000),
+            randomization_factor: 0.5,
+            emit_buffer_size: None,
+        }
+    }
+}
+
+impl ClientOptions {
+    pub fn builder() -> ClientOptionsBuilder {
+        ClientOptionsBuilder::default()
+    }
+
+    /// Get the Engine.IO URL of a transport, joining the session `sid` when
+    /// given
+    pub(crate) fn engine_url(
+        &self,
+        url: &str,
+        transport: TransportType,
+        sid: Option<&str>,
+    ) -> String {
+        let mut query = vec![("EIO", "4"), ("transport", transport.name())];
+        if let Some(sid) = sid {
+            query.push(("sid", sid));
+        }
+        query.extend(self.query.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+        let query = serde_urlencoded::to_string(query).unwrap_or_default();
+
+        let url = url.trim_end_matches('/');
+        let url = match transport {
+            TransportType::Websocket if url.starts_with("https://") => {
+                url.replacen("https://", "wss://", 1)
+            }
+            TransportType::Websocket if url.starts_with("http://") => {
+                url.replacen("http://", "ws://", 1)
+            }
+            _ => url.to_string(),
+        };
+        format!("{}{}?{}", url, self.path, query)
+    }
+}
+
+/// Builds [`ClientOptions`](struct.ClientOptions.html), starting from the
+/// defaults
+/// ```rust
+/// # use socket_io_rust::engine_io_server::transport::TransportType;
+/// # use socket_io_rust::socket_io_client::client_options::ClientOptions;
+/// let options = ClientOptions::builder()
+///     .transports(vec![TransportType::Websocket])
+///     .query("token", "abc")
+///     .build();
+/// assert!(!options.transports.contains(&TransportType::Polling));
+/// ```
+#[derive(Debug, Default)]
+pub struct ClientOptionsBuilder {
+    options: ClientOptions,
+}
+
+impl ClientOptionsBuilder {
+    pub fn path(mut self, path: &str) -> Self {
+        self.options.path = path.to_string();
+        self
+    }
+
+    pub fn transports(mut self, transports: Vec<TransportType>) -> Self {
+        self.options.transports = transports;
+        self
+    }
+
+    pub fn upgrade(mut self, upgrade: bool) -> Self {
+        self.options.upgrade = upgrade;
+        self
+    }
+
+    /// Adds a query parameter to the requests
+    pub fn query(mut self, key: &str, value: &str) -> Self {
+        self.options
+            .query
+            .push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn max_payload(mut self, max_payload: usize) -> Self {
+        self.options.max_payload = max_payload;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = timeout;
+        self
+    }
+
+    pub fn reconnection(mut self, reconnection: bool) -> Self {
+        self.options.reconnection = reconnection;
+        self
+    }
+
+    pub fn reconnection_attempts(mut self, reconnection_attempts: u32) -> Self {
+        self.options.reconnection_attempts = Some(reconnection_attempts);
+        self
+    }
+
+    pub fn reconnection_delay(mut self, reconnection_delay: Duration) -> Self {
+        self.options.reconnection_delay = reconnection_delay;
+        self
+    }
+
+    pub fn reconnection_delay_max(mut self, reconnection_delay_max: Duration) -> Self {
+        self.options.reconnection_delay_max = reconnection_delay_max;
+        self
+    }
+
+    pub fn randomization_factor(mut self, randomization_factor: f64) -> Self {
+        self.options.randomization_factor = randomization_factor;
+        self
+    }
+
+    pub fn emit_buffer_size(mut self, emit_buffer_size: usize) -> Self {
+        self.options.emit_buffer_size = Some(emit_buffer_size);
+        self
+    }
+
+    pub fn build(self) -> ClientOptions {
+        self.options
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_server::transport::TransportType;
+    use crate::socket_io_client::client_options::ClientOptions;
+
+    #[test]
+    fn engine_url_test() {
+        let options = ClientOptions::builder().query("token", "a b").build();
+        assert_eq!(
+            options.engine_url("http://localhost:8080/", TransportType::Polling, None),
+            "http://localhost:8080/socket.io/?EIO=4&transport=polling&token=a+b"
+        );
+        assert_eq!(
+            options.engine_url("https://example.com", TransportType::Websocket, Some("abc")),
+            "wss://example.com/socket.io/?EIO=4&transport=websocket&sid=abc&token=a+b"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use crate::engine_io_server::transport::TransportError;
+use crate::socket_io_packet_codec::PacketCodecError;
+use serde_json::Value;
+use thiserror::Error;
+
+/// Why a request of the client failed
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("Transport error: {0}")]
+    Transport(#[from] TransportError),
+    /// The packet of the event could not be encoded
+    #[error("Encoding error: {0}")]
+    Encode(#[from] PacketCodecError),
+    #[error("Invalid handshake: {0}")]
+    InvalidHandshake(String),
+    /// The server refused the connection to the namespace, with the data of
+    /// its error packet
+    #[error("Connection to the namespace refused: {0}")]
+    ConnectError(Value),
+    #[error("The connection is closed")]
+    Closed,
+    #[error("Timed out")]
+    Timeout,
+    /// The socket is not connected and its buffer of events is full
+    #[error("The buffer of events is full")]
+    BufferFull,
+}
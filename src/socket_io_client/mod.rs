@@ -0,0 +1,23 @@
+//! A native Socket.IO client, speaking the protocol of the server over the
+//! websocket and polling transports
+
+use crate::engine_io_server::transport::TransportError;
+use std::{fmt, io};
+
+mod backoff;
+pub mod client;
+mod client_connection;
+pub mod client_options;
+pub mod error;
+mod polling_transport;
+mod websocket_transport;
+
+/// Reports a failed request of a client transport
+fn transport_error(e: impl fmt::Display) -> TransportError {
+    TransportError::Io(io::Error::other(e.to_string()))
+}
+
+/// Reports data a client transport could not decode
+fn invalid_data(e: impl fmt::Display) -> TransportError {
+    TransportError::InvalidData(e.to_string())
+}
@@ -0,0 +1,283 @@
+use crate::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use crate::engine_io_parser::packet::{Packet, PacketType};
+use crate::engine_io_parser::EngineIoParser;
+use crate::engine_io_server::transport::{
+    Transport, TransportError, TransportEvent, TransportEvents,
+};
+use crate::socket_io_client::{invalid_data, transport_error};
+use actix_web::client::Client;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+
+/// The state of the polling transport, shared with its request loops
+#[derive(Debug, Default)]
+struct PollingState {
+    /// Engine.IO session id, learned from the open packet
+    sid: Option<String>,
+    /// Whether a GET request is in flight
+    polling: bool,
+    /// Whether a POST request is in flight
+    writing: bool,
+    /// Set when the connection upgrades, no more GET requests are sent
+    paused: bool,
+    closed: bool,
+}
+
+impl PollingState {
+    fn url(&self, url: &str) -> String {
+        match self.sid.as_ref() {
+            Some(sid) => format!("{}&sid={}", url, sid),
+            None => url.to_string(),
+        }
+    }
+}
+
+/// Pauses the polling transport of a connection, before it upgrades
+#[derive(Debug, Clone)]
+pub(crate) struct PollingHandle {
+    state: Arc<Mutex<PollingState>>,
+}
+
+impl PollingHandle {
+    /// Stops sending GET requests. A drain event is reported once the
+    /// pending one completes.
+    pub(crate) fn pause(&self) {
+        self.state.lock().unwrap().paused = true;
+    }
+
+    /// Whether polling is paused with no request in flight
+    pub(crate) fn is_paused(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.paused && !state.polling && !state.writing
+    }
+}
+
+/// A payload to post, and where to report the outcome
+type Write = (String, oneshot::Sender<Result<(), TransportError>>);
+
+/// The HTTP long-polling transport of the client. A GET request is always
+/// pending to receive packets, and packets are sent in one POST request at a
+/// time, so the transport is not writable while one is in flight.
+///
+/// Dropping the transport stops polling without closing the session, which
+/// is how the connection leaves it once upgraded.
+#[derive(Debug)]
+pub(crate) struct PollingTransport {
+    state: Arc<Mutex<PollingState>>,
+    writes: mpsc::UnboundedSender<Write>,
+}
+
+impl PollingTransport {
+    /// Starts polling the Engine.IO URL of the server. The first response
+    /// carries the open packet.
+    pub(crate) fn open(url: String, max_payload: usize) -> (Self, PollingHandle, TransportEvents) {
+        let state = Arc::new(Mutex::new(PollingState::default()));
+        let (events, receiver) = mpsc::unbounded();
+        let (writes, pending_writes) = mpsc::unbounded();
+        // long polls last up to the ping interval, the connection watches
+        // the heartbeat instead
+        let client = Client::build().disable_timeout().finish();
+
+        actix::spawn(poll(
+            client.clone(),
+            url.clone(),
+            state.clone(),
+            events.clone(),
+            max_payload,
+        ));
+        actix::spawn(write(client, url, state.clone(), pending_writes, events));
+        let handle = PollingHandle {
+            state: state.clone(),
+        };
+        (PollingTransport { state, writes }, handle, receiver)
+    }
+}
+
+impl Drop for PollingTransport {
+    fn drop(&mut self) {
+        self.state.lock().unwrap().closed = true;
+    }
+}
+
+impl Transport for PollingTransport {
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+
+    fn writable(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        state.sid.is_some() && !state.writing && !state.closed
+    }
+
+    fn send(
+        &mut self,
+        packets: Vec<Packet>,
+        _compress: bool,
+    ) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        if !self.writable() {
+            return future::err(TransportError::NotWritable).boxed_local();
+        }
+        let payload = match EngineIOParserImpl::encode_payload(packets) {
+            Ok(payload) => payload,
+            Err(e) => return future::err(invalid_data(e)).boxed_local(),
+        };
+        self.state.lock().unwrap().writing = true;
+        let (result, receiver) = oneshot::channel();
+        if self.writes.unbounded_send((payload, result)).is_err() {
+            return future::err(TransportError::Closed).boxed_local();
+        }
+        receiver
+            .map(|result| result.unwrap_or(Err(TransportError::Closed)))
+            .boxed_local()
+    }
+
+    /// Posts a close packet, which ends the session
+    fn close(&mut self) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return future::ok(()).boxed_local();
+        }
+        let opened = state.sid.is_some();
+        state.writing = false;
+        drop(state);
+
+        let sent = if opened {
+            self.send(vec![Packet::new(PacketType::Close, Vec::new())], false)
+        } else {
+            future::ok(()).boxed_local()
+        };
+        self.state.lock().unwrap().closed = true;
+        sent
+    }
+}
+
+/// Sends GET requests until the transport is closed or paused, reporting the
+/// packets they receive
+async fn poll(
+    client: Client,
+    url: String,
+    state: Arc<Mutex<PollingState>>,
+    events: mpsc::UnboundedSender<TransportEvent>,
+    max_payload: usize,
+) {
+    loop {
+        let url = {
+            let mut state = state.lock().unwrap();
+            if state.closed {
+                return;
+            }
+            if state.paused {
+                let _ = events.unbounded_send(TransportEvent::Drain);
+                return;
+            }
+            state.polling = true;
+            state.url(&url)
+        };
+        let packets = get(&client, &url, max_payload).await;
+        let mut locked = state.lock().unwrap();
+        locked.polling = false;
+        if locked.closed {
+            return;
+        }
+        drop(locked);
+        let packets = match packets {
+            Ok(packets) => packets,
+            Err(e) => {
+                state.lock().unwrap().closed = true;
+                let _ = events.unbounded_send(TransportEvent::Error(e));
+                return;
+            }
+        };
+
+        for packet in packets {
+            match packet.packet_type {
+                PacketType::Open => {
+                    let sid = serde_json::from_slice::<Value>(&packet.data)
+                        .ok()
+                        .and_then(|open| open["sid"].as_str().map(str::to_string));
+                    state.lock().unwrap().sid = sid;
+                    let _ = events.unbounded_send(TransportEvent::Packet(packet));
+                    let _ = events.unbounded_send(TransportEvent::Drain);
+                }
+                PacketType::Close => {
+                    state.lock().unwrap().closed = true;
+                    let _ = events.unbounded_send(TransportEvent::Close);
+                    return;
+                }
+                _ => {
+                    let _ = events.unbounded_send(TransportEvent::Packet(packet));
+                }
+            }
+        }
+    }
+}
+
+async fn get(
+    client: &Client,
+    url: &str,
+    max_payload: usize,
+) -> Result<Vec<Packet>, TransportError> {
+    let mut res = client.get(url).send().await.map_err(transport_error)?;
+    if !res.status().is_success() {
+        return Err(TransportError::InvalidData(format!(
+            "unexpected status {}",
+            res.status()
+        )));
+    }
+    let body = res
+        .body()
+        .limit(max_payload)
+        .await
+        .map_err(transport_error)?;
+    let body = std::str::from_utf8(&body).map_err(invalid_data)?;
+    EngineIOParserImpl::decode_payload(body).map_err(invalid_data)
+}
+
+/// Posts the payloads the transport sends, one at a time
+async fn write(
+    client: Client,
+    url: String,
+    state: Arc<Mutex<PollingState>>,
+    mut pending_writes: mpsc::UnboundedReceiver<Write>,
+    events: mpsc::UnboundedSender<TransportEvent>,
+) {
+    while let Some((payload, result)) = pending_writes.next().await {
+        let url = state.lock().unwrap().url(&url);
+        let posted = post(&client, &url, payload).await;
+        let mut state = state.lock().unwrap();
+        state.writing = false;
+        let closed = state.closed;
+        drop(state);
+
+        let failed = posted.is_err();
+        let _ = result.send(posted);
+        if closed {
+            continue;
+        }
+        let event = if failed {
+            TransportEvent::Error(TransportError::Closed)
+        } else {
+            TransportEvent::Drain
+        };
+        let _ = events.unbounded_send(event);
+    }
+}
+
+async fn post(client: &Client, url: &str, payload: String) -> Result<(), TransportError> {
+    let res = client
+        .post(url)
+        .content_type("text/plain;charset=UTF-8")
+        .send_body(payload)
+        .await
+        .map_err(transport_error)?;
+    if !res.status().is_success() {
+        return Err(TransportError::InvalidData(format!(
+            "unexpected status {}",
+            res.status()
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use crate::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use crate::engine_io_parser::packet::Packet;
+use crate::engine_io_parser::EngineIoParser;
+use crate::engine_io_server::transport::{
+    Transport, TransportError, TransportEvent, TransportEvents,
+};
+use crate::socket_io_client::{invalid_data, transport_error};
+use actix_http::ws::{CloseCode, Frame, Message, ProtocolError};
+use actix_web::client::Client;
+use actix_web::web::Bytes;
+use futures::channel::mpsc;
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use futures::StreamExt;
+
+/// The websocket transport of the client. The frames are written and read by
+/// tasks of the current arbiter, the transport only queues the messages to
+/// write.
+#[derive(Debug)]
+pub(crate) struct WebsocketTransport {
+    messages: mpsc::UnboundedSender<Message>,
+}
+
+impl WebsocketTransport {
+    /// Opens a websocket to the Engine.IO URL of the server
+    pub(crate) async fn connect(
+        url: &str,
+        max_payload: usize,
+    ) -> Result<(Self, TransportEvents), TransportError> {
+        let (_, framed) = Client::new()
+            .ws(url)
+            .max_frame_size(max_payload)
+            .connect()
+            .await
+            .map_err(transport_error)?;
+        let (sink, mut stream) = framed.split();
+        let (messages, outgoing) = mpsc::unbounded();
+        let (events, receiver) = mpsc::unbounded();
+
+        actix::spawn(
+            outgoing
+                .map(Ok::<_, ProtocolError>)
+                .forward(sink)
+                .map(|_| ()),
+        );
+        let pongs = messages.clone();
+        actix::spawn(async move {
+            while let Some(frame) = stream.next().await {
+                let event = match frame {
+                    Ok(Frame::Text(text)) => std::str::from_utf8(&text)
+                        .map_err(invalid_data)
+                        .and_then(|text| {
+                            EngineIOParserImpl::decode_packet_str(text).map_err(invalid_data)
+                        })
+                        .map_or_else(TransportEvent::Error, TransportEvent::Packet),
+                    Ok(Frame::Binary(data)) => {
+                        TransportEvent::Packet(Packet::binary(data.to_vec()))
+                    }
+                    Ok(Frame::Ping(data)) => {
+                        let _ = pongs.unbounded_send(Message::Pong(data));
+                        continue;
+                    }
+                    Ok(Frame::Pong(_)) => continue,
+                    Ok(Frame::Close(_)) => TransportEvent::Close,
+                    Ok(Frame::Continuation(_)) => TransportEvent::Error(
+                        TransportError::InvalidData("fragmented message".to_string()),
+                    ),
+                    Err(e) => TransportEvent::Error(transport_error(e)),
+                };
+                let packet = matches!(event, TransportEvent::Packet(_));
+                if events.unbounded_send(event).is_err() || !packet {
+                    return;
+                }
+            }
+            let _ = events.unbounded_send(TransportEvent::Close);
+        });
+        Ok((WebsocketTransport { messages }, receiver))
+    }
+}
+
+impl Transport for WebsocketTransport {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+
+    fn send(
+        &mut self,
+        packets: Vec<Packet>,
+        _compress: bool,
+    ) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let result = packets.into_iter().try_for_each(|packet| {
+            let message = if packet.binary {
+                Message::Binary(Bytes::from(packet.data))
+            } else {
+                Message::Text(EngineIOParserImpl::encode_packet_str(packet).map_err(invalid_data)?)
+            };
+            self.messages
+                .unbounded_send(message)
+                .map_err(|_| TransportError::Closed)
+        });
+        future::ready(result).boxed_local()
+    }
+
+    fn close(&mut self) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let result = self
+            .messages
+            .unbounded_send(Message::Close(Some(CloseCode::Normal.into())))
+            .map_err(|_| TransportError::Closed);
+        self.messages.close_channel();
+        future::ready(result).boxed_local()
+    }
+}
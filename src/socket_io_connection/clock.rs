@@ -0,0 +1,50 @@
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+#[cfg(any(test, feature = "test-util"))]
+use crate::socket_io_testing::virtual_clock::VirtualClock;
+use actix::{AsyncContext, Context, Message};
+use std::time::{Duration, Instant};
+
+/// A heartbeat timer of a connection going off. Only the timer armed last
+/// counts, the ones it replaced are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Heartbeat(pub(crate) u64);
+
+impl Message for Heartbeat {
+    type Result = ();
+}
+
+/// Where the connections get the time from, for their heartbeat
+#[derive(Debug, Clone, Default)]
+pub(crate) enum Clock {
+    #[default]
+    System,
+    /// Moves only when advanced, so that tests do not wait for the heartbeat
+    #[cfg(any(test, feature = "test-util"))]
+    Virtual(VirtualClock),
+}
+
+impl Clock {
+    pub(crate) fn now(&self) -> Instant {
+        match self {
+            Clock::System => Instant::now(),
+            #[cfg(any(test, feature = "test-util"))]
+            Clock::Virtual(clock) => clock.now(),
+        }
+    }
+
+    /// Sends the heartbeat timer to the connection once `delay` elapsed
+    pub(crate) fn notify_later(
+        &self,
+        ctx: &mut Context<SocketIoConnection>,
+        heartbeat: Heartbeat,
+        delay: Duration,
+    ) {
+        match self {
+            Clock::System => {
+                ctx.notify_later(heartbeat, delay);
+            }
+            #[cfg(any(test, feature = "test-util"))]
+            Clock::Virtual(clock) => clock.add_timer(delay, ctx.address(), heartbeat),
+        }
+    }
+}
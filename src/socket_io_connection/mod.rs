@@ -0,0 +1,4 @@
+pub(crate) mod clock;
+pub mod socket_io_connection_impl;
+pub mod socket_message;
+pub mod transport_message;
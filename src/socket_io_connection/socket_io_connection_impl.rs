@@ -0,0 +1,1029 @@
+use crate::engine_io_parser::packet::{Packet, PacketType};
+use crate::engine_io_parser::ProtocolVersion;
+use crate::engine_io_server::transport::{
+    Transport, TransportError, TransportEvent, TransportEvents, TransportType,
+};
+use crate::random_id_generator::RandomIdGenerator;
+use crate::socket_io_adaptor::disconnect_reason::DisconnectReason;
+use crate::socket_io_adaptor::socket_ref::SocketRef;
+use crate::socket_io_admin::admin_ui::{AdminUi, SocketDetails};
+use crate::socket_io_connection::clock::Heartbeat;
+use crate::socket_io_connection::socket_message::{
+    AckCallback, AckSender, DisconnectMessage, ShutdownMessage, SocketMessage,
+};
+use crate::socket_io_connection::transport_message::{AttachTransport, GetPolling};
+use crate::socket_io_packet_codec::attachments;
+use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use crate::socket_io_packet_codec::payload::Payload;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use crate::socket_io_packet_codec::SocketIoProtocolVersion;
+use crate::socket_io_polling::polling_transport::{PollingHandle, PollingTransport};
+use crate::socket_io_websocket::app_state::AppState;
+use actix::fut::{ActorFuture, WrapFuture};
+use actix::{Actor, ActorContext, Addr, AsyncContext, Context, Handler, StreamHandler};
+use actix_web::web;
+use futures::future::LocalBoxFuture;
+use futures::{FutureExt, StreamExt};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime};
+use std::{fmt, io};
+use tracing::{debug, debug_span, field, info, info_span, Span};
+
+/// The target of the debug logs of the raw packets, which can be turned off
+/// on their own, e.g. with `RUST_LOG=debug,socket_io_rust::packets=off`
+pub const RAW_PACKETS_TARGET: &str = "socket_io_rust::packets";
+
+/// A socket of the connection, in one of the namespaces
+#[derive(Debug)]
+struct NamespaceSocket {
+    id: String,
+    /// Child of the span of the connection
+    span: Span,
+}
+
+/// A binary packet of the client, waiting for its attachments
+#[derive(Debug)]
+struct IncomingPacket {
+    packet: SocketIoPacket,
+    /// How many attachments the packet has
+    expected: usize,
+    attachments: Vec<Vec<u8>>,
+    /// The size of the encoded packet, without its attachments
+    size: usize,
+}
+
+/// An event emitted to the client, waiting for its acknowledgement
+struct PendingAck {
+    nsp: String,
+    sent_at: Instant,
+    callback: AckCallback,
+}
+
+impl fmt::Debug for PendingAck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PendingAck")
+            .field("nsp", &self.nsp)
+            .field("sent_at", &self.sent_at)
+            .finish()
+    }
+}
+
+/// An Engine.IO connection and the Socket.IO sockets multiplexed on it.
+///
+/// The connection outlives its transports: it starts with either polling or
+/// websocket, and a polling connection may be upgraded to websocket.
+#[derive(Debug)]
+pub struct SocketIoConnection {
+    /// Id of the heartbeat timer armed last, the ones it replaced are ignored
+    heartbeat_timer: u64,
+    /// Whether the client is pinged once the heartbeat timer goes off, rather
+    /// than the connection being closed for not hearing from the client
+    ping_due: bool,
+    global_state: web::Data<AppState>,
+    /// Engine.IO session id
+    id: String,
+    /// The transport carrying the packets of the session
+    transport: Box<dyn Transport + Send>,
+    /// Identifies the events of the current transport
+    transport_id: usize,
+    /// The transport the connection is being upgraded to, probed by the
+    /// client before the upgrade
+    upgrade: Option<(usize, Box<dyn Transport + Send>)>,
+    /// Identifies the events of the next transport
+    next_transport_id: usize,
+    /// The polling transport, handed the polling requests until the
+    /// connection is upgraded
+    polling: Option<PollingHandle>,
+    /// Engine.IO protocol revision of the client
+    protocol: ProtocolVersion,
+    /// Socket.IO protocol revision of the client
+    socket_io_protocol: SocketIoProtocolVersion,
+    /// Packets waiting for the transport to be writable
+    write_buffer: Vec<Packet>,
+    /// Whether one of the buffered packets may be compressed
+    compress_write_buffer: bool,
+    /// Packets handed to the transport and not sent yet, in batches
+    pending_sends: usize,
+    /// Socket of each namespace the client is connected to
+    sockets: HashMap<String, NamespaceSocket>,
+    /// The binary packet whose attachments are being received
+    incoming: Option<IncomingPacket>,
+    /// Events emitted to the client waiting for their acknowledgement, by id
+    pending_acks: HashMap<u32, PendingAck>,
+    next_ack_id: u32,
+    /// Why the connection is being closed, given to the namespaces it is
+    /// connected to
+    close_reason: Option<DisconnectReason>,
+    /// Set once the server shuts down: the connection closes for this reason
+    /// once its packets are sent
+    closing: Option<DisconnectReason>,
+    /// The address of the client, if known
+    remote_addr: Option<SocketAddr>,
+    /// Span of the session, with its id, transport and remote address
+    span: Span,
+}
+
+impl Actor for SocketIoConnection {
+    type Context = Context<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        info!(parent: &self.span, protocol = ?self.protocol, "connection opened");
+        self.hb(ctx);
+        let handshake = self.handshake_packet();
+        self.send_packet(ctx, handshake, true);
+        self.on_open(ctx);
+        let connect_timeout = self.global_state.options.connect_timeout;
+        ctx.run_later(connect_timeout, |act, ctx| {
+            if act.sockets.is_empty() {
+                act.close(ctx, DisconnectReason::TransportClose);
+            }
+        });
+    }
+
+    fn stopped(&mut self, _: &mut Self::Context) {
+        self.global_state
+            .connections
+            .write()
+            .unwrap()
+            .remove(&self.id);
+        self.remove_sockets_from_adaptors();
+        self.drop_pending_acks(None);
+
+        spawn_ignored(self.transport.close());
+        if let Some((_, mut upgrade)) = self.upgrade.take() {
+            spawn_ignored(upgrade.close());
+        }
+    }
+}
+
+/// An event of one of the transports of a connection
+struct TaggedTransportEvent {
+    transport_id: usize,
+    event: TransportEvent,
+}
+
+/// Runs a transport future whose outcome does not matter
+fn spawn_ignored(fut: LocalBoxFuture<'static, Result<(), TransportError>>) {
+    actix::spawn(fut.map(|_| ()));
+}
+
+impl Handler<SocketMessage> for SocketIoConnection {
+    type Result = Result<(), io::Error>;
+
+    fn handle(&mut self, mut msg: SocketMessage, ctx: &mut Self::Context) -> Self::Result {
+        if let Some(callback) = msg.ack {
+            // no more acknowledgements are awaited once the server shuts down
+            if self.closing.is_some() || !self.sockets.contains_key(&msg.packet.nsp) {
+                callback(None);
+                return Ok(());
+            }
+            let id = self.next_ack_id;
+            self.next_ack_id = self.next_ack_id.wrapping_add(1);
+            msg.packet.id = Some(id);
+            let pending = PendingAck {
+                nsp: msg.packet.nsp.clone(),
+                sent_at: self.global_state.clock.now(),
+                callback,
+            };
+            self.pending_acks.insert(id, pending);
+        }
+        self.send_binary_packet(ctx, &msg.packet, msg.attachments, msg.compress);
+        Ok(())
+    }
+}
+
+impl Handler<DisconnectMessage> for SocketIoConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: DisconnectMessage, ctx: &mut Self::Context) -> Self::Result {
+        let socket = match self.sockets.remove(&msg.nsp) {
+            Some(socket) => socket,
+            None => return,
+        };
+        info!(parent: &socket.span, "socket disconnected by the server");
+        self.drop_pending_acks(Some(&msg.nsp));
+        let packet = SocketIoPacket {
+            packet_type: SocketIoPacketType::Disconnect,
+            nsp: msg.nsp,
+            id: None,
+            data: None,
+        };
+        self.send_socket_io_packet(ctx, &packet, true);
+        if msg.close {
+            // the sockets of the other namespaces were not disconnected by
+            // the server, they only lose their connection
+            self.close(ctx, DisconnectReason::ForcedClose);
+        } else if self.sockets.is_empty() {
+            self.close(ctx, DisconnectReason::ServerNamespaceDisconnect);
+        } else {
+            self.shutdown_if_acked(ctx);
+        }
+    }
+}
+
+impl Handler<ShutdownMessage> for SocketIoConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: ShutdownMessage, ctx: &mut Self::Context) -> Self::Result {
+        self.closing = Some(msg.reason);
+        if msg.force {
+            self.drop_pending_acks(None);
+        }
+        self.shutdown_if_acked(ctx);
+    }
+}
+
+impl SocketIoConnection {
+    /// Disconnects the sockets once the server shuts down and the events
+    /// emitted to the client are acknowledged, then closes the connection
+    /// once the DISCONNECT packets are sent
+    fn shutdown_if_acked(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let reason = match self.closing {
+            Some(reason) if self.pending_acks.is_empty() => reason,
+            _ => return,
+        };
+        let sockets: Vec<(String, NamespaceSocket)> = self.sockets.drain().collect();
+        {
+            let mut namespace_manager = self.global_state.namespace_manager.write().unwrap();
+            for (nsp, socket) in sockets.iter() {
+                info!(parent: &socket.span, %reason, "socket disconnected by the server");
+                if let Some(namespace) = namespace_manager.get_adaptor_mut(nsp) {
+                    namespace.remove_socket(&socket.id, reason);
+                }
+            }
+        }
+        for (nsp, _) in sockets {
+            let packet = SocketIoPacket {
+                packet_type: SocketIoPacketType::Disconnect,
+                nsp,
+                id: None,
+                data: None,
+            };
+            self.send_socket_io_packet(ctx, &packet, true);
+        }
+        self.close_if_sent(ctx);
+    }
+}
+
+impl Handler<Heartbeat> for SocketIoConnection {
+    type Result = ();
+
+    fn handle(&mut self, msg: Heartbeat, ctx: &mut Self::Context) -> Self::Result {
+        self.on_heartbeat(ctx, msg.0);
+    }
+}
+
+impl StreamHandler<TaggedTransportEvent> for SocketIoConnection {
+    fn handle(&mut self, item: TaggedTransportEvent, ctx: &mut Self::Context) {
+        let is_upgrade = |(id, _): &(usize, Box<dyn Transport + Send>)| *id == item.transport_id;
+        if item.transport_id == self.transport_id {
+            match item.event {
+                TransportEvent::Packet(packet) => self.on_packet(ctx, packet),
+                TransportEvent::Drain => self.flush(ctx),
+                TransportEvent::Close => self.close(ctx, DisconnectReason::TransportClose),
+                TransportEvent::Error(_) => self.close(ctx, DisconnectReason::TransportError),
+            }
+        } else if self.upgrade.as_ref().is_some_and(is_upgrade) {
+            match item.event {
+                TransportEvent::Packet(packet) => self.on_upgrade_packet(ctx, packet),
+                TransportEvent::Drain => {}
+                // the upgrade failed, keep the current transport
+                TransportEvent::Close | TransportEvent::Error(_) => self.upgrade = None,
+            }
+        }
+    }
+
+    /// The transports report their closing as an event, the end of their
+    /// events does not stop the connection
+    fn finished(&mut self, _: &mut Self::Context) {}
+}
+
+impl Handler<AttachTransport> for SocketIoConnection {
+    type Result = ();
+
+    fn handle(&mut self, mut msg: AttachTransport, ctx: &mut Self::Context) -> Self::Result {
+        let options = &self.global_state.options;
+        let upgradable = TransportType::from_name(self.transport.name())
+            .map(|transport| options.get_upgrades(transport))
+            .unwrap_or_default()
+            .into_iter()
+            .any(|upgrade| upgrade.name() == msg.transport.name());
+        if self.upgrade.is_some() || !upgradable {
+            spawn_ignored(msg.transport.close());
+            return;
+        }
+        let transport_id = self.add_transport_events(ctx, msg.events);
+        self.upgrade = Some((transport_id, msg.transport));
+
+        // the client probes the transport then asks for the upgrade, which
+        // must happen in time
+        let upgrade_timeout = self.global_state.options.upgrade_timeout;
+        ctx.run_later(upgrade_timeout, move |act, _| {
+            if let Some((id, mut upgrade)) = act.upgrade.take() {
+                if id == transport_id {
+                    spawn_ignored(upgrade.close());
+                } else {
+                    act.upgrade = Some((id, upgrade));
+                }
+            }
+        });
+    }
+}
+
+impl Handler<GetPolling> for SocketIoConnection {
+    type Result = Option<PollingHandle>;
+
+    fn handle(&mut self, _: GetPolling, _: &mut Self::Context) -> Self::Result {
+        self.polling.clone()
+    }
+}
+
+impl SocketIoConnection {
+    pub fn new(
+        data: web::Data<AppState>,
+        transport: Box<dyn Transport + Send>,
+        protocol: ProtocolVersion,
+        remote_addr: Option<SocketAddr>,
+    ) -> Self {
+        let id = RandomIdGenerator::get_random_uuid();
+        let span = info_span!(
+            "connection",
+            sid = %id,
+            transport = transport.name(),
+            remote_addr = field::Empty,
+        );
+        if let Some(remote_addr) = remote_addr {
+            span.record("remote_addr", field::display(remote_addr));
+        }
+        Self {
+            heartbeat_timer: 0,
+            ping_due: false,
+            global_state: data,
+            id,
+            transport,
+            transport_id: 0,
+            upgrade: None,
+            next_transport_id: 0,
+            polling: None,
+            protocol,
+            socket_io_protocol: protocol.into(),
+            write_buffer: Vec::new(),
+            compress_write_buffer: false,
+            pending_sends: 0,
+            sockets: HashMap::new(),
+            incoming: None,
+            pending_acks: HashMap::new(),
+            next_ack_id: 0,
+            close_reason: None,
+            closing: None,
+            remote_addr,
+            span,
+        }
+    }
+
+    /// Starts a connection over a transport and registers it so that the
+    /// requests of the client can find it. Returns its session id. The open
+    /// packet is the first packet the transport sends.
+    pub fn open(
+        data: web::Data<AppState>,
+        transport: Box<dyn Transport + Send>,
+        events: TransportEvents,
+        protocol: ProtocolVersion,
+        remote_addr: Option<SocketAddr>,
+    ) -> (String, Addr<Self>) {
+        let connection = SocketIoConnection::new(data, transport, protocol, remote_addr);
+        connection.start_with_events(events)
+    }
+
+    /// Starts a connection over the polling transport. Returns the handle the
+    /// polling requests are given to as well.
+    pub fn open_polling(
+        data: web::Data<AppState>,
+        protocol: ProtocolVersion,
+        remote_addr: Option<SocketAddr>,
+    ) -> (String, Addr<Self>, PollingHandle) {
+        let (transport, handle, events) = PollingTransport::new();
+        let mut connection =
+            SocketIoConnection::new(data, Box::new(transport), protocol, remote_addr);
+        connection.polling = Some(handle.clone());
+        let (id, addr) = connection.start_with_events(events);
+        (id, addr, handle)
+    }
+
+    fn start_with_events(mut self, events: TransportEvents) -> (String, Addr<Self>) {
+        let id = self.id.clone();
+        let data = self.global_state.clone();
+        let addr = SocketIoConnection::create(move |ctx| {
+            self.transport_id = self.add_transport_events(ctx, events);
+            self
+        });
+        data.connections
+            .write()
+            .unwrap()
+            .insert(id.clone(), addr.clone());
+        (id, addr)
+    }
+
+    /// Handles the events of a transport, tagged with the returned id
+    fn add_transport_events(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        events: TransportEvents,
+    ) -> usize {
+        let transport_id = self.next_transport_id;
+        self.next_transport_id += 1;
+        ctx.add_stream(events.map(move |event| TaggedTransportEvent {
+            transport_id,
+            event,
+        }));
+        transport_id
+    }
+
+    /// Get the Engine.IO session id
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Get the open packet starting the Engine.IO session
+    pub fn handshake_packet(&self) -> Packet {
+        let options = &self.global_state.options;
+        let upgrades: Vec<&str> = TransportType::from_name(self.transport.name())
+            .map(|transport| options.get_upgrades(transport))
+            .unwrap_or_default()
+            .into_iter()
+            .map(TransportType::name)
+            .collect();
+        let mut handshake = json!({
+            "sid": self.id,
+            "upgrades": upgrades,
+            "pingInterval": options.ping_interval.as_millis() as u64,
+            "pingTimeout": options.ping_timeout.as_millis() as u64,
+        });
+        if self.protocol == ProtocolVersion::V4 {
+            handshake["maxPayload"] = json!(options.max_http_buffer_size);
+        }
+        Packet::new(PacketType::Open, handshake.to_string().into_bytes())
+    }
+
+    /// Called once the open packet is on its way to the client. Clients of
+    /// Socket.IO protocol v4 join the root namespace without asking.
+    fn on_open(&mut self, ctx: &mut <Self as Actor>::Context) {
+        if self.socket_io_protocol == SocketIoProtocolVersion::V4 {
+            let connect = SocketIoPacket {
+                packet_type: SocketIoPacketType::Connect,
+                nsp: "/".to_string(),
+                id: None,
+                data: None,
+            };
+            self.on_connect(ctx, connect);
+        }
+    }
+
+    /// Closes the connection, letting the namespaces know why
+    fn close(&mut self, ctx: &mut <Self as Actor>::Context, reason: DisconnectReason) {
+        if self.close_reason.is_none() {
+            info!(parent: &self.span, %reason, "connection closed");
+        }
+        self.close_reason = Some(reason);
+        ctx.stop();
+    }
+
+    /// Arms the heartbeat timer for the next ping. Engine.IO v4 connections
+    /// ping the client once the ping interval elapses. Engine.IO v3 clients
+    /// send the pings themselves, and the connection is closed when none
+    /// comes within the ping interval and timeout.
+    fn hb(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let options = &self.global_state.options;
+        let (ping_interval, ping_timeout) = (options.ping_interval, options.ping_timeout);
+        match self.protocol {
+            ProtocolVersion::V4 => self.arm_heartbeat(ctx, true, ping_interval),
+            ProtocolVersion::V3 => self.arm_heartbeat(ctx, false, ping_interval + ping_timeout),
+        }
+    }
+
+    /// Arms the heartbeat timer, replacing the one armed before
+    fn arm_heartbeat(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        ping_due: bool,
+        delay: Duration,
+    ) {
+        self.heartbeat_timer = self.heartbeat_timer.wrapping_add(1);
+        self.ping_due = ping_due;
+        self.global_state
+            .clock
+            .notify_later(ctx, Heartbeat(self.heartbeat_timer), delay);
+    }
+
+    /// Pings the client when the ping is due, then waits for its pong for
+    /// the ping timeout. Otherwise the client was not heard from in time.
+    fn on_heartbeat(&mut self, ctx: &mut <Self as Actor>::Context, timer: u64) {
+        if timer != self.heartbeat_timer {
+            return;
+        }
+        if self.ping_due {
+            self.send_packet(ctx, Packet::new(PacketType::Ping, Vec::new()), true);
+            let ping_timeout = self.global_state.options.ping_timeout;
+            self.arm_heartbeat(ctx, false, ping_timeout);
+            return;
+        }
+        if let Some(metrics) = self.global_state.options.metrics.as_ref() {
+            metrics.on_heartbeat_timeout();
+        }
+        self.close(ctx, DisconnectReason::PingTimeout);
+    }
+
+    /// Sends a packet through the current transport, which may compress it
+    /// when `compress` is true. The packet is buffered until the transport is
+    /// writable.
+    fn send_packet(&mut self, ctx: &mut <Self as Actor>::Context, packet: Packet, compress: bool) {
+        log_raw_packet(&self.span, "out", &packet);
+        self.write_buffer.push(packet);
+        self.compress_write_buffer |= compress;
+        self.flush(ctx);
+    }
+
+    /// Sends the buffered packets, if the transport is writable
+    fn flush(&mut self, ctx: &mut <Self as Actor>::Context) {
+        if self.write_buffer.is_empty() || !self.transport.writable() {
+            return;
+        }
+        let sent = self.transport.send(
+            std::mem::take(&mut self.write_buffer),
+            std::mem::take(&mut self.compress_write_buffer),
+        );
+        let transport_id = self.transport_id;
+        self.pending_sends += 1;
+        ctx.spawn(sent.into_actor(self).map(move |result, act, ctx| {
+            act.pending_sends -= 1;
+            if result.is_err() && act.transport_id == transport_id {
+                act.close(ctx, DisconnectReason::TransportError);
+            } else {
+                act.close_if_sent(ctx);
+            }
+        }));
+    }
+
+    /// Closes the connection once the server shuts down, the events emitted
+    /// to the client are acknowledged and every packet is sent. Polling
+    /// clients get theirs with their next request.
+    fn close_if_sent(&mut self, ctx: &mut <Self as Actor>::Context) {
+        if let Some(reason) = self.closing {
+            let sent = self.write_buffer.is_empty() && self.pending_sends == 0;
+            if sent && self.pending_acks.is_empty() {
+                self.close(ctx, reason);
+            }
+        }
+    }
+
+    /// Ends the current polling cycle, so that the client can upgrade
+    fn send_noop(&mut self) {
+        if self.transport.writable() {
+            let noop = Packet::new(PacketType::Noop, Vec::new());
+            spawn_ignored(self.transport.send(vec![noop], false));
+        }
+    }
+
+    fn send_socket_io_packet(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        packet: &SocketIoPacket,
+        compress: bool,
+    ) {
+        self.send_binary_packet(ctx, packet, Vec::new(), compress);
+    }
+
+    /// Sends a Socket.IO packet, followed by its binary attachments
+    fn send_binary_packet(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        packet: &SocketIoPacket,
+        attachments: Vec<Vec<u8>>,
+        compress: bool,
+    ) {
+        if let Ok(text) = self.global_state.options.codec().encode_to_string(packet) {
+            if let Some(span) = self.event_span(packet, "out", text.len()) {
+                debug!(parent: &span, "event emitted");
+            }
+            self.send_packet(
+                ctx,
+                Packet::new(PacketType::Message, text.into_bytes()),
+                compress,
+            );
+            for attachment in attachments {
+                self.send_packet(ctx, Packet::binary(attachment), compress);
+            }
+        }
+    }
+
+    /// Handles the packets of the transport the connection is being upgraded
+    /// to
+    fn on_upgrade_packet(&mut self, ctx: &mut <Self as Actor>::Context, packet: Packet) {
+        log_raw_packet(&self.span, "in", &packet);
+        match packet.packet_type {
+            PacketType::Ping if packet.data == b"probe" => {
+                if let Some((_, upgrade)) = self.upgrade.as_mut() {
+                    let pong = Packet::new(PacketType::Pong, packet.data);
+                    spawn_ignored(upgrade.send(vec![pong], false));
+                }
+                self.send_noop();
+            }
+            PacketType::Upgrade => {
+                if let Some((transport_id, transport)) = self.upgrade.take() {
+                    self.send_noop();
+                    let mut previous = std::mem::replace(&mut self.transport, transport);
+                    spawn_ignored(previous.close());
+                    self.span.record("transport", self.transport.name());
+                    debug!(parent: &self.span, from = previous.name(), "transport upgraded");
+                    self.transport_id = transport_id;
+                    self.polling = None;
+                    self.flush(ctx);
+                    if let Some(admin_ui) = self.global_state.admin_ui.as_ref() {
+                        admin_ui.on_transport_upgraded(&self.id, self.transport.name());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_packet(&mut self, ctx: &mut <Self as Actor>::Context, packet: Packet) {
+        log_raw_packet(&self.span, "in", &packet);
+        match packet.packet_type {
+            PacketType::Ping => {
+                if self.protocol == ProtocolVersion::V3 {
+                    self.hb(ctx);
+                }
+                self.send_packet(ctx, Packet::new(PacketType::Pong, packet.data), true);
+            }
+            // the pong of the ping the connection waits for
+            PacketType::Pong if self.protocol == ProtocolVersion::V4 && !self.ping_due => {
+                self.hb(ctx);
+            }
+            PacketType::Pong => {}
+            PacketType::Message if packet.binary => self.on_attachment(ctx, packet.data),
+            PacketType::Message => {
+                let codec = self.global_state.options.codec();
+                let decoded = std::str::from_utf8(&packet.data)
+                    .ok()
+                    .and_then(|data| codec.decode(data).ok());
+                let size = packet.data.len();
+                match decoded {
+                    Some(packet) => self.on_socket_io_packet(ctx, packet, size),
+                    None => self.close(ctx, DisconnectReason::ParseError),
+                }
+            }
+            PacketType::Close => self.close(ctx, DisconnectReason::TransportClose),
+            _ => {}
+        }
+    }
+
+    /// Handles a Socket.IO packet, `size` being the one of its encoding
+    fn on_socket_io_packet(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        packet: SocketIoPacket,
+        size: usize,
+    ) {
+        match packet.packet_type {
+            SocketIoPacketType::Connect => self.on_connect(ctx, packet),
+            SocketIoPacketType::Disconnect => self.on_disconnect(ctx, &packet.nsp),
+            SocketIoPacketType::Event => self.on_event(ctx, packet, Vec::new(), size),
+            SocketIoPacketType::Ack => self.on_ack(ctx, packet, Vec::new()),
+            SocketIoPacketType::BinaryEvent | SocketIoPacketType::BinaryAck => {
+                let expected = packet
+                    .data
+                    .as_ref()
+                    .map_or(0, |data| attachments::placeholders(data).len());
+                if expected > self.global_state.options.max_attachments {
+                    return self.close(ctx, DisconnectReason::ParseError);
+                }
+                self.incoming = Some(IncomingPacket {
+                    packet,
+                    expected,
+                    attachments: Vec::new(),
+                    size,
+                });
+                self.on_binary_packet(ctx);
+            }
+            SocketIoPacketType::Error => {}
+        }
+    }
+
+    /// Handles an attachment of the binary packet being received. An
+    /// attachment no packet expects closes the connection, as does a packet
+    /// larger than the maximum size of a message along with its attachments.
+    fn on_attachment(&mut self, ctx: &mut <Self as Actor>::Context, data: Vec<u8>) {
+        let incoming = match self.incoming.as_mut() {
+            Some(incoming) => incoming,
+            None => return self.close(ctx, DisconnectReason::ParseError),
+        };
+        incoming.attachments.push(data);
+        let size = incoming.size + incoming.attachments.iter().map(Vec::len).sum::<usize>();
+        if size > self.global_state.options.max_http_buffer_size {
+            return self.close(ctx, DisconnectReason::TransportError);
+        }
+        self.on_binary_packet(ctx);
+    }
+
+    /// Handles the binary packet being received, once it has its attachments
+    fn on_binary_packet(&mut self, ctx: &mut <Self as Actor>::Context) {
+        let complete = self
+            .incoming
+            .as_ref()
+            .is_some_and(|incoming| incoming.attachments.len() == incoming.expected);
+        if !complete {
+            return;
+        }
+        if let Some(incoming) = self.incoming.take() {
+            match incoming.packet.packet_type {
+                SocketIoPacketType::BinaryAck => {
+                    self.on_ack(ctx, incoming.packet, incoming.attachments)
+                }
+                _ => self.on_event(ctx, incoming.packet, incoming.attachments, incoming.size),
+            }
+        }
+    }
+
+    /// Gives the acknowledgement of an event emitted to the client to its
+    /// callback, recording how long the client took
+    fn on_ack(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        packet: SocketIoPacket,
+        attachments: Vec<Vec<u8>>,
+    ) {
+        let id = match packet.id {
+            Some(id)
+                if self
+                    .pending_acks
+                    .get(&id)
+                    .is_some_and(|p| p.nsp == packet.nsp) =>
+            {
+                id
+            }
+            _ => return,
+        };
+        if let Some(pending) = self.pending_acks.remove(&id) {
+            if let Some(metrics) = self.global_state.options.metrics.as_ref() {
+                metrics.on_ack(
+                    self.global_state
+                        .clock
+                        .now()
+                        .duration_since(pending.sent_at),
+                );
+            }
+            let args = match packet.data {
+                Some(Value::Array(args)) => args,
+                _ => Vec::new(),
+            };
+            (pending.callback)(Some(Payload::from_args(args, attachments)));
+        }
+        self.shutdown_if_acked(ctx);
+    }
+
+    /// Tells the callbacks of the events emitted to the sockets leaving that
+    /// they will not be acknowledged, for every namespace when `nsp` is `None`
+    fn drop_pending_acks(&mut self, nsp: Option<&str>) {
+        let ids: Vec<u32> = self
+            .pending_acks
+            .iter()
+            .filter(|(_, pending)| nsp.is_none_or(|nsp| pending.nsp == nsp))
+            .map(|(&id, _)| id)
+            .collect();
+        for id in ids {
+            if let Some(pending) = self.pending_acks.remove(&id) {
+                (pending.callback)(None);
+            }
+        }
+    }
+
+    /// Admits the client to a namespace, restoring its previous socket when
+    /// it sends a valid private session id and offset
+    fn on_connect(&mut self, ctx: &mut <Self as Actor>::Context, packet: SocketIoPacket) {
+        if self.closing.is_some() || self.sockets.contains_key(&packet.nsp) {
+            return;
+        }
+
+        let auth = packet.data.unwrap_or(Value::Null);
+        let admin_ui = self.global_state.admin_ui.clone();
+        let admin_ui = admin_ui.as_ref();
+        let is_admin = match admin_ui {
+            Some(admin_ui) => admin_ui.namespace() == packet.nsp,
+            None => false,
+        };
+        // the dashboard is let in the admin namespace with its credentials only
+        if is_admin && !admin_ui.is_some_and(|admin_ui| admin_ui.authenticate(&auth)) {
+            let error = self.global_state.options.codec().connect_error(
+                &packet.nsp,
+                "invalid credentials",
+                self.socket_io_protocol,
+            );
+            self.send_socket_io_packet(ctx, &error, true);
+            return;
+        }
+
+        let global_state = self.global_state.clone();
+        let mut namespace_manager = global_state.namespace_manager.write().unwrap();
+        let namespace = match namespace_manager.get_adaptor_mut(&packet.nsp) {
+            Some(namespace) => namespace,
+            None => {
+                drop(namespace_manager);
+                let error = self.global_state.options.codec().connect_error(
+                    &packet.nsp,
+                    "Invalid namespace",
+                    self.socket_io_protocol,
+                );
+                self.send_socket_io_packet(ctx, &error, true);
+                return;
+            }
+        };
+
+        let pid = auth.get("pid").and_then(Value::as_str);
+        let offset = auth.get("offset").and_then(Value::as_str);
+        let recovered = match (pid, offset) {
+            (Some(pid), Some(offset)) => {
+                match namespace.restore_socket(pid, offset, ctx.address()) {
+                    Ok(session) => Some(session),
+                    Err(err) => {
+                        debug!(parent: &self.span, nsp = %packet.nsp, %err, "session not recovered");
+                        None
+                    }
+                }
+            }
+            _ => None,
+        };
+        let recovered_session = recovered.is_some();
+        let (socket_id, missed_packets) = match recovered {
+            Some(session) => (session.sid, session.missed_packets),
+            None => {
+                let socket_id = RandomIdGenerator::get_random_uuid();
+                namespace.add_socket(&socket_id, ctx.address());
+                (socket_id, Vec::new())
+            }
+        };
+        let span = info_span!(parent: &self.span, "socket", nsp = %packet.nsp, id = %socket_id);
+        info!(parent: &span, recovered = recovered_session, "socket connected");
+        let mut data = json!({ "sid": socket_id });
+        if let Some(pid) = namespace.get_private_id(&socket_id) {
+            data["pid"] = Value::String(pid);
+        }
+        match admin_ui {
+            Some(admin_ui) if is_admin => {
+                admin_ui.on_admin_connected(&namespace_manager, &socket_id, ctx.address())
+            }
+            Some(admin_ui) => {
+                let details = SocketDetails {
+                    client_id: self.id.clone(),
+                    transport: self.transport.name(),
+                    address: self.remote_addr.map(|addr| addr.ip().to_string()),
+                    time: SystemTime::now(),
+                    auth,
+                };
+                if let Some(namespace) = namespace_manager.get_adaptor(&packet.nsp) {
+                    admin_ui.on_socket_connected(SocketRef::new(namespace, &socket_id), details);
+                }
+            }
+            None => {}
+        }
+        drop(namespace_manager);
+        if is_admin {
+            AdminUi::start_server_stats(self.global_state.clone());
+        }
+
+        self.sockets.insert(
+            packet.nsp.clone(),
+            NamespaceSocket {
+                id: socket_id,
+                span,
+            },
+        );
+        // protocol v4 clients expect an empty connect packet
+        let data = match self.socket_io_protocol {
+            SocketIoProtocolVersion::V4 => None,
+            SocketIoProtocolVersion::V5 => Some(data),
+        };
+        let connect = SocketIoPacket {
+            packet_type: SocketIoPacketType::Connect,
+            nsp: packet.nsp,
+            id: None,
+            data,
+        };
+        self.send_socket_io_packet(ctx, &connect, true);
+        for missed_packet in missed_packets.iter() {
+            self.send_socket_io_packet(ctx, missed_packet, true);
+        }
+    }
+
+    fn on_disconnect(&mut self, ctx: &mut <Self as Actor>::Context, nsp: &str) {
+        if let Some(socket) = self.sockets.remove(nsp) {
+            info!(parent: &socket.span, "socket disconnected by the client");
+            let mut namespace_manager = self.global_state.namespace_manager.write().unwrap();
+            if let Some(namespace) = namespace_manager.get_adaptor_mut(nsp) {
+                namespace.remove_socket(&socket.id, DisconnectReason::ClientNamespaceDisconnect);
+            }
+            drop(namespace_manager);
+            self.drop_pending_acks(Some(nsp));
+            self.shutdown_if_acked(ctx);
+        }
+    }
+
+    /// Get the span of an event of one of the sockets, child of the span of
+    /// the socket. `size` is the one of the encoded packet.
+    fn event_span(&self, packet: &SocketIoPacket, direction: &str, size: usize) -> Option<Span> {
+        let socket = match packet.packet_type {
+            SocketIoPacketType::Event | SocketIoPacketType::BinaryEvent => {
+                self.sockets.get(&packet.nsp)?
+            }
+            _ => return None,
+        };
+        let name = packet
+            .data
+            .as_ref()
+            .and_then(|data| data.get(0))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        Some(debug_span!(
+            parent: &socket.span,
+            "event",
+            direction,
+            name,
+            ack_id = ?packet.id,
+            size,
+        ))
+    }
+
+    /// Gives an event to the event listeners of its namespace, which may
+    /// acknowledge it when the client asks to
+    fn on_event(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        packet: SocketIoPacket,
+        attachments: Vec<Vec<u8>>,
+        size: usize,
+    ) {
+        let span = match self.event_span(&packet, "in", size) {
+            Some(span) => span,
+            None => return,
+        };
+        let _entered = span.enter();
+        debug!("event received");
+        let mut args = match packet.data {
+            Some(Value::Array(args)) => args,
+            _ => return,
+        };
+        let event_name = match args.first().and_then(Value::as_str) {
+            Some(event_name) => event_name.to_string(),
+            None => return,
+        };
+
+        if let Some(admin_ui) = self.global_state.admin_ui.as_ref() {
+            if admin_ui.namespace() == packet.nsp {
+                let mut namespace_manager = self.global_state.namespace_manager.write().unwrap();
+                admin_ui.on_command(&mut namespace_manager, &event_name, &args[1..]);
+                return;
+            }
+        }
+
+        let socket = match self.sockets.get(&packet.nsp) {
+            Some(socket) => socket,
+            None => return,
+        };
+        args.remove(0);
+        let payload = Payload::from_args(args, attachments);
+        let nsp = &packet.nsp;
+        let ack = packet.id.map(|id| AckSender::new(ctx.address(), nsp, id));
+        let namespace_manager = self.global_state.namespace_manager.read().unwrap();
+        if let Some(namespace) = namespace_manager.get_adaptor(nsp) {
+            namespace.dispatch_event(&socket.id, &event_name, payload, ack);
+        }
+    }
+
+    fn remove_sockets_from_adaptors(&mut self) {
+        let reason = self
+            .close_reason
+            .unwrap_or(DisconnectReason::TransportClose);
+        let mut namespace_manager = self.global_state.namespace_manager.write().unwrap();
+        for (nsp, socket) in self.sockets.drain() {
+            info!(parent: &socket.span, %reason, "socket disconnected");
+            if let Some(adaptor) = namespace_manager.get_adaptor_mut(&nsp) {
+                adaptor.remove_socket(&socket.id, reason);
+            }
+        }
+    }
+}
+
+/// Logs a packet of the session, as it is on the wire
+fn log_raw_packet(span: &Span, direction: &str, packet: &Packet) {
+    debug!(
+        target: RAW_PACKETS_TARGET,
+        parent: span,
+        direction,
+        packet_type = ?packet.packet_type,
+        binary = packet.binary,
+        data = %String::from_utf8_lossy(&packet.data),
+        "packet",
+    );
+}
@@ -0,0 +1,103 @@
+use crate::socket_io_adaptor::disconnect_reason::DisconnectReason;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use crate::socket_io_packet_codec::payload::Payload;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use actix::{Addr, Message};
+use serde_json::Value;
+use std::{fmt, io};
+
+/// Called with the arguments the client acknowledged an event with, or with
+/// `None` when the socket leaves its namespace first
+pub type AckCallback = Box<dyn FnOnce(Option<Payload>) + Send>;
+
+/// Acknowledges an event of a client, given to the event listeners of the
+/// namespace when the client asks for it
+pub struct AckSender {
+    addr: Addr<SocketIoConnection>,
+    nsp: String,
+    id: u32,
+}
+
+impl AckSender {
+    pub(crate) fn new(addr: Addr<SocketIoConnection>, nsp: &str, id: u32) -> Self {
+        Self {
+            addr,
+            nsp: nsp.to_string(),
+            id,
+        }
+    }
+
+    /// Sends the acknowledgement with its arguments, as a BINARY_ACK when
+    /// they are binary
+    pub fn send(self, payload: Payload) {
+        let (args, attachments) = payload.into_args();
+        let packet_type = if attachments.is_empty() {
+            SocketIoPacketType::Ack
+        } else {
+            SocketIoPacketType::BinaryAck
+        };
+        self.addr.do_send(SocketMessage {
+            packet: SocketIoPacket {
+                packet_type,
+                nsp: self.nsp,
+                id: Some(self.id),
+                data: Some(Value::Array(args)),
+            },
+            attachments,
+            compress: true,
+            ack: None,
+        });
+    }
+}
+
+impl fmt::Debug for AckSender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AckSender")
+            .field("nsp", &self.nsp)
+            .field("id", &self.id)
+            .finish()
+    }
+}
+
+/// Asks a socket to send a packet to its client
+pub(crate) struct SocketMessage {
+    pub(crate) packet: SocketIoPacket,
+    /// The binary attachments of the packet, sent after it
+    pub(crate) attachments: Vec<Vec<u8>>,
+    /// Whether the packet may be compressed
+    pub(crate) compress: bool,
+    /// Set when the client is asked to acknowledge the packet, which is then
+    /// given an id
+    pub(crate) ack: Option<AckCallback>,
+}
+
+impl Message for SocketMessage {
+    type Result = Result<(), io::Error>;
+}
+
+/// Asks a socket to disconnect, initiated by the server
+pub(crate) struct DisconnectMessage {
+    /// The namespace the socket leaves
+    pub(crate) nsp: String,
+    /// Whether to close the underlying connection as well
+    pub(crate) close: bool,
+}
+
+impl Message for DisconnectMessage {
+    type Result = ();
+}
+
+/// Asks a connection to disconnect all of its sockets once the events it
+/// emitted are acknowledged, then to close once the packets it buffered are
+/// sent
+pub(crate) struct ShutdownMessage {
+    pub(crate) reason: DisconnectReason,
+    /// Whether to disconnect the sockets right away, without waiting for the
+    /// acknowledgements
+    pub(crate) force: bool,
+}
+
+impl Message for ShutdownMessage {
+    type Result = ();
+}
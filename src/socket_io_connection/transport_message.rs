@@ -0,0 +1,39 @@
+use crate::engine_io_parser::packet::Packet;
+use crate::engine_io_server::transport::{Transport, TransportEvents};
+use crate::socket_io_polling::polling_transport::PollingHandle;
+use actix::Message;
+
+/// A transport was opened to upgrade a connection. The connection takes it
+/// over once the client probed it and asked for the upgrade.
+pub struct AttachTransport {
+    pub transport: Box<dyn Transport + Send>,
+    pub events: TransportEvents,
+}
+
+impl Message for AttachTransport {
+    type Result = ();
+}
+
+/// The packets answering a polling request
+#[derive(Debug)]
+pub struct PollPayload {
+    pub packets: Vec<Packet>,
+    /// Whether the response may be compressed, which is the case when one of
+    /// the packets may be
+    pub compress: bool,
+}
+
+/// Get the polling transport of a connection, to hand it a polling request.
+/// Resolves to `None` when the connection is not polling.
+pub(crate) struct GetPolling;
+
+impl Message for GetPolling {
+    type Result = Option<PollingHandle>;
+}
+
+/// Asks a websocket to close
+pub(crate) struct CloseWebsocket;
+
+impl Message for CloseWebsocket {
+    type Result = ();
+}
@@ -0,0 +1,96 @@
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A Prometheus histogram of integer observations, updated without locking.
+///
+/// Observations are counted in a unit `scale` times smaller than the one
+/// exposed, so that latencies can be observed in microseconds and exposed in
+/// seconds.
+#[derive(Debug)]
+pub struct Histogram {
+    /// Upper bounds of the buckets, in the unit of the observations
+    bounds: &'static [u64],
+    scale: f64,
+    /// Observations in each bucket, the last one being `+Inf`
+    buckets: Vec<AtomicU64>,
+    sum: AtomicU64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [u64], scale: f64) -> Self {
+        Histogram {
+            bounds,
+            scale,
+            buckets: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            sum: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, value: u64) {
+        let bucket = self
+            .bounds
+            .iter()
+            .position(|&bound| value <= bound)
+            .unwrap_or(self.bounds.len());
+        self.buckets[bucket].fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Writes the samples of the histogram, with cumulative buckets. `labels`
+    /// are the other labels of the samples, such as `namespace="/"`, if any.
+    pub fn render(&self, f: &mut impl Write, name: &str, labels: &str) -> fmt::Result {
+        let (bucket_labels, labels) = if labels.is_empty() {
+            (String::new(), String::new())
+        } else {
+            (format!("{},", labels), format!("{{{}}}", labels))
+        };
+        let mut count = 0;
+        for (i, bucket) in self.buckets.iter().enumerate() {
+            count += bucket.load(Ordering::Relaxed);
+            let le = match self.bounds.get(i) {
+                Some(&bound) => (bound as f64 / self.scale).to_string(),
+                None => "+Inf".to_string(),
+            };
+            writeln!(
+                f,
+                "{}_bucket{{{}le=\"{}\"}} {}",
+                name, bucket_labels, le, count
+            )?;
+        }
+        let sum = self.sum.load(Ordering::Relaxed) as f64 / self.scale;
+        writeln!(f, "{}_sum{} {}", name, labels, sum)?;
+        writeln!(f, "{}_count{} {}", name, labels, count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_metrics::histogram::Histogram;
+
+    #[test]
+    fn render_test() {
+        let histogram = Histogram::new(&[1, 10], 1.0);
+        for &value in [0, 1, 5, 100].iter() {
+            histogram.observe(value);
+        }
+        let mut rendered = String::new();
+        histogram
+            .render(&mut rendered, "fanout", "namespace=\"/\"")
+            .unwrap();
+        assert_eq!(
+            rendered,
+            "fanout_bucket{namespace=\"/\",le=\"1\"} 2\n\
+             fanout_bucket{namespace=\"/\",le=\"10\"} 3\n\
+             fanout_bucket{namespace=\"/\",le=\"+Inf\"} 4\n\
+             fanout_sum{namespace=\"/\"} 106\n\
+             fanout_count{namespace=\"/\"} 4\n"
+        );
+
+        let latency = Histogram::new(&[5_000], 1_000_000.0);
+        latency.observe(2_500);
+        let mut rendered = String::new();
+        latency.render(&mut rendered, "latency", "").unwrap();
+        assert!(rendered.starts_with("latency_bucket{le=\"0.005\"} 1\n"));
+        assert!(rendered.contains("latency_sum 0.0025\n"));
+    }
+}
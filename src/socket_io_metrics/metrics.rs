@@ -0,0 +1,386 @@
+use crate::engine_io_parser::packet::{Packet, PacketType};
+use crate::engine_io_server::error::EngineIoErrorCode;
+use crate::socket_io_metrics::histogram::Histogram;
+use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use std::fmt::{self, Write};
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+/// Which way a packet went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Received from a client
+    In = 0,
+    /// Sent to a client
+    Out = 1,
+}
+
+const DIRECTIONS: [&str; 2] = ["in", "out"];
+
+const ENGINE_IO_PACKET_TYPES: [&str; 7] = [
+    "open", "close", "ping", "pong", "message", "upgrade", "noop",
+];
+
+const SOCKET_IO_PACKET_TYPES: [&str; 7] = [
+    "connect",
+    "disconnect",
+    "event",
+    "ack",
+    "connect_error",
+    "binary_event",
+    "binary_ack",
+];
+
+const HANDSHAKE_ERROR_CODES: usize = 6;
+
+/// Sockets a broadcast is sent to
+const FANOUT_BUCKETS: &[u64] = &[1, 10, 100, 1_000, 10_000, 100_000];
+
+/// Microseconds between an acknowledged packet and its acknowledgement
+const ACK_LATENCY_BUCKETS: &[u64] = &[
+    1_000, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000, 1_000_000, 2_500_000,
+    5_000_000, 10_000_000,
+];
+
+fn engine_io_type_index(packet_type: &PacketType) -> usize {
+    match packet_type {
+        PacketType::Open => 0,
+        PacketType::Close => 1,
+        PacketType::Ping => 2,
+        PacketType::Pong => 3,
+        PacketType::Message => 4,
+        PacketType::Upgrade => 5,
+        PacketType::Noop => 6,
+    }
+}
+
+fn counters<const N: usize>() -> [AtomicU64; N] {
+    [(); N].map(|_| AtomicU64::new(0))
+}
+
+/// The metrics of a namespace, shared with the [`Namespace`] which updates
+/// them.
+///
+/// [`Namespace`]: ../../socket_io_adaptor/namespace/struct.Namespace.html
+#[derive(Debug)]
+pub struct NamespaceMetrics {
+    sockets: AtomicI64,
+    rooms: AtomicI64,
+    broadcast_fanout: Histogram,
+}
+
+impl Default for NamespaceMetrics {
+    fn default() -> Self {
+        Self {
+            sockets: AtomicI64::new(0),
+            rooms: AtomicI64::new(0),
+            broadcast_fanout: Histogram::new(FANOUT_BUCKETS, 1.0),
+        }
+    }
+}
+
+impl NamespaceMetrics {
+    pub fn add_sockets(&self, n: i64) {
+        self.sockets.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Counts the rooms of the namespace, including the one named after each
+    /// socket id
+    pub fn add_rooms(&self, n: i64) {
+        self.rooms.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// Records how many sockets a broadcast was sent to
+    pub fn on_broadcast(&self, sockets: usize) {
+        self.broadcast_fanout.observe(sockets as u64);
+    }
+
+    pub fn sockets(&self) -> i64 {
+        self.sockets.load(Ordering::Relaxed)
+    }
+
+    pub fn rooms(&self) -> i64 {
+        self.rooms.load(Ordering::Relaxed)
+    }
+}
+
+/// The metrics of a server, exposed in the Prometheus text format.
+///
+/// Every metric is an atomic, updated without locking. Only registering a
+/// namespace and rendering the metrics lock the list of namespaces.
+#[derive(Debug)]
+pub struct Metrics {
+    namespaces: RwLock<Vec<(String, Arc<NamespaceMetrics>)>>,
+    /// Engine.IO packets, by direction and type
+    engine_io_packets: [[AtomicU64; 7]; 2],
+    /// Bytes of the Engine.IO packets, by direction and type
+    engine_io_bytes: [[AtomicU64; 7]; 2],
+    /// Socket.IO packets, by direction and type
+    socket_io_packets: [[AtomicU64; 7]; 2],
+    /// Rejected Engine.IO requests, by error code
+    handshake_failures: [AtomicU64; HANDSHAKE_ERROR_CODES],
+    heartbeat_timeouts: AtomicU64,
+    ack_latency: Histogram,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            namespaces: RwLock::new(Vec::new()),
+            engine_io_packets: [counters(), counters()],
+            engine_io_bytes: [counters(), counters()],
+            socket_io_packets: [counters(), counters()],
+            handshake_failures: counters(),
+            heartbeat_timeouts: AtomicU64::new(0),
+            ack_latency: Histogram::new(ACK_LATENCY_BUCKETS, 1_000_000.0),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the metrics of a namespace, registering it on first use. A
+    /// namespace created again under the same name keeps its metrics.
+    pub fn namespace(&self, name: &str) -> Arc<NamespaceMetrics> {
+        if let Some((_, metrics)) = self
+            .namespaces
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(n, _)| n == name)
+        {
+            return metrics.clone();
+        }
+        let mut namespaces = self.namespaces.write().unwrap();
+        match namespaces.iter().find(|(n, _)| n == name) {
+            Some((_, metrics)) => metrics.clone(),
+            None => {
+                let metrics = Arc::new(NamespaceMetrics::default());
+                namespaces.push((name.to_string(), metrics.clone()));
+                metrics
+            }
+        }
+    }
+
+    /// Counts an Engine.IO packet, whose size is the one of its encoding as a
+    /// websocket frame
+    pub fn on_engine_io_packet(&self, direction: Direction, packet: &Packet) {
+        let (d, t) = (
+            direction as usize,
+            engine_io_type_index(&packet.packet_type),
+        );
+        let size = packet.data.len() + if packet.binary { 0 } else { 1 };
+        self.engine_io_packets[d][t].fetch_add(1, Ordering::Relaxed);
+        self.engine_io_bytes[d][t].fetch_add(size as u64, Ordering::Relaxed);
+    }
+
+    pub fn on_socket_io_packet(&self, direction: Direction, packet_type: SocketIoPacketType) {
+        self.socket_io_packets[direction as usize][packet_type as usize]
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn on_handshake_failure(&self, code: EngineIoErrorCode) {
+        self.handshake_failures[code as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn on_heartbeat_timeout(&self) {
+        self.heartbeat_timeouts.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records how long a client took to acknowledge an event emitted with
+    /// [`Namespace::emit_with_ack`](../../socket_io_adaptor/namespace/struct.Namespace.html#method.emit_with_ack)
+    pub fn on_ack(&self, latency: Duration) {
+        self.ack_latency.observe(latency.as_micros() as u64);
+    }
+
+    /// Get the metrics in the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        self.write(&mut out).unwrap();
+        out
+    }
+
+    fn write(&self, f: &mut impl Write) -> fmt::Result {
+        let mut namespaces = self.namespaces.read().unwrap().clone();
+        namespaces.sort_by(|(a, _), (b, _)| a.cmp(b));
+        header(
+            f,
+            "socketio_connected_sockets",
+            "gauge",
+            "Connected sockets, by namespace",
+        )?;
+        for (name, metrics) in namespaces.iter() {
+            writeln!(
+                f,
+                "socketio_connected_sockets{{namespace=\"{}\"}} {}",
+                escape(name),
+                metrics.sockets()
+            )?;
+        }
+        header(f, "socketio_rooms", "gauge", "Rooms, by namespace")?;
+        for (name, metrics) in namespaces.iter() {
+            writeln!(
+                f,
+                "socketio_rooms{{namespace=\"{}\"}} {}",
+                escape(name),
+                metrics.rooms()
+            )?;
+        }
+
+        let engine_io = [
+            (
+                "socketio_engine_packets_total",
+                "Engine.IO packets, by direction and type",
+                &self.engine_io_packets,
+            ),
+            (
+                "socketio_engine_bytes_total",
+                "Bytes of the Engine.IO packets, by direction and type",
+                &self.engine_io_bytes,
+            ),
+        ];
+        for (name, help, counters) in engine_io.iter() {
+            header(f, name, "counter", help)?;
+            write_by_type(f, name, counters, &ENGINE_IO_PACKET_TYPES)?;
+        }
+        let name = "socketio_packets_total";
+        header(
+            f,
+            name,
+            "counter",
+            "Socket.IO packets, by direction and type",
+        )?;
+        write_by_type(f, name, &self.socket_io_packets, &SOCKET_IO_PACKET_TYPES)?;
+
+        let name = "socketio_handshake_failures_total";
+        header(
+            f,
+            name,
+            "counter",
+            "Rejected Engine.IO requests, by error code",
+        )?;
+        for (code, counter) in self.handshake_failures.iter().enumerate() {
+            let value = counter.load(Ordering::Relaxed);
+            writeln!(f, "{}{{code=\"{}\"}} {}", name, code, value)?;
+        }
+        let name = "socketio_heartbeat_timeouts_total";
+        header(f, name, "counter", "Connections closed by a ping timeout")?;
+        let value = self.heartbeat_timeouts.load(Ordering::Relaxed);
+        writeln!(f, "{} {}", name, value)?;
+
+        let name = "socketio_broadcast_fanout";
+        header(
+            f,
+            name,
+            "histogram",
+            "Sockets a broadcast is sent to, by namespace",
+        )?;
+        for (nsp, metrics) in namespaces.iter() {
+            let labels = format!("namespace=\"{}\"", escape(nsp));
+            metrics.broadcast_fanout.render(f, name, &labels)?;
+        }
+        let name = "socketio_ack_latency_seconds";
+        header(
+            f,
+            name,
+            "histogram",
+            "Time taken by the clients to acknowledge a packet",
+        )?;
+        self.ack_latency.render(f, name, "")
+    }
+}
+
+fn header(f: &mut impl Write, name: &str, metric_type: &str, help: &str) -> fmt::Result {
+    writeln!(f, "# HELP {} {}", name, help)?;
+    writeln!(f, "# TYPE {} {}", name, metric_type)
+}
+
+fn write_by_type(
+    f: &mut impl Write,
+    name: &str,
+    counters: &[[AtomicU64; 7]; 2],
+    types: &[&str; 7],
+) -> fmt::Result {
+    for (direction, counters) in DIRECTIONS.iter().zip(counters.iter()) {
+        for (packet_type, counter) in types.iter().zip(counters.iter()) {
+            writeln!(
+                f,
+                "{}{{direction=\"{}\",type=\"{}\"}} {}",
+                name,
+                direction,
+                packet_type,
+                counter.load(Ordering::Relaxed)
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Escapes a label value, as the text format requires
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_parser::packet::{Packet, PacketType};
+    use crate::engine_io_server::error::EngineIoErrorCode;
+    use crate::socket_io_metrics::metrics::{Direction, Metrics};
+    use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+    use std::sync::Arc;
+
+    #[test]
+    fn namespace_test() {
+        let metrics = Metrics::new();
+        let root = metrics.namespace("/");
+        root.add_sockets(2);
+        root.add_rooms(3);
+        assert!(Arc::ptr_eq(&root, &metrics.namespace("/")));
+        metrics.namespace("/a\"b");
+
+        let rendered = metrics.render();
+        assert!(rendered.contains("socketio_connected_sockets{namespace=\"/\"} 2\n"));
+        assert!(rendered.contains("socketio_rooms{namespace=\"/\"} 3\n"));
+        assert!(rendered.contains("socketio_rooms{namespace=\"/a\\\"b\"} 0\n"));
+    }
+
+    #[test]
+    fn render_test() {
+        let metrics = Metrics::new();
+        metrics.on_engine_io_packet(
+            Direction::In,
+            &Packet::new(PacketType::Message, b"2[\"a\"]".to_vec()),
+        );
+        metrics.on_engine_io_packet(Direction::Out, &Packet::binary(vec![1, 2, 3]));
+        metrics.on_socket_io_packet(Direction::Out, SocketIoPacketType::Error);
+        metrics.on_handshake_failure(EngineIoErrorCode::UnknownSid);
+        metrics.on_heartbeat_timeout();
+        metrics.namespace("/").on_broadcast(42);
+
+        let rendered = metrics.render();
+        for line in [
+            "# TYPE socketio_engine_packets_total counter",
+            "socketio_engine_packets_total{direction=\"in\",type=\"message\"} 1",
+            "socketio_engine_bytes_total{direction=\"in\",type=\"message\"} 7",
+            "socketio_engine_bytes_total{direction=\"out\",type=\"message\"} 3",
+            "socketio_engine_packets_total{direction=\"out\",type=\"ping\"} 0",
+            "socketio_packets_total{direction=\"out\",type=\"connect_error\"} 1",
+            "socketio_handshake_failures_total{code=\"1\"} 1",
+            "socketio_heartbeat_timeouts_total 1",
+            "socketio_broadcast_fanout_bucket{namespace=\"/\",le=\"10\"} 0",
+            "socketio_broadcast_fanout_bucket{namespace=\"/\",le=\"100\"} 1",
+            "socketio_ack_latency_seconds_count 0",
+        ]
+        .iter()
+        {
+            assert!(rendered.lines().any(|l| l == *line), "{} is missing", line);
+        }
+    }
+}
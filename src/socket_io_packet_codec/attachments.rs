@@ -0,0 +1,78 @@
+//! Binary attachments are sent as separate Engine.IO packets following the
+//! packet that references them. The data of the packet holds a placeholder
+//! `{"_placeholder":true,"num":<index>}` in place of each attachment.
+
+use serde_json::{json, Value};
+
+/// Get the placeholder of the attachment of the given index
+pub fn placeholder(num: usize) -> Value {
+    json!({ "_placeholder": true, "num": num })
+}
+
+/// Get the index of the attachment a value stands for, if it is a placeholder
+pub fn placeholder_num(value: &Value) -> Option<usize> {
+    let object = value.as_object()?;
+    if object.len() != 2 || object.get("_placeholder") != Some(&Value::Bool(true)) {
+        return None;
+    }
+    object.get("num")?.as_u64().map(|num| num as usize)
+}
+
+/// Get the indexes of the placeholders found in a value, in order
+pub fn placeholders(value: &Value) -> Vec<usize> {
+    let mut nums = Vec::new();
+    collect_placeholders(value, &mut nums);
+    nums
+}
+
+fn collect_placeholders(value: &Value, nums: &mut Vec<usize>) {
+    if let Some(num) = placeholder_num(value) {
+        nums.push(num);
+        return;
+    }
+    match value {
+        Value::Array(values) => values.iter().for_each(|v| collect_placeholders(v, nums)),
+        Value::Object(map) => map.values().for_each(|v| collect_placeholders(v, nums)),
+        _ => {}
+    }
+}
+
+/// Replaces the placeholders of a value with the value `f` returns for their
+/// index
+pub fn replace_placeholders(value: Value, f: &mut impl FnMut(usize) -> Value) -> Value {
+    if let Some(num) = placeholder_num(&value) {
+        return f(num);
+    }
+    match value {
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|v| replace_placeholders(v, f))
+                .collect(),
+        ),
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(k, v)| (k, replace_placeholders(v, f)))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_packet_codec::attachments::{
+        placeholder, placeholders, replace_placeholders,
+    };
+    use serde_json::json;
+
+    #[test]
+    fn placeholders_test() {
+        let data = json!(["file", placeholder(0), { "thumb": placeholder(1) }]);
+        assert_eq!(vec![0, 1], placeholders(&data));
+        assert!(placeholders(&json!(["hello", { "num": 0 }])).is_empty());
+
+        let replaced = replace_placeholders(data, &mut |num| json!(num * 10));
+        assert_eq!(json!(["file", 0, { "thumb": 10 }]), replaced);
+    }
+}
@@ -1,20 +1,27 @@
-use crate::socket_io_packet_codec::number_util::convert_char_to_number;
+use crate::socket_io_packet_codec::attachments;
 use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
 use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
-use crate::socket_io_packet_codec::{SocketIoPacketCodec, PacketCodecError};
+use crate::socket_io_packet_codec::{
+    PacketCodecError, SocketIoPacketCodec, SocketIoProtocolVersion,
+};
 use core::fmt;
-
+use serde_json::{json, Value};
 
 #[derive(Default, Debug)]
 pub struct DefaultPacketCodec;
 
-
 impl SocketIoPacketCodec for DefaultPacketCodec {
     type Error = PacketCodecError;
 
     fn encode(&self, packet: &SocketIoPacket, f: &mut impl fmt::Write) -> Result<(), Self::Error> {
         write!(f, "{}", packet.packet_type as u8)?;
-        // todo: handle BINARY_EVENT
+        if is_binary(packet.packet_type) {
+            let attachments = packet
+                .data
+                .as_ref()
+                .map_or(0, |data| attachments::placeholders(data).len());
+            write!(f, "{}-", attachments)?;
+        }
 
         if packet.nsp != "/" {
             write!(f, "{}", packet.nsp)?;
@@ -32,16 +39,15 @@ impl SocketIoPacketCodec for DefaultPacketCodec {
     }
 
     fn decode(&self, data: &str) -> Result<SocketIoPacket, Self::Error> {
-        if data.is_empty() {
-            return Err(PacketCodecError::DecodeError("Data is empty".to_string()));
-        }
-
-        let mut idx = 0;
-        let chars: Vec<char> = data.chars().collect();
-
-        // get packet type
-        let packet_type = SocketIoPacketType::convert_from_char(chars[idx])
-            .map_err(|_| PacketCodecError::DecodeError("Unknown packet type".to_string()))?;
+        // the delimiters are all ASCII, so the rest of the data can be sliced
+        // at them without splitting a character
+        let first = match data.chars().next() {
+            Some(first) => first,
+            None => return Err(PacketCodecError::DecodeError("Data is empty".to_string())),
+        };
+        let packet_type = SocketIoPacketType::convert_from_char(first)
+            .ok_or_else(|| PacketCodecError::DecodeError("Unknown packet type".to_string()))?;
+        let mut rest = &data[first.len_utf8()..];
 
         let mut packet = SocketIoPacket {
             packet_type,
@@ -50,68 +56,57 @@ impl SocketIoPacketCodec for DefaultPacketCodec {
             data: None,
         };
 
-        // todo handle BINARY_EVENT
-        if packet.packet_type == SocketIoPacketType::BinaryAck
-            || packet.packet_type == SocketIoPacketType::BinaryEvent
-        {
-            return Err(PacketCodecError::DecodeError("Binary not supported".to_string()));
-        }
-
-        // get packet namespace
-        if chars.len() > idx + 1 && *chars.get(idx + 1).unwrap() == '/' {
-            loop {
-                idx += 1;
-                if idx >= chars.len() {
-                    break;
+        let mut attachments = 0;
+        if is_binary(packet.packet_type) {
+            // the number of attachments comes first, ended by '-'
+            let (digits, after) = rest.split_at(leading_digits(rest));
+            rest = match after.strip_prefix('-') {
+                Some(after) if !digits.is_empty() => after,
+                _ => {
+                    return Err(PacketCodecError::DecodeError(
+                        "Illegal attachments".to_string(),
+                    ))
                 }
-                let &current_char = chars.get(idx).unwrap();
-                if current_char == ',' {
-                    break;
+            };
+            attachments = match digits.parse::<usize>() {
+                Ok(n) => n,
+                _ => {
+                    return Err(PacketCodecError::DecodeError(
+                        "Illegal attachments".to_string(),
+                    ))
                 }
-                packet.nsp.push(current_char);
-            }
+            };
+        }
+
+        // get packet namespace, ended by ','
+        if rest.starts_with('/') {
+            let (nsp, after) = match rest.find(',') {
+                Some(end) => (&rest[..end], &rest[end + 1..]),
+                None => (rest, ""),
+            };
+            packet.nsp.push_str(nsp);
+            rest = after;
         } else {
             packet.nsp.push('/');
         }
 
         // lookup id
-        let next_idx = idx + 1;
-        if chars.len() > next_idx {
-            let mut id_builder = String::new();
-            // check the first char is a number between 0 - 10
-            let &next_char = chars.get(next_idx).unwrap();
-            if convert_char_to_number(next_char) != None {
-                id_builder.push(next_char);
-                idx += 1;
-                loop {
-                    idx += 1;
-
-                    // idx exceeds the length
-                    if idx >= chars.len() {
-                        break;
-                    }
-                    // check next char is number
-                    let &c = chars.get(idx).unwrap();
-
-                    // if not, minus 1 to idx
-                    if convert_char_to_number(c) == None {
-                        idx -= 1;
-                        break;
-                    }
-                    id_builder.push(c);
+        let (id, after) = rest.split_at(leading_digits(rest));
+        if !id.is_empty() {
+            match id.parse::<u32>() {
+                Ok(n) => packet.id = Some(n),
+                Err(_) => {
+                    return Err(PacketCodecError::DecodeError(
+                        "Unable to parse ID".to_string(),
+                    ))
                 }
-
-                match id_builder.parse::<u32>() {
-                    Ok(n) => packet.id = Some(n),
-                    Err(_) => return Err(PacketCodecError::DecodeError("Unable to parse ID".to_string())),
-                };
-            }
+            };
+            rest = after;
         }
 
         // parse json data
-        idx += 1;
-        if chars.len() > idx {
-            match serde_json::from_str(&data[idx..]) {
+        if !rest.is_empty() {
+            match serde_json::from_str(rest) {
                 Ok(v) => {
                     packet.data = Some(v);
                 }
@@ -119,16 +114,62 @@ impl SocketIoPacketCodec for DefaultPacketCodec {
             }
         }
 
+        if is_binary(packet.packet_type) {
+            // each attachment must be referenced exactly once
+            let mut nums = packet
+                .data
+                .as_ref()
+                .map(attachments::placeholders)
+                .unwrap_or_default();
+            nums.sort_unstable();
+            if !nums.iter().copied().eq(0..attachments) {
+                return Err(PacketCodecError::DecodeError(
+                    "Illegal attachments".to_string(),
+                ));
+            }
+        }
+
         Ok(packet)
     }
+
+    fn connect_error(
+        &self,
+        nsp: &str,
+        message: &str,
+        protocol: SocketIoProtocolVersion,
+    ) -> SocketIoPacket {
+        let data = match protocol {
+            SocketIoProtocolVersion::V4 => Value::String(message.to_string()),
+            SocketIoProtocolVersion::V5 => json!({ "message": message }),
+        };
+        SocketIoPacket {
+            packet_type: SocketIoPacketType::Error,
+            nsp: nsp.to_string(),
+            id: None,
+            data: Some(data),
+        }
+    }
+}
+
+fn is_binary(packet_type: SocketIoPacketType) -> bool {
+    packet_type == SocketIoPacketType::BinaryEvent || packet_type == SocketIoPacketType::BinaryAck
+}
+
+/// Get the length in bytes of the ASCII digits `data` starts with
+fn leading_digits(data: &str) -> usize {
+    data.bytes().take_while(u8::is_ascii_digit).count()
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::socket_io_packet_codec::attachments::placeholder;
     use crate::socket_io_packet_codec::default_packet_codec::DefaultPacketCodec;
     use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
     use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
-    use crate::socket_io_packet_codec::SocketIoPacketCodec;
+    use crate::socket_io_packet_codec::{
+        PacketCodecError, SocketIoPacketCodec, SocketIoProtocolVersion,
+    };
+    use serde_json::json;
 
     fn convert_type_to_string(packet: SocketIoPacketType) -> String {
         format!("{}", packet as u8)
@@ -351,4 +392,95 @@ mod tests {
         let decoded = DefaultPacketCodec.decode(&input).unwrap();
         assert_eq!(packet, decoded);
     }
+
+    #[test]
+    fn decode_test_attachments() {
+        match DefaultPacketCodec.decode("511-[\"hello\"]") {
+            Err(PacketCodecError::DecodeError(e)) => assert_eq!(e, "Illegal attachments"),
+            _ => panic!("missing attachments must be rejected"),
+        }
+        match DefaultPacketCodec.decode("599999999999999999999999-[\"hello\"]") {
+            Err(PacketCodecError::DecodeError(e)) => assert_eq!(e, "Illegal attachments"),
+            _ => panic!("the number of attachments must fit"),
+        }
+        match DefaultPacketCodec.decode("51[\"hello\"]") {
+            Err(PacketCodecError::DecodeError(e)) => assert_eq!(e, "Illegal attachments"),
+            _ => panic!("attachments must end with '-'"),
+        }
+    }
+
+    #[test]
+    fn binary_test() {
+        let packet = SocketIoPacket {
+            packet_type: SocketIoPacketType::BinaryEvent,
+            nsp: "/chat".to_string(),
+            id: Some(3),
+            data: Some(json!(["upload", placeholder(0), { "thumb": placeholder(1) }])),
+        };
+        let encoded = DefaultPacketCodec.encode_packet_to_string(&packet).unwrap();
+        assert_eq!(
+            encoded,
+            "52-/chat,3[\"upload\",{\"_placeholder\":true,\"num\":0},{\"thumb\":{\"_placeholder\":true,\"num\":1}}]"
+        );
+        assert_eq!(packet, DefaultPacketCodec.decode(&encoded).unwrap());
+
+        let ack = DefaultPacketCodec
+            .decode("61-7[{\"_placeholder\":true,\"num\":0}]")
+            .unwrap();
+        assert_eq!(ack.packet_type, SocketIoPacketType::BinaryAck);
+        assert_eq!(ack.nsp, "/");
+        assert_eq!(ack.id, Some(7));
+
+        match DefaultPacketCodec.decode("52-[\"upload\",{\"_placeholder\":true,\"num\":0}]") {
+            Err(PacketCodecError::DecodeError(e)) => assert_eq!(e, "Illegal attachments"),
+            _ => panic!("every attachment must have a placeholder"),
+        }
+    }
+
+    #[test]
+    fn decode_malformed_test() {
+        let packet = DefaultPacketCodec.decode("2/café,1[\"é\"]").unwrap();
+        assert_eq!(packet.nsp, "/café");
+        assert_eq!(packet.id, Some(1));
+        assert_eq!(packet.data, Some(json!(["é"])));
+        let packet = DefaultPacketCodec.decode("2/éé,[1]").unwrap();
+        assert_eq!(packet.nsp, "/éé");
+        assert_eq!(packet.data, Some(json!([1])));
+
+        for data in &[
+            "",
+            "é",
+            "7",
+            "5",
+            "5-[]",
+            "5a-[]",
+            "599-[]",
+            "2[",
+            "299999999999[]",
+        ] {
+            assert!(DefaultPacketCodec.decode(data).is_err(), "{:?}", data);
+        }
+    }
+
+    #[test]
+    fn connect_error_test() {
+        let v4 = DefaultPacketCodec.connect_error(
+            "/admin",
+            "Invalid namespace",
+            SocketIoProtocolVersion::V4,
+        );
+        assert_eq!(
+            DefaultPacketCodec.encode_packet_to_string(&v4).unwrap(),
+            "4/admin,\"Invalid namespace\""
+        );
+        let v5 = DefaultPacketCodec.connect_error(
+            "/admin",
+            "Invalid namespace",
+            SocketIoProtocolVersion::V5,
+        );
+        assert_eq!(
+            DefaultPacketCodec.encode_packet_to_string(&v5).unwrap(),
+            "4/admin,{\"message\":\"Invalid namespace\"}"
+        );
+    }
 }
@@ -1,12 +1,15 @@
+use crate::engine_io_parser::ProtocolVersion;
 use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
 
+pub mod attachments;
 pub mod default_packet_codec;
 mod number_util;
 pub mod packet_type;
+pub mod payload;
 pub mod socket_io_packet;
 
+use std::{error, fmt};
 use thiserror::Error;
-use std::{fmt, error};
 
 #[derive(Debug, Error)]
 pub enum PacketCodecError {
@@ -16,20 +19,61 @@ pub enum PacketCodecError {
     EncodeError(#[from] fmt::Error),
 }
 
+/// The revisions of the Socket.IO protocol, following the Engine.IO revision
+/// chosen in the handshake
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SocketIoProtocolVersion {
+    /// Used by socket.io-client 2.x: clients join the root namespace without
+    /// asking and connect errors are plain strings
+    V4,
+    V5,
+}
+
+impl From<ProtocolVersion> for SocketIoProtocolVersion {
+    fn from(engine_io: ProtocolVersion) -> Self {
+        match engine_io {
+            ProtocolVersion::V3 => SocketIoProtocolVersion::V4,
+            ProtocolVersion::V4 => SocketIoProtocolVersion::V5,
+        }
+    }
+}
+
 pub trait SocketIoPacketCodec {
     type Error: error::Error;
 
     fn encode(&self, packet: &SocketIoPacket, f: &mut impl fmt::Write) -> Result<(), Self::Error>;
     fn decode(&self, data: &str) -> Result<SocketIoPacket, Self::Error>;
 
-}
+    /// Get the packet refusing the connection of a client to a namespace
+    fn connect_error(
+        &self,
+        nsp: &str,
+        message: &str,
+        protocol: SocketIoProtocolVersion,
+    ) -> SocketIoPacket;
 
+    /// Encodes a packet into a newly allocated string
+    fn encode_packet_to_string(&self, packet: &SocketIoPacket) -> Result<String, Self::Error> {
+        let mut s = String::new();
+        self.encode(packet, &mut s).map(|_| s)
+    }
+}
 
+/// Object safe counterpart of [`SocketIoPacketCodec`], so that the codec can
+/// be chosen at runtime
+///
+/// [`SocketIoPacketCodec`]: trait.SocketIoPacketCodec.html
 pub(crate) trait SocketIoPacketCodecMono {
     type Error;
     fn encode_mono(&self, packet: &SocketIoPacket, f: &mut String) -> Result<(), Self::Error>;
     fn encode_to_string_mono(&self, packet: &SocketIoPacket) -> Result<String, Self::Error>;
     fn decode_mon(&self, data: &str) -> Result<SocketIoPacket, Self::Error>;
+    fn connect_error_mono(
+        &self,
+        nsp: &str,
+        message: &str,
+        protocol: SocketIoProtocolVersion,
+    ) -> SocketIoPacket;
 }
 
 impl<T: SocketIoPacketCodec> SocketIoPacketCodecMono for T {
@@ -41,10 +85,19 @@ impl<T: SocketIoPacketCodec> SocketIoPacketCodecMono for T {
 
     fn encode_to_string_mono(&self, packet: &SocketIoPacket) -> Result<String, Self::Error> {
         let mut s = String::new();
-        self.encode(packet, &mut s).map(|_| s)
+        self.encode_mono(packet, &mut s).map(|_| s)
     }
 
     fn decode_mon(&self, data: &str) -> Result<SocketIoPacket, Self::Error> {
         self.decode(data)
     }
-}
\ No newline at end of file
+
+    fn connect_error_mono(
+        &self,
+        nsp: &str,
+        message: &str,
+        protocol: SocketIoProtocolVersion,
+    ) -> SocketIoPacket {
+        self.connect_error(nsp, message, protocol)
+    }
+}
@@ -12,9 +12,9 @@ pub enum SocketIoPacketType {
     BinaryAck = 6,
 }
 
-impl Into<u8> for SocketIoPacketType {
-    fn into(self) -> u8 {
-        self as u8
+impl From<SocketIoPacketType> for u8 {
+    fn from(packet_type: SocketIoPacketType) -> Self {
+        packet_type as u8
     }
 }
 
@@ -36,11 +36,9 @@ impl TryFrom<u8> for SocketIoPacketType {
 }
 
 impl SocketIoPacketType {
-    pub fn convert_from_char(c: char) -> Result<Self, ()> {
-        match convert_char_to_number(c) {
-            Some(n) => SocketIoPacketType::try_from(n),
-            None => Err(()),
-        }
+    /// Get the packet type from the first character of an encoded packet
+    pub fn convert_from_char(c: char) -> Option<Self> {
+        convert_char_to_number(c).and_then(|n| SocketIoPacketType::try_from(n).ok())
     }
 }
 
@@ -52,7 +50,7 @@ mod tests {
 
     #[test]
     fn convert_from_char_test() {
-        let chars = vec!['0', '1', '2', '3', '4', '5', '6'];
+        let chars = ['0', '1', '2', '3', '4', '5', '6'];
         let types = vec![
             Connect,
             Disconnect,
@@ -70,13 +68,13 @@ mod tests {
 
     #[test]
     fn convert_from_char_test_error() {
-        assert!(SocketIoPacketType::convert_from_char('7').is_err());
-        assert!(SocketIoPacketType::convert_from_char('a').is_err());
+        assert!(SocketIoPacketType::convert_from_char('7').is_none());
+        assert!(SocketIoPacketType::convert_from_char('a').is_none());
     }
 
     #[test]
     fn convert_packet_type_to_u8_test() {
-        let types = vec![
+        let types = [
             Connect,
             Disconnect,
             Event,
@@ -92,7 +90,7 @@ mod tests {
 
     #[test]
     fn convert_from_u8_to_packet_type_test() {
-        let types = vec![
+        let types = [
             Connect,
             Disconnect,
             Event,
@@ -0,0 +1,104 @@
+use crate::socket_io_packet_codec::attachments;
+use serde_json::Value;
+
+/// The arguments of an event, or of its acknowledgement
+#[derive(Debug, Clone, PartialEq)]
+pub enum Payload {
+    /// JSON arguments
+    Text(Vec<Value>),
+    /// A single binary argument, sent as an attachment
+    Binary(Vec<u8>),
+}
+
+impl Payload {
+    /// Get the JSON arguments to send, with a placeholder standing for the
+    /// binary argument, and the attachments
+    pub(crate) fn into_args(self) -> (Vec<Value>, Vec<Vec<u8>>) {
+        match self {
+            Payload::Text(args) => (args, Vec::new()),
+            Payload::Binary(data) => (vec![attachments::placeholder(0)], vec![data]),
+        }
+    }
+
+    /// Rebuilds the payload of received arguments. A lone attachment is
+    /// given as binary, otherwise the attachments replace their placeholders
+    /// as arrays of bytes.
+    pub(crate) fn from_args(args: Vec<Value>, mut buffers: Vec<Vec<u8>>) -> Self {
+        if buffers.len() == 1
+            && args.len() == 1
+            && attachments::placeholder_num(&args[0]) == Some(0)
+        {
+            return Payload::Binary(buffers.remove(0));
+        }
+        if buffers.is_empty() {
+            return Payload::Text(args);
+        }
+        let args = args
+            .into_iter()
+            .map(|arg| {
+                attachments::replace_placeholders(arg, &mut |num| {
+                    buffers.get(num).map_or(Value::Null, |buffer| {
+                        Value::Array(buffer.iter().map(|&b| Value::from(b)).collect())
+                    })
+                })
+            })
+            .collect();
+        Payload::Text(args)
+    }
+}
+
+impl From<Value> for Payload {
+    fn from(value: Value) -> Self {
+        Payload::Text(vec![value])
+    }
+}
+
+impl From<Vec<Value>> for Payload {
+    fn from(values: Vec<Value>) -> Self {
+        Payload::Text(values)
+    }
+}
+
+impl From<&str> for Payload {
+    fn from(text: &str) -> Self {
+        Payload::Text(vec![Value::String(text.to_string())])
+    }
+}
+
+impl From<String> for Payload {
+    fn from(text: String) -> Self {
+        Payload::Text(vec![Value::String(text)])
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(data: Vec<u8>) -> Self {
+        Payload::Binary(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_packet_codec::attachments::placeholder;
+    use crate::socket_io_packet_codec::payload::Payload;
+    use serde_json::json;
+
+    #[test]
+    fn from_args_test() {
+        let (args, buffers) = Payload::Binary(vec![1, 2]).into_args();
+        assert_eq!(
+            Payload::Binary(vec![1, 2]),
+            Payload::from_args(args, buffers)
+        );
+
+        let args = vec![json!("name"), json!({ "data": placeholder(0) })];
+        assert_eq!(
+            Payload::Text(vec![json!("name"), json!({ "data": [1, 2] })]),
+            Payload::from_args(args, vec![vec![1, 2]])
+        );
+        assert_eq!(
+            Payload::Text(vec![json!("hello")]),
+            Payload::from_args(vec![json!("hello")], Vec::new())
+        );
+    }
+}
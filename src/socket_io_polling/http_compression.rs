@@ -0,0 +1,133 @@
+//! Compression of the polling responses, negotiated with `Accept-Encoding`
+
+use actix_web::http::header;
+use actix_web::HttpRequest;
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression;
+use std::io::{self, Write};
+
+/// The content encodings the server can compress responses with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ContentEncoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentEncoding {
+    /// Get the encoding accepted by the client, gzip being preferred. The
+    /// encodings with a q-value of 0 are not acceptable.
+    pub(crate) fn accepted_by(req: &HttpRequest) -> Option<Self> {
+        let accepted: Vec<&str> = req
+            .headers()
+            .get_all(header::ACCEPT_ENCODING)
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(acceptable_coding)
+            .collect();
+        if accepted.contains(&"gzip") {
+            Some(ContentEncoding::Gzip)
+        } else if accepted.contains(&"deflate") {
+            Some(ContentEncoding::Deflate)
+        } else {
+            None
+        }
+    }
+
+    /// The value of the `Content-Encoding` header
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+        }
+    }
+
+    pub(crate) fn encode(self, data: &[u8]) -> io::Result<Vec<u8>> {
+        match self {
+            ContentEncoding::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+            ContentEncoding::Deflate => {
+                let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+                encoder.write_all(data)?;
+                encoder.finish()
+            }
+        }
+    }
+}
+
+/// Get the content coding of an `Accept-Encoding` entry, e.g. `gzip;q=0.8`,
+/// unless its q-value makes it not acceptable
+fn acceptable_coding(entry: &str) -> Option<&str> {
+    let mut params = entry.split(';');
+    let coding = params.next()?.trim();
+    for param in params {
+        let mut pair = param.splitn(2, '=');
+        if !pair.next()?.trim().eq_ignore_ascii_case("q") {
+            continue;
+        }
+        match pair.next()?.trim().parse::<f32>() {
+            Ok(q) if q > 0.0 => {}
+            _ => return None,
+        }
+    }
+    Some(coding)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_polling::http_compression::ContentEncoding;
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    #[test]
+    fn accepted_by_test() {
+        let req = TestRequest::default()
+            .header(header::ACCEPT_ENCODING, "deflate, gzip;q=1.0, *;q=0.5")
+            .to_http_request();
+        assert_eq!(
+            ContentEncoding::accepted_by(&req),
+            Some(ContentEncoding::Gzip)
+        );
+
+        let req = TestRequest::default()
+            .header(header::ACCEPT_ENCODING, "deflate")
+            .to_http_request();
+        assert_eq!(
+            ContentEncoding::accepted_by(&req),
+            Some(ContentEncoding::Deflate)
+        );
+
+        let req = TestRequest::default()
+            .header(header::ACCEPT_ENCODING, "br")
+            .to_http_request();
+        assert_eq!(ContentEncoding::accepted_by(&req), None);
+
+        // q=0 means not acceptable
+        let req = TestRequest::default()
+            .header(header::ACCEPT_ENCODING, "gzip;q=0, deflate;q=0.5")
+            .to_http_request();
+        assert_eq!(
+            ContentEncoding::accepted_by(&req),
+            Some(ContentEncoding::Deflate)
+        );
+
+        let req = TestRequest::default()
+            .header(header::ACCEPT_ENCODING, "gzip; q=0.000")
+            .to_http_request();
+        assert_eq!(ContentEncoding::accepted_by(&req), None);
+    }
+
+    #[test]
+    fn encode_test() {
+        let encoded = ContentEncoding::Gzip.encode(b"4hello").unwrap();
+        let mut decoded = String::new();
+        GzDecoder::new(&encoded[..])
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, "4hello");
+    }
+}
@@ -0,0 +1,61 @@
+//! The JSONP variant of the polling transport. Responses are scripts calling
+//! the `___eio[j]` callback with the payload, and the client posts its
+//! payloads as the `d` field of a form.
+
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct JsonpForm {
+    d: String,
+}
+
+/// Wraps a payload in a call to the callback of index `index`
+pub(crate) fn wrap(index: u32, payload: &str) -> String {
+    // JSON strings may contain the line and paragraph separators, which end
+    // javascript strings
+    let data = serde_json::to_string(payload)
+        .unwrap_or_default()
+        .replace('\u{2028}', "\\u2028")
+        .replace('\u{2029}', "\\u2029");
+    format!("___eio[{}]({});", index, data)
+}
+
+/// Get the payload of a posted form. The client escapes the newlines of the
+/// payload as `\n`, an escaped backslash being kept as is.
+pub(crate) fn unwrap_form(body: &[u8]) -> Option<String> {
+    let form: JsonpForm = serde_urlencoded::from_bytes(body).ok()?;
+    let mut payload = String::with_capacity(form.d.len());
+    let mut chars = form.d.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            payload.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => payload.push('\n'),
+            Some(next) => {
+                payload.push(c);
+                payload.push(next);
+            }
+            None => payload.push(c),
+        }
+    }
+    Some(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_polling::jsonp::{unwrap_form, wrap};
+
+    #[test]
+    fn wrap_test() {
+        assert_eq!(wrap(0, "4\"hi\"\u{2028}"), r#"___eio[0]("4\"hi\"\u2028");"#);
+    }
+
+    #[test]
+    fn unwrap_form_test() {
+        assert_eq!(unwrap_form(b"d=4hello%5Cnworld").unwrap(), "4hello\nworld");
+        assert_eq!(unwrap_form(b"d=4a%5C%5Cnb").unwrap(), "4a\\\\nb");
+        assert!(unwrap_form(b"e=4hello").is_none());
+    }
+}
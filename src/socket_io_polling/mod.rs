@@ -0,0 +1,4 @@
+mod http_compression;
+mod jsonp;
+pub mod polling_transport;
+pub mod socket_io_polling_impl;
@@ -0,0 +1,150 @@
+use crate::engine_io_parser::packet::{Packet, PacketType};
+use crate::engine_io_server::transport::{
+    Transport, TransportError, TransportEvent, TransportEvents,
+};
+use crate::socket_io_connection::transport_message::PollPayload;
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Default)]
+struct PollingState {
+    /// The polling request waiting for packets to send
+    pending_poll: Option<oneshot::Sender<PollPayload>>,
+    closed: bool,
+}
+
+/// The HTTP long-polling transport. It is writable while a polling request
+/// waits for packets.
+#[derive(Debug)]
+pub struct PollingTransport {
+    state: Arc<Mutex<PollingState>>,
+}
+
+/// Hands the requests of the client to a [`PollingTransport`]
+///
+/// [`PollingTransport`]: struct.PollingTransport.html
+#[derive(Debug, Clone)]
+pub struct PollingHandle {
+    state: Arc<Mutex<PollingState>>,
+    events: mpsc::UnboundedSender<TransportEvent>,
+}
+
+impl PollingTransport {
+    pub fn new() -> (Self, PollingHandle, TransportEvents) {
+        let state = Arc::new(Mutex::new(PollingState::default()));
+        let (events, receiver) = mpsc::unbounded();
+        let handle = PollingHandle {
+            state: state.clone(),
+            events,
+        };
+        (PollingTransport { state }, handle, receiver)
+    }
+}
+
+impl PollingHandle {
+    /// A polling request waits for the packets to send. Returns `None` when
+    /// the transport is closed or another request is already waiting, which
+    /// closes the transport.
+    pub fn poll(&self) -> Option<oneshot::Receiver<PollPayload>> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            return None;
+        }
+        if state.pending_poll.is_some() {
+            drop(state);
+            self.on_error(TransportError::InvalidData(
+                "overlapping polling requests".to_string(),
+            ));
+            return None;
+        }
+
+        let (sender, receiver) = oneshot::channel();
+        state.pending_poll = Some(sender);
+        let _ = self.events.unbounded_send(TransportEvent::Drain);
+        Some(receiver)
+    }
+
+    /// Forwards the packets the client posted
+    pub fn on_packets(&self, packets: Vec<Packet>) {
+        for packet in packets {
+            let _ = self.events.unbounded_send(TransportEvent::Packet(packet));
+        }
+    }
+
+    /// Closes the transport after an invalid request
+    pub fn on_error(&self, err: TransportError) {
+        self.state.lock().unwrap().closed = true;
+        let _ = self.events.unbounded_send(TransportEvent::Error(err));
+    }
+}
+
+impl Transport for PollingTransport {
+    fn name(&self) -> &'static str {
+        "polling"
+    }
+
+    fn writable(&self) -> bool {
+        self.state.lock().unwrap().pending_poll.is_some()
+    }
+
+    fn send(
+        &mut self,
+        packets: Vec<Packet>,
+        compress: bool,
+    ) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let result = match self.state.lock().unwrap().pending_poll.take() {
+            Some(pending_poll) => pending_poll
+                .send(PollPayload { packets, compress })
+                .map_err(|_| TransportError::Closed),
+            None => Err(TransportError::NotWritable),
+        };
+        future::ready(result).boxed_local()
+    }
+
+    /// Closes the transport, answering the waiting request with a close
+    /// packet
+    fn close(&mut self) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        if let Some(pending_poll) = state.pending_poll.take() {
+            let _ = pending_poll.send(PollPayload {
+                packets: vec![Packet::new(PacketType::Close, Vec::new())],
+                compress: false,
+            });
+        }
+        future::ready(Ok(())).boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_parser::packet::{Packet, PacketType};
+    use crate::engine_io_server::transport::{Transport, TransportEvent};
+    use crate::socket_io_polling::polling_transport::PollingTransport;
+    use futures::StreamExt;
+
+    #[actix_rt::test]
+    async fn polling_transport_test() {
+        let (mut transport, handle, mut events) = PollingTransport::new();
+        assert!(!transport.writable());
+
+        let receiver = handle.poll().unwrap();
+        assert!(matches!(events.next().await, Some(TransportEvent::Drain)));
+        assert!(transport.writable());
+        let packet = Packet::new(PacketType::Message, b"hello".to_vec());
+        transport.send(vec![packet], false).await.unwrap();
+        let payload = receiver.await.unwrap();
+        assert_eq!(payload.packets[0].data, b"hello");
+        assert!(!transport.writable());
+
+        // overlapping requests close the transport
+        let _receiver = handle.poll().unwrap();
+        assert!(handle.poll().is_none());
+        events.next().await;
+        assert!(matches!(
+            events.next().await,
+            Some(TransportEvent::Error(_))
+        ));
+    }
+}
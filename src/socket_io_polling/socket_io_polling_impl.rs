@@ -0,0 +1,193 @@
+use crate::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use crate::engine_io_parser::{EngineIoParser, ProtocolVersion};
+use crate::engine_io_server::error::{EngineIoError, EngineIoErrorCode};
+use crate::engine_io_server::transport::TransportError;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use crate::socket_io_connection::transport_message::{GetPolling, PollPayload};
+use crate::socket_io_metrics::metrics::Direction;
+use crate::socket_io_polling::http_compression::ContentEncoding;
+use crate::socket_io_polling::jsonp;
+use crate::socket_io_polling::polling_transport::PollingHandle;
+use crate::socket_io_websocket::app_state::AppState;
+use actix::Addr;
+use actix_web::http::header;
+use actix_web::{web, HttpRequest, HttpResponse, ResponseError};
+use futures::StreamExt;
+
+/// How the payloads of the polling requests are carried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollingFormat {
+    /// The payloads are the bodies of the requests
+    Xhr,
+    /// The responses call the JSONP callback of the given index and the
+    /// client posts forms
+    Jsonp(u32),
+}
+
+/// The HTTP long-polling transport of a [`SocketIoConnection`]. The client
+/// receives packets by sending GET requests, which are answered once there are
+/// packets to send, and sends packets in POST requests.
+///
+/// [`SocketIoConnection`]: ../../socket_io_connection/socket_io_connection_impl/struct.SocketIoConnection.html
+#[derive(Debug)]
+pub struct SocketIoPolling;
+
+impl SocketIoPolling {
+    /// Opens a connection, answering with the open packet and the session
+    /// cookie
+    pub async fn handshake(
+        data: web::Data<AppState>,
+        r: &HttpRequest,
+        protocol: ProtocolVersion,
+        format: PollingFormat,
+    ) -> HttpResponse {
+        let (sid, _, polling) =
+            SocketIoConnection::open_polling(data.clone(), protocol, r.peer_addr());
+        let mut res = SocketIoPolling::wait(&data, r, protocol, format, &polling).await;
+        data.options.set_session_cookie(&mut res, &sid);
+        res
+    }
+
+    /// Waits for the packets the connection sends
+    pub async fn poll(
+        data: &AppState,
+        r: &HttpRequest,
+        protocol: ProtocolVersion,
+        format: PollingFormat,
+        connection: Addr<SocketIoConnection>,
+    ) -> HttpResponse {
+        match connection.send(GetPolling).await {
+            Ok(Some(polling)) => SocketIoPolling::wait(data, r, protocol, format, &polling).await,
+            _ => EngineIoError::new(EngineIoErrorCode::BadRequest).error_response(),
+        }
+    }
+
+    async fn wait(
+        data: &AppState,
+        r: &HttpRequest,
+        protocol: ProtocolVersion,
+        format: PollingFormat,
+        polling: &PollingHandle,
+    ) -> HttpResponse {
+        let receiver = match polling.poll() {
+            Some(receiver) => receiver,
+            None => return EngineIoError::new(EngineIoErrorCode::BadRequest).error_response(),
+        };
+        match receiver.await {
+            Ok(payload) => SocketIoPolling::respond(data, r, protocol, format, payload),
+            Err(_) => EngineIoError::new(EngineIoErrorCode::BadRequest).error_response(),
+        }
+    }
+
+    /// Forwards the packets the client sends to the connection. A request
+    /// larger than the maximum size of a message, or with too many packets,
+    /// closes the connection.
+    pub async fn post(
+        data: &AppState,
+        protocol: ProtocolVersion,
+        format: PollingFormat,
+        connection: Addr<SocketIoConnection>,
+        mut payload: web::Payload,
+    ) -> HttpResponse {
+        let polling = match connection.send(GetPolling).await {
+            Ok(Some(polling)) => polling,
+            _ => return EngineIoError::new(EngineIoErrorCode::BadRequest).error_response(),
+        };
+        let max_size = data.options.max_http_buffer_size;
+        let mut body = web::BytesMut::new();
+        while let Some(chunk) = payload.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => return SocketIoPolling::on_error(&polling, e.to_string()),
+            };
+            if body.len() + chunk.len() > max_size {
+                SocketIoPolling::on_error(&polling, "payload is too large".to_string());
+                return HttpResponse::PayloadTooLarge().finish();
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        let body = match format {
+            PollingFormat::Xhr => String::from_utf8(body.to_vec()).ok(),
+            PollingFormat::Jsonp(_) => jsonp::unwrap_form(&body),
+        };
+        let max_packets = data.options.max_payload_packets;
+        let packets = body.and_then(|data| {
+            match protocol {
+                ProtocolVersion::V3 => EngineIOParserImpl::decode_payload_v3(&data, max_packets),
+                ProtocolVersion::V4 => {
+                    EngineIOParserImpl::decode_payload_limited(&data, max_packets)
+                }
+            }
+            .ok()
+        });
+        match packets {
+            Some(packets) => {
+                if let Some(metrics) = data.options.metrics.as_ref() {
+                    for packet in packets.iter() {
+                        metrics.on_engine_io_packet(Direction::In, packet);
+                    }
+                }
+                polling.on_packets(packets)
+            }
+            None => return SocketIoPolling::on_error(&polling, "invalid payload".to_string()),
+        }
+        HttpResponse::Ok().content_type("text/html").body("ok")
+    }
+
+    /// Closes the connection after an invalid request
+    fn on_error(polling: &PollingHandle, message: String) -> HttpResponse {
+        polling.on_error(TransportError::InvalidData(message));
+        EngineIoError::new(EngineIoErrorCode::BadRequest).error_response()
+    }
+
+    /// Answers a polling request. The response is compressed when enabled,
+    /// large enough and accepted by the client.
+    fn respond(
+        data: &AppState,
+        r: &HttpRequest,
+        protocol: ProtocolVersion,
+        format: PollingFormat,
+        payload: PollPayload,
+    ) -> HttpResponse {
+        if let Some(metrics) = data.options.metrics.as_ref() {
+            for packet in payload.packets.iter() {
+                metrics.on_engine_io_packet(Direction::Out, packet);
+            }
+        }
+        let body = match protocol {
+            ProtocolVersion::V3 => EngineIOParserImpl::encode_payload_v3(payload.packets),
+            ProtocolVersion::V4 => EngineIOParserImpl::encode_payload(payload.packets),
+        };
+        let body = match body {
+            Ok(body) => body,
+            Err(_) => return HttpResponse::InternalServerError().finish(),
+        };
+        let mut res = HttpResponse::Ok();
+        let body = match format {
+            PollingFormat::Xhr => {
+                res.content_type("text/plain; charset=UTF-8");
+                body
+            }
+            PollingFormat::Jsonp(index) => {
+                res.content_type("text/javascript; charset=UTF-8");
+                jsonp::wrap(index, &body)
+            }
+        };
+
+        let encoding = match data.options.http_compression.as_ref() {
+            Some(options) if payload.compress && body.len() >= options.threshold => {
+                ContentEncoding::accepted_by(r)
+            }
+            _ => None,
+        };
+        if let Some(encoding) = encoding {
+            if let Ok(encoded) = encoding.encode(body.as_bytes()) {
+                return res
+                    .header(header::CONTENT_ENCODING, encoding.name())
+                    .body(encoded);
+            }
+        }
+        res.body(body)
+    }
+}
@@ -0,0 +1,66 @@
+use crate::engine_io_parser::packet::Packet;
+use crate::engine_io_server::transport::{
+    Transport, TransportError, TransportEvent, TransportEvents,
+};
+use futures::channel::mpsc;
+use futures::future::{self, FutureExt, LocalBoxFuture};
+
+/// An in-memory transport, handing the packets of a session to a virtual
+/// client as they are sent. It is always writable, like a websocket.
+#[derive(Debug)]
+pub(crate) struct MockTransport {
+    /// Dropped once the transport is closed, which the client notices
+    packets: Option<mpsc::UnboundedSender<Packet>>,
+}
+
+/// The client end of a [`MockTransport`]
+pub(crate) struct MockPeer {
+    /// The packets sent by the server
+    pub(crate) packets: mpsc::UnboundedReceiver<Packet>,
+    /// What the client sends, as the transport would report it
+    pub(crate) events: mpsc::UnboundedSender<TransportEvent>,
+}
+
+impl MockTransport {
+    /// Creates a transport along with its events, for the session, and its
+    /// client end
+    pub(crate) fn pair() -> (Self, TransportEvents, MockPeer) {
+        let (packets_sender, packets) = mpsc::unbounded();
+        let (events, events_receiver) = mpsc::unbounded();
+        let transport = MockTransport {
+            packets: Some(packets_sender),
+        };
+        (transport, events_receiver, MockPeer { packets, events })
+    }
+}
+
+impl Transport for MockTransport {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    fn writable(&self) -> bool {
+        self.packets.is_some()
+    }
+
+    fn send(
+        &mut self,
+        packets: Vec<Packet>,
+        _: bool,
+    ) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let result = match self.packets.as_ref() {
+            Some(sender) => packets.into_iter().try_for_each(|packet| {
+                sender
+                    .unbounded_send(packet)
+                    .map_err(|_| TransportError::Closed)
+            }),
+            None => Err(TransportError::Closed),
+        };
+        future::ready(result).boxed_local()
+    }
+
+    fn close(&mut self) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        self.packets = None;
+        future::ready(Ok(())).boxed_local()
+    }
+}
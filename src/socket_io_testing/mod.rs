@@ -0,0 +1,16 @@
+//! An in-process test harness: a server built from an [`AppState`] and
+//! virtual clients connected to it through an in-memory transport, without
+//! binding any port.
+//!
+//! Everything runs on the current arbiter, so the packets of a connection
+//! arrive in the order they were sent. The heartbeat of the connections
+//! follows a virtual clock, which only moves when the test advances it.
+//!
+//! Enabled in the tests of this crate, and by the `test-util` feature.
+//!
+//! [`AppState`]: ../socket_io_websocket/app_state/struct.AppState.html
+
+pub(crate) mod mock_transport;
+pub mod test_server;
+pub mod virtual_client;
+pub(crate) mod virtual_clock;
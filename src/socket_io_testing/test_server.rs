@@ -0,0 +1,846 @@
+use crate::engine_io_parser::ProtocolVersion;
+use crate::socket_io::server_options::ServerOptions;
+use crate::socket_io_adaptor::chat_room_manager::BroadcastOptions;
+use crate::socket_io_adaptor::disconnect_reason::DisconnectReason;
+use crate::socket_io_adaptor::namespace::Namespace;
+use crate::socket_io_adaptor::namespace_manager::NamespaceManager;
+use crate::socket_io_connection::clock::Clock;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use crate::socket_io_testing::mock_transport::MockTransport;
+use crate::socket_io_testing::virtual_client::VirtualClient;
+use crate::socket_io_testing::virtual_clock::VirtualClock;
+use crate::socket_io_websocket::app_state::AppState;
+use actix_web::web::Data;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How many times [`TestServer::settle`] yields to the other tasks
+///
+/// [`TestServer::settle`]: struct.TestServer.html#method.settle
+const SETTLE_YIELDS: usize = 64;
+
+/// A socket that left a namespace, as the namespace reported it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisconnectRecord {
+    pub nsp: String,
+    /// The socket id
+    pub sid: String,
+    pub reason: DisconnectReason,
+}
+
+/// Makes a namespace acknowledge each event of its clients with the event
+/// arguments, then relay the event to all of its sockets, the sender
+/// included, like the server the conformance tests expect
+pub fn relay_events(namespace: &mut Namespace) {
+    namespace.on_event(|namespace, _, event_name, payload, ack| {
+        if let Some(ack) = ack {
+            ack.send(payload.clone());
+        }
+        namespace.emit_payload_with_options(&BroadcastOptions::default(), event_name, payload);
+    });
+}
+
+/// A server running in the current arbiter, whose clients are
+/// [`VirtualClient`]s. Its connections check their heartbeat as the virtual
+/// clock is [`advance`]d.
+///
+/// [`advance`]: #method.advance
+/// [`VirtualClient`]: ../virtual_client/struct.VirtualClient.html
+#[derive(Debug)]
+pub struct TestServer {
+    app_data: Data<AppState>,
+    clock: VirtualClock,
+    disconnects: Arc<Mutex<Vec<DisconnectRecord>>>,
+}
+
+impl Default for TestServer {
+    fn default() -> Self {
+        Self::with_options(ServerOptions::default())
+    }
+}
+
+impl TestServer {
+    /// Builds a server from its state. The disconnections of the sockets of
+    /// its namespaces are recorded.
+    pub fn new(mut app_state: AppState) -> Self {
+        let clock = VirtualClock::default();
+        app_state.clock = Clock::Virtual(clock.clone());
+        let disconnects = Arc::new(Mutex::new(Vec::new()));
+        for namespace in app_state
+            .namespace_manager
+            .write()
+            .unwrap()
+            .get_adaptors_mut()
+        {
+            let nsp = namespace.get_name().to_string();
+            let disconnects = disconnects.clone();
+            namespace.on_disconnect(move |sid, reason| {
+                disconnects.lock().unwrap().push(DisconnectRecord {
+                    nsp: nsp.clone(),
+                    sid: sid.to_string(),
+                    reason,
+                });
+            });
+        }
+        TestServer {
+            app_data: Data::new(app_state),
+            clock,
+            disconnects,
+        }
+    }
+
+    /// Builds a server with the root namespace only
+    pub fn with_options(options: ServerOptions) -> Self {
+        Self::new(AppState::with_options(NamespaceManager::new(), options))
+    }
+
+    /// Makes the namespaces of the server relay the events of their clients,
+    /// see [`relay_events`]
+    ///
+    /// [`relay_events`]: fn.relay_events.html
+    pub fn relay_events(&self) {
+        for namespace in self
+            .app_data
+            .namespace_manager
+            .write()
+            .unwrap()
+            .get_adaptors_mut()
+        {
+            relay_events(namespace);
+        }
+    }
+
+    /// Get the state of the server, to look at its namespaces or broadcast
+    /// from them
+    pub fn app_data(&self) -> &Data<AppState> {
+        &self.app_data
+    }
+
+    /// Opens a connection with the Engine.IO protocol v4 and waits for the
+    /// open packet
+    pub async fn connect(&self) -> VirtualClient {
+        self.connect_with_protocol(ProtocolVersion::V4).await
+    }
+
+    pub async fn connect_with_protocol(&self, protocol: ProtocolVersion) -> VirtualClient {
+        let (transport, events, peer) = MockTransport::pair();
+        SocketIoConnection::open(
+            self.app_data.clone(),
+            Box::new(transport),
+            events,
+            protocol,
+            None,
+        );
+        VirtualClient::open(self.app_data.clone(), peer).await
+    }
+
+    /// Opens `n` connections, one after the other
+    pub async fn connect_clients(&self, n: usize) -> Vec<VirtualClient> {
+        let mut clients = Vec::with_capacity(n);
+        for _ in 0..n {
+            clients.push(self.connect().await);
+        }
+        clients
+    }
+
+    /// Lets the actors handle the messages sent so far, so that what they
+    /// send in return can be looked at without waiting
+    pub async fn settle(&self) {
+        for _ in 0..SETTLE_YIELDS {
+            let _ = tokio::task::yield_now().await;
+        }
+    }
+
+    /// Moves the virtual clock forward. The heartbeat timers of the
+    /// connections go off as their time passes, one at a time.
+    pub async fn advance(&self, duration: Duration) {
+        let until = self.clock.elapsed() + duration;
+        // what the clients sent so far happened before
+        self.settle().await;
+        while self.clock.step(until) {
+            self.settle().await;
+        }
+    }
+
+    /// Get the rooms of a socket, including the one named after its id,
+    /// sorted
+    pub fn rooms(&self, nsp: &str, sid: &str) -> Vec<String> {
+        let namespace_manager = self.app_data.namespace_manager.read().unwrap();
+        let mut rooms = namespace_manager
+            .get_adaptor(nsp)
+            .map(|namespace| namespace.get_socket_rooms(sid))
+            .unwrap_or_default();
+        rooms.sort();
+        rooms
+    }
+
+    /// Get the ids of the sockets in a room, or of every socket of the
+    /// namespace when `room` is `None`, sorted
+    pub fn sockets(&self, nsp: &str, room: Option<&str>) -> Vec<String> {
+        let opts = BroadcastOptions {
+            rooms: room.into_iter().map(|room| room.to_string()).collect(),
+            ..Default::default()
+        };
+        let namespace_manager = self.app_data.namespace_manager.read().unwrap();
+        let mut sids = namespace_manager
+            .get_adaptor(nsp)
+            .map(|namespace| namespace.get_matching_sids(&opts))
+            .unwrap_or_default();
+        sids.sort();
+        sids
+    }
+
+    /// Get the sockets that left a namespace so far, in the order they left
+    pub fn disconnects(&self) -> Vec<DisconnectRecord> {
+        self.disconnects.lock().unwrap().clone()
+    }
+
+    /// Get why a socket left a namespace, if it did
+    pub fn disconnect_reason(&self, nsp: &str, sid: &str) -> Option<DisconnectReason> {
+        self.disconnects
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|record| record.nsp == nsp && record.sid == sid)
+            .map(|record| record.reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_parser::packet::{Packet, PacketType};
+    use crate::engine_io_parser::ProtocolVersion;
+    use crate::socket_io::server_options::ServerOptions;
+    use crate::socket_io_adaptor::chat_room_manager::BroadcastFlags;
+    use crate::socket_io_adaptor::disconnect_reason::DisconnectReason;
+    use crate::socket_io_adaptor::namespace_manager::NamespaceManager;
+    use crate::socket_io_connection::socket_io_connection_impl::RAW_PACKETS_TARGET;
+    use crate::socket_io_packet_codec::attachments::placeholder;
+    use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+    use crate::socket_io_packet_codec::payload::Payload;
+    use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+    use crate::socket_io_testing::test_server::TestServer;
+    use crate::socket_io_websocket::app_state::AppState;
+    use actix_rt::time::delay_for;
+    use futures::channel::oneshot;
+    use serde_json::json;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tracing_subscriber::EnvFilter;
+
+    #[actix_rt::test]
+    async fn broadcast_test() {
+        let server = TestServer::default();
+        server.relay_events();
+        let mut clients = server.connect_clients(3).await;
+        let mut sids = Vec::new();
+        for client in clients.iter_mut() {
+            sids.push(client.connect("/", None).await.unwrap());
+        }
+        sids.sort();
+        assert_eq!(server.sockets("/", None), sids);
+        assert_eq!(server.rooms("/", &sids[0]), vec![sids[0].clone()]);
+
+        // events are acknowledged with their arguments, and relayed to every
+        // socket of the namespace
+        let id = clients[0].emit_with_ack("/", "chat message", vec![json!("hello")]);
+        let ack = clients[0].recv_socket_io().await.unwrap();
+        assert_eq!(ack.packet_type, SocketIoPacketType::Ack);
+        assert_eq!(ack.id, Some(id));
+        assert_eq!(ack.data, Some(json!(["hello"])));
+        for client in clients.iter_mut() {
+            let packet = client.recv_socket_io().await.unwrap();
+            assert_eq!(packet.packet_type, SocketIoPacketType::Event);
+            assert_eq!(packet.data, Some(json!(["chat message", "hello"])));
+            assert_eq!(packet.id, None);
+        }
+        server.settle().await;
+        assert!(clients[0].try_recv().is_none());
+
+        {
+            let mut namespace_manager = server.app_data().namespace_manager.write().unwrap();
+            let namespace = namespace_manager.get_adaptor_mut("/").unwrap();
+            namespace.join_room(&sids[1], "room1");
+            namespace.emit_to("room1", "news", "in room1");
+        }
+        assert_eq!(server.sockets("/", Some("room1")), vec![sids[1].clone()]);
+        let receiver = clients
+            .iter_mut()
+            .find(|client| client.socket_id("/") == Some(sids[1].as_str()))
+            .unwrap();
+        let packet = receiver.recv_socket_io().await.unwrap();
+        assert_eq!(packet.data, Some(json!(["news", "in room1"])));
+    }
+
+    #[actix_rt::test]
+    async fn binary_event_test() {
+        let server = TestServer::default();
+        server.relay_events();
+        let mut clients = server.connect_clients(2).await;
+        for client in clients.iter_mut() {
+            client.connect("/", None).await.unwrap();
+        }
+
+        // the event is handled once its attachment is received
+        clients[0].send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::BinaryEvent,
+            nsp: "/".to_string(),
+            id: Some(7),
+            data: Some(json!(["file", placeholder(0)])),
+        });
+        server.settle().await;
+        assert!(clients[1].try_recv().is_none());
+        clients[0].send(Packet::binary(vec![1, 2, 3]));
+
+        let ack = clients[0].recv_socket_io().await.unwrap();
+        assert_eq!(ack.packet_type, SocketIoPacketType::BinaryAck);
+        assert_eq!(ack.id, Some(7));
+        assert_eq!(ack.data, Some(json!([placeholder(0)])));
+        assert_eq!(
+            clients[0].recv().await.unwrap(),
+            Packet::binary(vec![1, 2, 3])
+        );
+        for client in clients.iter_mut() {
+            let packet = client.recv_socket_io().await.unwrap();
+            assert_eq!(packet.packet_type, SocketIoPacketType::BinaryEvent);
+            assert_eq!(packet.data, Some(json!(["file", placeholder(0)])));
+            assert_eq!(client.recv().await.unwrap(), Packet::binary(vec![1, 2, 3]));
+        }
+
+        // an attachment no packet expects closes the connection
+        clients[1].send(Packet::binary(vec![4]));
+        clients[1].closed().await;
+    }
+
+    #[actix_rt::test]
+    async fn event_listeners_test() {
+        let server = TestServer::default();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        {
+            let events = events.clone();
+            let mut namespace_manager = server.app_data().namespace_manager.write().unwrap();
+            let namespace = namespace_manager.get_adaptor_mut("/").unwrap();
+            namespace.on_event(move |_, sid, event_name, payload, ack| {
+                events
+                    .lock()
+                    .unwrap()
+                    .push((sid.to_string(), event_name.to_string(), payload));
+                if let Some(ack) = ack {
+                    ack.send(Payload::Binary(vec![4, 5]));
+                }
+            });
+        }
+        let mut clients = server.connect_clients(2).await;
+        let sid = clients[0].connect("/", None).await.unwrap();
+        clients[1].connect("/", None).await.unwrap();
+
+        // the listeners get the arguments, attachments included, and may
+        // acknowledge the event
+        clients[0].send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::BinaryEvent,
+            nsp: "/".to_string(),
+            id: Some(3),
+            data: Some(json!(["upload", placeholder(0)])),
+        });
+        clients[0].send(Packet::binary(vec![1, 2, 3]));
+        let ack = clients[0].recv_socket_io().await.unwrap();
+        assert_eq!(ack.packet_type, SocketIoPacketType::BinaryAck);
+        assert_eq!(ack.id, Some(3));
+        assert_eq!(ack.data, Some(json!([placeholder(0)])));
+        assert_eq!(clients[0].recv().await.unwrap(), Packet::binary(vec![4, 5]));
+
+        // nothing is relayed by the server itself
+        clients[0].emit("/", "chat message", vec![json!("hello")]);
+        server.settle().await;
+        assert!(clients[0].try_recv().is_none());
+        assert!(clients[1].try_recv().is_none());
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (
+                    sid.clone(),
+                    "upload".to_string(),
+                    Payload::Binary(vec![1, 2, 3])
+                ),
+                (sid, "chat message".to_string(), Payload::from("hello")),
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn attachment_limits_test() {
+        let options = ServerOptions::builder()
+            .max_attachments(1)
+            .max_http_buffer_size(64)
+            .build();
+        let server = TestServer::with_options(options);
+        let mut clients = server.connect_clients(2).await;
+        let mut sids = Vec::new();
+        for client in clients.iter_mut() {
+            sids.push(client.connect("/", None).await.unwrap());
+        }
+
+        // a packet with too many attachments closes the connection
+        clients[0].send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::BinaryEvent,
+            nsp: "/".to_string(),
+            id: None,
+            data: Some(json!(["files", placeholder(0), placeholder(1)])),
+        });
+        clients[0].closed().await;
+
+        // so does a packet larger than a message along with its attachments
+        clients[1].send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::BinaryEvent,
+            nsp: "/".to_string(),
+            id: None,
+            data: Some(json!(["file", placeholder(0)])),
+        });
+        clients[1].send(Packet::binary(vec![0; 64]));
+        clients[1].closed().await;
+        server.settle().await;
+        assert_eq!(
+            server.disconnect_reason("/", &sids[0]),
+            Some(DisconnectReason::ParseError)
+        );
+        assert_eq!(
+            server.disconnect_reason("/", &sids[1]),
+            Some(DisconnectReason::TransportError)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn server_ack_test() {
+        let server = TestServer::with_options(ServerOptions::builder().metrics(true).build());
+        let mut client = server.connect().await;
+        let sid = client.connect("/", None).await.unwrap();
+
+        let (sender, receiver) = oneshot::channel();
+        let (dropped_sender, dropped) = oneshot::channel();
+        {
+            let namespace_manager = server.app_data().namespace_manager.read().unwrap();
+            let namespace = namespace_manager.get_adaptor("/").unwrap();
+            let flags = BroadcastFlags::default();
+            assert!(
+                namespace.emit_with_ack(&sid, &flags, "question", "ready?", move |payload| {
+                    let _ = sender.send(payload);
+                })
+            );
+            assert!(
+                namespace.emit_with_ack(&sid, &flags, "question", "again?", move |payload| {
+                    let _ = dropped_sender.send(payload);
+                })
+            );
+            assert!(!namespace.emit_with_ack("unknown", &flags, "question", "?", |_| {}));
+        }
+
+        let first = client.recv_socket_io().await.unwrap();
+        assert_eq!(first.data, Some(json!(["question", "ready?"])));
+        let second = client.recv_socket_io().await.unwrap();
+        assert_ne!(first.id, second.id);
+        server.advance(Duration::from_millis(30)).await;
+        client.send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::Ack,
+            nsp: "/".to_string(),
+            id: first.id,
+            data: Some(json!(["yes"])),
+        });
+        assert_eq!(receiver.await.unwrap(), Some(Payload::from("yes")));
+        let metrics = server.app_data().options.metrics.as_ref().unwrap().render();
+        for line in [
+            "socketio_ack_latency_seconds_bucket{le=\"0.025\"} 0",
+            "socketio_ack_latency_seconds_bucket{le=\"0.05\"} 1",
+            "socketio_ack_latency_seconds_count 1",
+        ]
+        .iter()
+        {
+            assert!(metrics.lines().any(|l| l == *line), "{} is missing", line);
+        }
+
+        // the callbacks are told when the socket leaves unacknowledged
+        client.disconnect("/");
+        assert_eq!(dropped.await.unwrap(), None);
+    }
+
+    #[actix_rt::test]
+    async fn disconnect_reasons_test() {
+        let server = TestServer::default();
+        let mut clients = server.connect_clients(3).await;
+        let mut sids = Vec::new();
+        for client in clients.iter_mut() {
+            sids.push(client.connect("/", None).await.unwrap());
+        }
+        assert_eq!(
+            clients[0].connect("/admin", None).await,
+            Err(json!({ "message": "Invalid namespace" }))
+        );
+
+        clients[0].disconnect("/");
+        clients[1].close();
+        server
+            .app_data()
+            .namespace_manager
+            .write()
+            .unwrap()
+            .get_adaptor_mut("/")
+            .unwrap()
+            .disconnect_socket(&sids[2], false);
+
+        // the server closes the connection once its last namespace is left
+        // on its side
+        clients[1].closed().await;
+        let packets = clients[2].closed().await;
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].data, b"1");
+        server.settle().await;
+        assert_eq!(
+            server.disconnect_reason("/", &sids[0]),
+            Some(DisconnectReason::ClientNamespaceDisconnect)
+        );
+        assert_eq!(
+            server.disconnect_reason("/", &sids[1]),
+            Some(DisconnectReason::TransportClose)
+        );
+        assert_eq!(
+            server.disconnect_reason("/", &sids[2]),
+            Some(DisconnectReason::ServerNamespaceDisconnect)
+        );
+        assert!(server.sockets("/", None).is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn disconnect_socket_test() {
+        let mut nsm = NamespaceManager::new();
+        nsm.create_namespace("/chat").unwrap();
+        let server = TestServer::new(AppState::with_options(nsm, ServerOptions::default()));
+        server.relay_events();
+        let disconnect = |nsp: &str, sid: &str, close: bool| {
+            server
+                .app_data()
+                .namespace_manager
+                .write()
+                .unwrap()
+                .get_adaptor_mut(nsp)
+                .unwrap()
+                .disconnect_socket(sid, close);
+        };
+        let mut clients = server.connect_clients(2).await;
+        let root = clients[0].connect("/", None).await.unwrap();
+        let chat = clients[0].connect("/chat", None).await.unwrap();
+        server
+            .app_data()
+            .namespace_manager
+            .write()
+            .unwrap()
+            .get_adaptor_mut("/chat")
+            .unwrap()
+            .join_room(&chat, "room1");
+
+        // the socket leaves its namespace and its rooms, the connection stays
+        // open for the other namespace
+        disconnect("/chat", &chat, false);
+        let packet = clients[0].recv_socket_io().await.unwrap();
+        assert_eq!(packet.packet_type, SocketIoPacketType::Disconnect);
+        assert_eq!(packet.nsp, "/chat");
+        assert!(server.rooms("/chat", &chat).is_empty());
+        assert!(server.sockets("/chat", Some("room1")).is_empty());
+        assert_eq!(
+            server.disconnect_reason("/chat", &chat),
+            Some(DisconnectReason::ServerNamespaceDisconnect)
+        );
+        clients[0].emit("/", "chat message", vec![json!("still open")]);
+        let packet = clients[0].recv_socket_io().await.unwrap();
+        assert_eq!(packet.data, Some(json!(["chat message", "still open"])));
+
+        // the connection closes once its last namespace is left
+        disconnect("/", &root, false);
+        let packets = clients[0].closed().await;
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].data, b"1");
+
+        // or right away when asked to
+        let root = clients[1].connect("/", None).await.unwrap();
+        let chat = clients[1].connect("/chat", None).await.unwrap();
+        disconnect("/", &root, true);
+        let packets = clients[1].closed().await;
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].data, b"1");
+        server.settle().await;
+        assert!(server.sockets("/chat", None).is_empty());
+        assert_eq!(
+            server.disconnect_reason("/", &root),
+            Some(DisconnectReason::ServerNamespaceDisconnect)
+        );
+        // the socket of the other namespace was not disconnected by the
+        // server, it lost its connection
+        assert_eq!(
+            server.disconnect_reason("/chat", &chat),
+            Some(DisconnectReason::ForcedClose)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn disconnect_listeners_test() {
+        let server = TestServer::default();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        {
+            let data = server.app_data().clone();
+            let seen = seen.clone();
+            let mut namespace_manager = server.app_data().namespace_manager.write().unwrap();
+            namespace_manager.create_namespace("/chat").unwrap();
+            // the listeners may use the namespace manager
+            let namespace = namespace_manager.get_adaptor_mut("/").unwrap();
+            namespace.on_disconnecting(move |socket, reason| {
+                let mut namespace_manager = data.namespace_manager.write().unwrap();
+                let chat = namespace_manager.get_adaptor_mut("/chat").unwrap();
+                chat.emit_to_all("left", socket.id());
+                let mut rooms = socket.rooms().to_vec();
+                rooms.sort();
+                seen.lock().unwrap().push((rooms, reason));
+            });
+        }
+        let mut client = server.connect().await;
+        let sid = client.connect("/", None).await.unwrap();
+        client.connect("/chat", None).await.unwrap();
+        server
+            .app_data()
+            .namespace_manager
+            .write()
+            .unwrap()
+            .get_adaptor_mut("/")
+            .unwrap()
+            .join_room(&sid, "room1");
+
+        client.disconnect("/");
+        let packet = client.recv_socket_io().await.unwrap();
+        assert_eq!(packet.nsp, "/chat");
+        assert_eq!(packet.data, Some(json!(["left", sid])));
+        let mut rooms = vec!["room1".to_string(), sid.clone()];
+        rooms.sort();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![(rooms, DisconnectReason::ClientNamespaceDisconnect)]
+        );
+        assert_eq!(
+            server.disconnect_reason("/", &sid),
+            Some(DisconnectReason::ClientNamespaceDisconnect)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn heartbeat_test() {
+        let options = ServerOptions::builder()
+            .ping_interval(Duration::from_secs(25))
+            .ping_timeout(Duration::from_secs(20))
+            .build();
+        let server = TestServer::with_options(options);
+        let mut client = server.connect().await;
+        let sid = client.connect("/", None).await.unwrap();
+
+        // answered pings keep the connection open
+        for _ in 0..3 {
+            server.advance(Duration::from_secs(25)).await;
+            let ping = client.try_recv().unwrap();
+            assert_eq!(ping.packet_type, PacketType::Ping);
+            client.send(Packet::new(PacketType::Pong, Vec::new()));
+        }
+        server.advance(Duration::from_secs(35)).await;
+        let ping = client.try_recv().unwrap();
+        assert_eq!(ping.packet_type, PacketType::Ping);
+        assert!(server.disconnect_reason("/", &sid).is_none());
+
+        // the last ping is not answered, the connection is closed once the
+        // ping timeout elapses
+        server.advance(Duration::from_secs(9)).await;
+        assert!(server.disconnect_reason("/", &sid).is_none());
+        server.advance(Duration::from_secs(1)).await;
+        assert!(client.closed().await.is_empty());
+        assert_eq!(
+            server.disconnect_reason("/", &sid),
+            Some(DisconnectReason::PingTimeout)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn heartbeat_test_eio3() {
+        let options = ServerOptions::builder()
+            .ping_interval(Duration::from_secs(25))
+            .ping_timeout(Duration::from_secs(20))
+            .build();
+        let server = TestServer::with_options(options);
+        let mut client = server.connect_with_protocol(ProtocolVersion::V3).await;
+        // the client joins the root namespace without asking
+        assert_eq!(
+            client.recv_socket_io().await.unwrap().packet_type,
+            SocketIoPacketType::Connect
+        );
+        let sid = server.sockets("/", None).pop().unwrap();
+
+        // the client pings the server itself
+        for _ in 0..3 {
+            server.advance(Duration::from_secs(30)).await;
+            client.send(Packet::new(PacketType::Ping, b"probe".to_vec()));
+            let pong = client.recv().await.unwrap();
+            assert_eq!(pong, Packet::new(PacketType::Pong, b"probe".to_vec()));
+        }
+
+        // and is disconnected when no ping comes within the ping interval and
+        // timeout
+        server.advance(Duration::from_secs(44)).await;
+        assert!(server.disconnect_reason("/", &sid).is_none());
+        server.advance(Duration::from_secs(1)).await;
+        assert!(client.closed().await.is_empty());
+        assert_eq!(
+            server.disconnect_reason("/", &sid),
+            Some(DisconnectReason::PingTimeout)
+        );
+    }
+
+    #[actix_rt::test]
+    async fn shutdown_test() {
+        let server = TestServer::default();
+        server
+            .app_data()
+            .namespace_manager
+            .write()
+            .unwrap()
+            .create_namespace("/chat")
+            .unwrap();
+        let mut clients = server.connect_clients(2).await;
+        let first = clients[0].connect("/", None).await.unwrap();
+        clients[0].connect("/chat", None).await.unwrap();
+        let second = clients[1].connect("/", None).await.unwrap();
+        assert!(server.app_data().is_ready());
+
+        // every socket is told, then every connection closes
+        assert!(server.app_data().shutdown(Duration::from_secs(5)).await);
+        assert!(!server.app_data().is_ready());
+        let mut packets: Vec<Vec<u8>> = clients[0]
+            .closed()
+            .await
+            .into_iter()
+            .map(|packet| packet.data)
+            .collect();
+        packets.sort();
+        assert_eq!(packets, vec![b"1".to_vec(), b"1/chat,".to_vec()]);
+        let packets = clients[1].closed().await;
+        assert_eq!(packets.len(), 1);
+        assert_eq!(packets[0].data, b"1");
+        for sid in [first, second].iter() {
+            assert_eq!(
+                server.disconnect_reason("/", sid),
+                Some(DisconnectReason::ServerShuttingDown)
+            );
+        }
+        assert!(server.sockets("/", None).is_empty());
+        assert!(server.app_data().connections.read().unwrap().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn shutdown_acks_test() {
+        let server = TestServer::default();
+        let mut clients = server.connect_clients(2).await;
+        let mut sids = Vec::new();
+        for client in clients.iter_mut() {
+            sids.push(client.connect("/", None).await.unwrap());
+        }
+        let (acked_sender, acked) = oneshot::channel();
+        let (dropped_sender, dropped) = oneshot::channel();
+        {
+            let namespace_manager = server.app_data().namespace_manager.read().unwrap();
+            let namespace = namespace_manager.get_adaptor("/").unwrap();
+            let flags = BroadcastFlags::default();
+            namespace.emit_with_ack(&sids[0], &flags, "question", "ready?", move |payload| {
+                let _ = acked_sender.send(payload);
+            });
+            namespace.emit_with_ack(&sids[1], &flags, "question", "ready?", move |payload| {
+                let _ = dropped_sender.send(payload);
+            });
+        }
+        let mut questions = Vec::new();
+        for client in clients.iter_mut() {
+            questions.push(client.recv_socket_io().await.unwrap());
+        }
+
+        // the sockets stay until their events are acknowledged, or until the
+        // deadline
+        let app_data = server.app_data().clone();
+        let (all_closed, _) =
+            futures::join!(app_data.shutdown(Duration::from_millis(200)), async {
+                delay_for(Duration::from_millis(50)).await;
+                assert_eq!(server.sockets("/", None).len(), 2);
+                clients[0].send_socket_io(&SocketIoPacket {
+                    packet_type: SocketIoPacketType::Ack,
+                    nsp: "/".to_string(),
+                    id: questions[0].id,
+                    data: Some(json!(["yes"])),
+                });
+            });
+        assert!(!all_closed);
+        assert_eq!(acked.await.unwrap(), Some(Payload::from("yes")));
+        assert_eq!(dropped.await.unwrap(), None);
+        for client in clients.iter_mut() {
+            let packets = client.closed().await;
+            assert_eq!(packets.last().unwrap().data, b"1");
+        }
+        for sid in sids.iter() {
+            assert_eq!(
+                server.disconnect_reason("/", sid),
+                Some(DisconnectReason::ServerShuttingDown)
+            );
+        }
+    }
+
+    /// Collects the logs of the tests
+    #[derive(Clone, Default)]
+    struct LogWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for LogWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[actix_rt::test]
+    async fn tracing_test() {
+        let logs = LogWriter::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::new("debug,socket_io_rust::packets=off"))
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let server = TestServer::default();
+        server.relay_events();
+        let mut client = server.connect().await;
+        let socket_id = client.connect("/", None).await.unwrap();
+        client.emit_with_ack("/", "chat message", vec![json!("hello")]);
+        client.recv_socket_io().await.unwrap();
+        client.close();
+        server.settle().await;
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let connection = format!("connection{{sid={} transport=\"mock\"}}", client.sid());
+        let socket = format!("{}:socket{{nsp=/ id={}}}", connection, socket_id);
+        for expected in [
+            format!("{}: socket_io_rust::socket_io_connection::socket_io_connection_impl: connection opened", connection),
+            format!("{}: socket_io_rust::socket_io_connection::socket_io_connection_impl: socket connected recovered=false", socket),
+            format!("{}:event{{direction=\"in\" name=\"chat message\" ack_id=Some(0) size=26}}: socket_io_rust::socket_io_connection::socket_io_connection_impl: event received", socket),
+            format!("{}:event{{direction=\"out\" name=\"chat message\" ack_id=None size=25}}: socket_io_rust::socket_io_connection::socket_io_connection_impl: event emitted", socket),
+            format!("{}: socket_io_rust::socket_io_connection::socket_io_connection_impl: connection closed reason=transport close", connection),
+        ]
+        .iter()
+        {
+            assert!(logs.contains(expected.as_str()), "{} is missing from\n{}", expected, logs);
+        }
+        assert!(!logs.contains(RAW_PACKETS_TARGET));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::engine_io_parser::packet::{Packet, PacketType};
+use crate::engine_io_server::transport::TransportEvent;
+use crate::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use crate::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use crate::socket_io_testing::mock_transport::MockPeer;
+use crate::socket_io_websocket::app_state::AppState;
+use actix_web::web::Data;
+use futures::StreamExt;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+/// A client connected to a [`TestServer`] through an in-memory transport.
+///
+/// It does nothing on its own: the tests send its packets and look at what
+/// the server sent, which is kept until received.
+///
+/// [`TestServer`]: ../test_server/struct.TestServer.html
+pub struct VirtualClient {
+    app_data: Data<AppState>,
+    peer: MockPeer,
+    /// Engine.IO session id
+    sid: String,
+    /// Socket id of each namespace the client joined
+    sockets: HashMap<String, String>,
+    /// Packets received while waiting for another one
+    pending: VecDeque<SocketIoPacket>,
+    next_ack_id: u32,
+}
+
+impl VirtualClient {
+    /// Waits for the open packet of the session
+    pub(crate) async fn open(app_data: Data<AppState>, peer: MockPeer) -> Self {
+        let mut client = VirtualClient {
+            app_data,
+            peer,
+            sid: String::new(),
+            sockets: HashMap::new(),
+            pending: VecDeque::new(),
+            next_ack_id: 0,
+        };
+        let open = client.recv().await.expect("the session was closed");
+        assert_eq!(
+            open.packet_type,
+            PacketType::Open,
+            "expected the open packet"
+        );
+        let handshake: Value = serde_json::from_slice(&open.data).expect("invalid open packet");
+        client.sid = handshake["sid"].as_str().unwrap_or_default().to_string();
+        client
+    }
+
+    /// Get the Engine.IO session id
+    pub fn sid(&self) -> &str {
+        &self.sid
+    }
+
+    /// Get the socket id of the client in a namespace it joined
+    pub fn socket_id(&self, nsp: &str) -> Option<&str> {
+        self.sockets.get(nsp).map(String::as_str)
+    }
+
+    /// Waits for the next Engine.IO packet sent by the server. Resolves to
+    /// `None` once the server closed the transport.
+    pub async fn recv(&mut self) -> Option<Packet> {
+        self.peer.packets.next().await
+    }
+
+    /// Get the next Engine.IO packet sent by the server, if it was already
+    /// sent
+    pub fn try_recv(&mut self) -> Option<Packet> {
+        self.peer.packets.try_recv().ok()
+    }
+
+    /// Waits for the next Socket.IO packet sent by the server. Pings are
+    /// answered on the way, and binary attachments skipped.
+    pub async fn recv_socket_io(&mut self) -> Option<SocketIoPacket> {
+        if let Some(packet) = self.pending.pop_front() {
+            return Some(packet);
+        }
+        loop {
+            let packet = self.recv().await?;
+            match packet.packet_type {
+                PacketType::Ping => self.send(Packet::new(PacketType::Pong, packet.data)),
+                PacketType::Message if !packet.binary => {
+                    let data = std::str::from_utf8(&packet.data).expect("invalid message");
+                    let packet = self
+                        .app_data
+                        .options
+                        .codec()
+                        .decode(data)
+                        .expect("invalid Socket.IO packet");
+                    return Some(packet);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Waits for the server to close the transport. Returns the packets sent
+    /// until then.
+    pub async fn closed(&mut self) -> Vec<Packet> {
+        let mut packets = Vec::new();
+        while let Some(packet) = self.recv().await {
+            packets.push(packet);
+        }
+        packets
+    }
+
+    /// Sends an Engine.IO packet to the server
+    pub fn send(&self, packet: Packet) {
+        let _ = self
+            .peer
+            .events
+            .unbounded_send(TransportEvent::Packet(packet));
+    }
+
+    /// Sends a Socket.IO packet to the server
+    pub fn send_socket_io(&self, packet: &SocketIoPacket) {
+        let text = self
+            .app_data
+            .options
+            .codec()
+            .encode_to_string(packet)
+            .expect("packet cannot be encoded");
+        self.send(Packet::new(PacketType::Message, text.into_bytes()));
+    }
+
+    /// Joins a namespace and waits for the answer of the server. Resolves to
+    /// the socket id, or to the data of the error packet when the server
+    /// refuses. The other packets received meanwhile are kept.
+    pub async fn connect(&mut self, nsp: &str, auth: Option<Value>) -> Result<String, Value> {
+        self.send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::Connect,
+            nsp: nsp.to_string(),
+            id: None,
+            data: auth,
+        });
+        let mut received = Vec::new();
+        let result = loop {
+            let packet = self.recv_socket_io().await.expect("the session was closed");
+            match packet.packet_type {
+                SocketIoPacketType::Connect if packet.nsp == nsp => {
+                    let data = packet.data.unwrap_or(Value::Null);
+                    let sid = data["sid"].as_str().unwrap_or_default().to_string();
+                    self.sockets.insert(nsp.to_string(), sid.clone());
+                    break Ok(sid);
+                }
+                SocketIoPacketType::Error if packet.nsp == nsp => {
+                    break Err(packet.data.unwrap_or(Value::Null))
+                }
+                _ => received.push(packet),
+            }
+        };
+        self.pending.extend(received);
+        result
+    }
+
+    /// Emits an event with its arguments
+    pub fn emit(&self, nsp: &str, event: &str, args: Vec<Value>) {
+        self.send_event(nsp, event, args, None);
+    }
+
+    /// Emits an event asking for an acknowledgement. Returns the id the
+    /// acknowledgement packet carries.
+    pub fn emit_with_ack(&mut self, nsp: &str, event: &str, args: Vec<Value>) -> u32 {
+        let id = self.next_ack_id;
+        self.next_ack_id += 1;
+        self.send_event(nsp, event, args, Some(id));
+        id
+    }
+
+    fn send_event(&self, nsp: &str, event: &str, args: Vec<Value>, id: Option<u32>) {
+        let mut data = vec![Value::String(event.to_string())];
+        data.extend(args);
+        self.send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::Event,
+            nsp: nsp.to_string(),
+            id,
+            data: Some(Value::Array(data)),
+        });
+    }
+
+    /// Leaves a namespace
+    pub fn disconnect(&mut self, nsp: &str) {
+        self.sockets.remove(nsp);
+        self.send_socket_io(&SocketIoPacket {
+            packet_type: SocketIoPacketType::Disconnect,
+            nsp: nsp.to_string(),
+            id: None,
+            data: None,
+        });
+    }
+
+    /// Closes the transport, as a client going away would
+    pub fn close(&self) {
+        let _ = self.peer.events.unbounded_send(TransportEvent::Close);
+    }
+}
+
+impl fmt::Debug for VirtualClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualClient")
+            .field("sid", &self.sid)
+            .field("sockets", &self.sockets)
+            .finish()
+    }
+}
@@ -0,0 +1,87 @@
+use crate::socket_io_connection::clock::Heartbeat;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use actix::Addr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A heartbeat timer of a connection, going off at a virtual time
+#[derive(Debug)]
+struct VirtualTimer {
+    at: Duration,
+    connection: Addr<SocketIoConnection>,
+    heartbeat: Heartbeat,
+}
+
+#[derive(Debug)]
+struct VirtualClockState {
+    start: Instant,
+    elapsed: Duration,
+    timers: Vec<VirtualTimer>,
+}
+
+/// A clock moved by hand. Connections arm their heartbeat timers with it and
+/// are sent them as the clock passes their time.
+#[derive(Debug, Clone)]
+pub(crate) struct VirtualClock(Arc<Mutex<VirtualClockState>>);
+
+impl Default for VirtualClock {
+    fn default() -> Self {
+        VirtualClock(Arc::new(Mutex::new(VirtualClockState {
+            start: Instant::now(),
+            elapsed: Duration::from_secs(0),
+            timers: Vec::new(),
+        })))
+    }
+}
+
+impl VirtualClock {
+    pub(crate) fn now(&self) -> Instant {
+        let state = self.0.lock().unwrap();
+        state.start + state.elapsed
+    }
+
+    /// Sends the heartbeat timer to the connection once `delay` elapsed
+    pub(crate) fn add_timer(
+        &self,
+        delay: Duration,
+        connection: Addr<SocketIoConnection>,
+        heartbeat: Heartbeat,
+    ) {
+        let mut state = self.0.lock().unwrap();
+        let at = state.elapsed + delay;
+        state.timers.push(VirtualTimer {
+            at,
+            connection,
+            heartbeat,
+        });
+    }
+
+    /// Moves the clock to the first timer due by `until`, and sends it.
+    /// Timers due at the same time are sent in the order they were armed.
+    /// Returns false, with the clock moved to `until`, once no timer is due.
+    pub(crate) fn step(&self, until: Duration) -> bool {
+        let mut state = self.0.lock().unwrap();
+        state.timers.retain(|timer| timer.connection.connected());
+        let next = state.timers.iter().map(|timer| timer.at).min();
+        let next = match next {
+            Some(next) if next <= until => next,
+            _ => {
+                state.elapsed = state.elapsed.max(until);
+                return false;
+            }
+        };
+        state.elapsed = next;
+        state.timers.retain(|timer| {
+            if timer.at == next {
+                timer.connection.do_send(timer.heartbeat);
+            }
+            timer.at != next
+        });
+        true
+    }
+
+    /// Get how long the clock was moved
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.0.lock().unwrap().elapsed
+    }
+}
@@ -1,26 +1,116 @@
-use crate::socket_io_adaptor::namespace_manager::NamespaceManager;
-use std::sync::RwLock;
+use crate::socket_io::server_options::ServerOptions;
+use crate::socket_io_adaptor::disconnect_reason::DisconnectReason;
+use crate::socket_io_adaptor::namespace_manager::{NamespaceManager, NamespaceManagerLock};
+use crate::socket_io_admin::admin_ui::AdminUi;
+use crate::socket_io_connection::clock::Clock;
+use crate::socket_io_connection::socket_io_connection_impl::SocketIoConnection;
+use crate::socket_io_connection::socket_message::ShutdownMessage;
+use actix::Addr;
+use actix_rt::time::delay_for;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// How often a shutdown checks whether the connections are closed
+const DRAIN_CHECK_INTERVAL: Duration = Duration::from_millis(20);
 
-// pub struct AppState {
-//     pub(crate) all_socket: RwLock<HashSet<Addr<SocketIoWebsocket>>>,
-// }
 #[derive(Debug)]
 pub struct AppState {
-    pub namespace_manager: RwLock<NamespaceManager>,
+    pub namespace_manager: NamespaceManagerLock,
+    pub options: ServerOptions,
+    /// Open connections, by Engine.IO session id
+    pub(crate) connections: RwLock<HashMap<String, Addr<SocketIoConnection>>>,
+    /// Where the connections get the time from, for their heartbeat
+    pub(crate) clock: Clock,
+    /// Set when the admin UI is enabled
+    pub(crate) admin_ui: Option<Arc<AdminUi>>,
+    /// Set once the server started shutting down
+    shutting_down: AtomicBool,
 }
 
 impl Default for AppState {
     fn default() -> Self {
-        Self {
-            namespace_manager: RwLock::new(NamespaceManager::new()),
-        }
+        Self::new(NamespaceManager::new())
     }
 }
 
 impl AppState {
     pub fn new(nsm: NamespaceManager) -> Self {
+        Self::with_options(nsm, ServerOptions::default())
+    }
+
+    pub fn with_options(mut nsm: NamespaceManager, options: ServerOptions) -> Self {
+        if let Some(recovery) = options.connection_state_recovery.as_ref() {
+            nsm.enable_connection_state_recovery(recovery.clone());
+        }
+        if let Some(metrics) = options.metrics.as_ref() {
+            nsm.enable_metrics(metrics.clone());
+        }
+        let admin_ui = options.admin_ui.as_ref().map(|admin_options| {
+            let admin_ui = Arc::new(AdminUi::new(admin_options.clone()));
+            if nsm.get_adaptor(admin_ui.namespace()).is_none() {
+                nsm.create_namespace(admin_ui.namespace())
+                    .expect("invalid admin UI namespace");
+            }
+            nsm.enable_admin_ui(admin_ui.clone());
+            admin_ui
+        });
         Self {
-            namespace_manager: RwLock::new(nsm),
+            namespace_manager: NamespaceManagerLock::new(nsm),
+            options,
+            connections: RwLock::new(HashMap::new()),
+            clock: Clock::default(),
+            admin_ui,
+            shutting_down: AtomicBool::new(false),
         }
     }
+
+    /// Whether the server accepts new connections, which it stops doing once
+    /// it starts shutting down
+    pub fn is_ready(&self) -> bool {
+        !self.shutting_down.load(Ordering::SeqCst)
+    }
+
+    /// Shuts the server down gracefully: handshakes are refused, and once
+    /// the events emitted with an acknowledgement are acknowledged, every
+    /// socket is sent a DISCONNECT packet and leaves its namespace with the
+    /// `server shutting down` reason. Each connection then closes once the
+    /// packets it buffered are sent.
+    ///
+    /// Waits for the connections to close, up to the deadline. Returns
+    /// whether they all did. At the deadline, the acknowledgements still
+    /// awaited are given up on and the remaining sockets disconnected.
+    pub async fn shutdown(&self, deadline: Duration) -> bool {
+        let deadline = Instant::now() + deadline;
+        self.shutting_down.store(true, Ordering::SeqCst);
+        let connections: Vec<Addr<SocketIoConnection>> =
+            self.connections.read().unwrap().values().cloned().collect();
+        info!(connections = connections.len(), "shutting down");
+        for connection in connections {
+            connection.do_send(ShutdownMessage {
+                reason: DisconnectReason::ServerShuttingDown,
+                force: false,
+            });
+        }
+        while !self.connections.read().unwrap().is_empty() {
+            if Instant::now() >= deadline {
+                for connection in self.connections.read().unwrap().values() {
+                    connection.do_send(ShutdownMessage {
+                        reason: DisconnectReason::ServerShuttingDown,
+                        force: true,
+                    });
+                }
+                return false;
+            }
+            delay_for(DRAIN_CHECK_INTERVAL).await;
+        }
+        true
+    }
+
+    /// Get an open connection by its Engine.IO session id
+    pub(crate) fn get_connection(&self, sid: &str) -> Option<Addr<SocketIoConnection>> {
+        self.connections.read().unwrap().get(sid).cloned()
+    }
 }
@@ -1,3 +1,4 @@
 pub mod app_state;
+mod per_message_deflate;
 pub mod socket_io_websocket_impl;
-pub mod socket_message;
+pub mod websocket_transport;
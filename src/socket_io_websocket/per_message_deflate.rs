@@ -0,0 +1,128 @@
+//! The permessage-deflate extension of the websocket protocol
+//! https://tools.ietf.org/html/rfc7692
+
+use actix_web::http::header;
+use actix_web::HttpRequest;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Decompress, FlushDecompress};
+use std::io::{self, Write};
+
+const EXTENSION: &str = "permessage-deflate";
+
+/// The accepted extension, sent in the handshake response. Neither side keeps
+/// its compression context between messages, so that an idle websocket does
+/// not hold onto it.
+pub(crate) const ACCEPTED_EXTENSION: &str =
+    "permessage-deflate; server_no_context_takeover; client_no_context_takeover";
+
+/// Set on the first frame of a compressed message
+pub(crate) const RSV1: u8 = 0x40;
+
+/// Ends every compressed message, removed by the sender
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Whether the client offers an extension the server can accept. Offers
+/// limiting the window of the server are declined.
+pub(crate) fn is_offered(req: &HttpRequest) -> bool {
+    req.headers()
+        .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|offer| {
+            let mut params = offer.split(';').map(str::trim);
+            params.next() == Some(EXTENSION)
+                && params.all(|param| !param.starts_with("server_max_window_bits"))
+        })
+}
+
+/// Compresses a message
+pub(crate) fn deflate(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::fast());
+    encoder.write_all(data)?;
+    // flushing ends the data with an empty block, ended by the trailer
+    encoder.flush()?;
+    let mut compressed = std::mem::take(encoder.get_mut());
+    if compressed.ends_with(&TRAILER) {
+        compressed.truncate(compressed.len() - TRAILER.len());
+    }
+    Ok(compressed)
+}
+
+/// Decompresses a message, failing when it is larger than `max_size`
+pub(crate) fn inflate(data: &[u8], max_size: usize) -> io::Result<Vec<u8>> {
+    let input = [data, &TRAILER[..]].concat();
+    let mut decompress = Decompress::new(false);
+    let mut decompressed = Vec::with_capacity(input.len() * 2);
+    loop {
+        let (total_in, total_out) = (decompress.total_in(), decompress.total_out());
+        decompress
+            .decompress_vec(
+                &input[total_in as usize..],
+                &mut decompressed,
+                FlushDecompress::Sync,
+            )
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if decompressed.len() > max_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message is too large",
+            ));
+        }
+
+        let has_room = decompressed.len() < decompressed.capacity();
+        if decompress.total_in() as usize == input.len() && has_room {
+            return Ok(decompressed);
+        }
+        if has_room && (total_in, total_out) == (decompress.total_in(), decompress.total_out()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "message is truncated",
+            ));
+        }
+        decompressed.reserve(decompressed.capacity());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::socket_io_websocket::per_message_deflate::{deflate, inflate, is_offered};
+    use actix_web::http::header;
+    use actix_web::test::TestRequest;
+
+    #[test]
+    fn deflate_test() {
+        let message = "42[\"message\",\"hello hello hello hello\"]".repeat(10);
+        let compressed = deflate(message.as_bytes()).unwrap();
+        assert!(compressed.len() < message.len());
+        assert_eq!(inflate(&compressed, 1000).unwrap(), message.as_bytes());
+        assert!(inflate(&compressed, 100).is_err());
+    }
+
+    #[test]
+    fn inflate_test() {
+        // "Hello" compressed by a client, from the examples of RFC 7692
+        let compressed = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        assert_eq!(inflate(&compressed, 100).unwrap(), b"Hello");
+    }
+
+    #[test]
+    fn is_offered_test() {
+        let req = TestRequest::default()
+            .header(
+                header::SEC_WEBSOCKET_EXTENSIONS,
+                "permessage-deflate; client_max_window_bits",
+            )
+            .to_http_request();
+        assert!(is_offered(&req));
+
+        let req = TestRequest::default()
+            .header(
+                header::SEC_WEBSOCKET_EXTENSIONS,
+                "permessage-deflate; server_max_window_bits=10",
+            )
+            .to_http_request();
+        assert!(!is_offered(&req));
+
+        assert!(!is_offered(&TestRequest::default().to_http_request()));
+    }
+}
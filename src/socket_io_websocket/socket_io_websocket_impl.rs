@@ -1,129 +1,258 @@
-use crate::random_id_generator::RandomIdGenerator;
-use crate::socket_io_websocket::app_state::AppState;
-use crate::socket_io_websocket::socket_message::SocketMessage;
-use actix::{Actor, ActorContext, AsyncContext, Handler, StreamHandler};
-use actix_web::web;
+use crate::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use crate::engine_io_parser::packet::Packet;
+use crate::engine_io_parser::{EngineIoParser, ProtocolVersion};
+use crate::engine_io_server::transport::{TransportError, TransportEvent, TransportEvents};
+use crate::socket_io::server_options::ServerOptions;
+use crate::socket_io_connection::transport_message::CloseWebsocket;
+use crate::socket_io_metrics::metrics::{Direction, Metrics};
+use crate::socket_io_websocket::per_message_deflate;
+use crate::socket_io_websocket::websocket_transport::{WebsocketTransport, WebsocketWriter};
+use actix::{Actor, ActorContext, AsyncContext, Context, Handler, StreamHandler};
+use actix_http::ws::{OpCode, Parser};
+use actix_web::error::PayloadError;
+use actix_web::http::header;
+use actix_web::web::{Bytes, BytesMut};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws;
-use std::io;
-use std::time::{Duration, Instant};
+use futures::channel::mpsc;
+use std::sync::Arc;
 
-/// How often heartbeat pings are sent
-const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
-/// How long before lack of client response causes a timeout
-const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+/// A message received in one or more frames
+#[derive(Debug)]
+struct IncomingMessage {
+    opcode: OpCode,
+    compressed: bool,
+    data: BytesMut,
+}
 
-/// websocket connection is long running connection, it easier
-/// to handle with an actor
+/// Reads the frames of a websocket, reporting the packets it receives to the
+/// session through the events of its [`WebsocketTransport`].
+///
+/// The frames are read and written here rather than by the actix codec, which
+/// does not support the per-message deflate extension.
+///
+/// [`WebsocketTransport`]: ../websocket_transport/struct.WebsocketTransport.html
 #[derive(Debug)]
 pub struct SocketIoWebsocket {
-    /// Client must send ping at least once per 10 seconds (CLIENT_TIMEOUT),
-    /// otherwise we drop connection.
-    hb: Instant,
-    global_state: web::Data<AppState>,
-    id: String,
-    namespace: String,
+    events: mpsc::UnboundedSender<TransportEvent>,
+    /// Set once the websocket is closed, the session being told why
+    closed: bool,
+    /// Answers the control frames of the client
+    writer: WebsocketWriter,
+    /// Received bytes that do not make a whole frame yet
+    read_buffer: BytesMut,
+    /// The message whose frames are being received
+    incoming: Option<IncomingMessage>,
+    max_size: usize,
+    /// Engine.IO protocol revision of the client, which tells how binary
+    /// messages are framed
+    protocol: ProtocolVersion,
+    metrics: Option<Arc<Metrics>>,
 }
 
 impl Actor for SocketIoWebsocket {
-    type Context = ws::WebsocketContext<Self>;
-
-    fn started(&mut self, ctx: &mut Self::Context) {
-        self.start_web_socket(ctx);
-    }
+    type Context = Context<Self>;
 
     fn stopped(&mut self, _: &mut Self::Context) {
-        self.stop_web_socket();
+        if !self.closed {
+            let _ = self.events.unbounded_send(TransportEvent::Close);
+        }
     }
 }
 
-impl Handler<SocketMessage> for SocketIoWebsocket {
-    type Result = Result<(), io::Error>;
+impl Handler<CloseWebsocket> for SocketIoWebsocket {
+    type Result = ();
 
-    fn handle(&mut self, msg: SocketMessage, ctx: &mut Self::Context) -> Self::Result {
-        let content = format!("{},{}", msg.event_name, msg.content);
-        ctx.text(content);
-        Ok(())
+    fn handle(&mut self, _: CloseWebsocket, ctx: &mut Self::Context) -> Self::Result {
+        self.close(ctx, TransportEvent::Close);
     }
 }
 
-/// Handler for `ws::Message`
-impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for SocketIoWebsocket {
-    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
-        // process websocket messages
-        match msg {
-            Ok(ws::Message::Ping(msg)) => {
-                self.hb = Instant::now();
-                ctx.pong(&msg);
-            }
-            Ok(ws::Message::Pong(_)) => {
-                self.hb = Instant::now();
-            }
-            Ok(ws::Message::Text(text)) => {
-                let namespace_manager = self.global_state.namespace_manager.read().unwrap();
-                let adaptor = namespace_manager.get_adaptor("/").unwrap();
-                adaptor.emit_to_all("hello", &text);
-            }
-            Ok(ws::Message::Binary(_)) => {
-                // todo unsupported binary
-                ctx.stop();
-            },
-            Ok(ws::Message::Close(_)) => {
-                ctx.stop();
+/// Handler for the bytes received from the client
+impl StreamHandler<Result<Bytes, PayloadError>> for SocketIoWebsocket {
+    fn handle(&mut self, chunk: Result<Bytes, PayloadError>, ctx: &mut Self::Context) {
+        match chunk {
+            Ok(chunk) => {
+                self.read_buffer.extend_from_slice(&chunk);
+                self.read_frames(ctx);
             }
-            _ => ctx.stop(),
+            Err(e) => self.close_with_error(ctx, TransportError::InvalidData(e.to_string())),
         }
     }
+
+    fn finished(&mut self, ctx: &mut Self::Context) {
+        self.close(ctx, TransportEvent::Close);
+    }
 }
 
-#[allow(dead_code)]
 impl SocketIoWebsocket {
-    pub fn new(data: web::Data<AppState>, namespace: &str) -> Self {
-        Self {
-            hb: Instant::now(),
-            global_state: data,
-            id: RandomIdGenerator::get_random_uuid(),
-            namespace: namespace.to_string(),
+    /// Starts a websocket transport, answering its handshake. Frames larger
+    /// than the maximum size of a message close it. The per-message deflate
+    /// extension is accepted when enabled and offered by the client.
+    pub fn start(
+        options: &ServerOptions,
+        protocol: ProtocolVersion,
+        r: &HttpRequest,
+        stream: web::Payload,
+    ) -> Result<(HttpResponse, WebsocketTransport, TransportEvents), Error> {
+        let mut res = ws::handshake(r)?;
+        let deflate = options
+            .per_message_deflate
+            .clone()
+            .filter(|_| per_message_deflate::is_offered(r));
+        if deflate.is_some() {
+            res.header(
+                header::SEC_WEBSOCKET_EXTENSIONS,
+                per_message_deflate::ACCEPTED_EXTENSION,
+            );
         }
-    }
 
-    /// helper method that sends ping to client every second.
-    ///
-    /// also this method checks heartbeats from client
-    fn hb(&self, ctx: &mut <Self as Actor>::Context) {
-        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
-            // check client heartbeats
-            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
-                // stop actor
-                ctx.stop();
-
-                // don't try to send a ping
-                return;
+        let (events, receiver) = mpsc::unbounded();
+        let (writer, frames) = WebsocketWriter::new(deflate, events.clone());
+        let max_size = options.max_http_buffer_size;
+        let reader_writer = writer.clone();
+        let reader_metrics = options.metrics.clone();
+        let reader = SocketIoWebsocket::create(move |ctx| {
+            ctx.add_stream(stream);
+            SocketIoWebsocket {
+                events,
+                closed: false,
+                writer: reader_writer,
+                read_buffer: BytesMut::new(),
+                incoming: None,
+                max_size,
+                protocol,
+                metrics: reader_metrics,
             }
-
-            ctx.ping(b"");
         });
+        let transport = WebsocketTransport::new(writer, reader, protocol, options.metrics.clone());
+        Ok((res.streaming(frames), transport, receiver))
+    }
+
+    /// Closes the websocket, letting the session know why
+    fn close(&mut self, ctx: &mut <Self as Actor>::Context, event: TransportEvent) {
+        if !self.closed {
+            self.closed = true;
+            let _ = self.events.unbounded_send(event);
+        }
+        ctx.stop();
+    }
+
+    fn close_with_error(&mut self, ctx: &mut <Self as Actor>::Context, err: TransportError) {
+        self.close(ctx, TransportEvent::Error(err));
     }
 
-    fn start_web_socket(&mut self, ctx: &mut <Self as Actor>::Context) {
-        self.add_socket_to_adaptor(ctx);
-        self.hb(ctx);
+    fn read_frames(&mut self, ctx: &mut <Self as Actor>::Context) {
+        while !self.closed {
+            let compressed = self
+                .read_buffer
+                .first()
+                .is_some_and(|b| b & per_message_deflate::RSV1 != 0);
+            match Parser::parse(&mut self.read_buffer, true, self.max_size) {
+                Ok(Some((finished, opcode, data))) => {
+                    let data = data.unwrap_or_else(BytesMut::new);
+                    self.on_frame(ctx, finished, opcode, compressed, data);
+                }
+                Ok(None) => return,
+                Err(e) => self.close_with_error(ctx, TransportError::InvalidData(e.to_string())),
+            }
+        }
     }
 
+    fn on_frame(
+        &mut self,
+        ctx: &mut <Self as Actor>::Context,
+        finished: bool,
+        opcode: OpCode,
+        compressed: bool,
+        data: BytesMut,
+    ) {
+        match opcode {
+            OpCode::Ping => {
+                if let Err(err) = self.writer.write_frame(&data, OpCode::Pong, false) {
+                    return self.close_with_error(ctx, err);
+                }
+            }
+            OpCode::Pong => {}
+            OpCode::Close => {
+                let _ = self.writer.write_close();
+                self.close(ctx, TransportEvent::Close);
+            }
+            OpCode::Text | OpCode::Binary if self.incoming.is_none() => {
+                self.incoming = Some(IncomingMessage {
+                    opcode,
+                    compressed,
+                    data,
+                });
+            }
+            OpCode::Continue if self.incoming.is_some() => {
+                let incoming = self.incoming.as_mut().unwrap();
+                if incoming.data.len() + data.len() > self.max_size {
+                    let err = TransportError::InvalidData("message is too large".to_string());
+                    return self.close_with_error(ctx, err);
+                }
+                incoming.data.extend_from_slice(&data);
+            }
+            _ => {
+                let err = TransportError::InvalidData("unexpected frame".to_string());
+                return self.close_with_error(ctx, err);
+            }
+        }
 
-    fn stop_web_socket(&mut self) {
-        self.remove_socket_from_adaptor();
+        let data_frame = matches!(opcode, OpCode::Text | OpCode::Binary | OpCode::Continue);
+        if finished && data_frame {
+            if let Some(incoming) = self.incoming.take() {
+                self.on_message(ctx, incoming);
+            }
+        }
     }
 
+    fn on_message(&mut self, ctx: &mut <Self as Actor>::Context, incoming: IncomingMessage) {
+        let binary = incoming.opcode == OpCode::Binary;
+        let data = match (incoming.compressed, self.writer.is_deflate_accepted()) {
+            (false, _) => incoming.data.to_vec(),
+            (true, true) => match per_message_deflate::inflate(&incoming.data, self.max_size) {
+                Ok(data) => data,
+                Err(err) => return self.close_with_error(ctx, err.into()),
+            },
+            (true, false) => {
+                let err = TransportError::InvalidData("unexpected compression".to_string());
+                return self.close_with_error(ctx, err);
+            }
+        };
 
-    fn add_socket_to_adaptor(&mut self, ctx: &mut <Self as Actor>::Context) {
-        let mut namespace_manager = self.global_state.namespace_manager.write().unwrap();
-        let adaptor = namespace_manager.get_adaptor_mut(&self.namespace).unwrap();
-        adaptor.add_socket(&self.id, ctx.address());
+        let packet = if binary {
+            self.decode_binary(data)
+        } else {
+            std::str::from_utf8(&data)
+                .map_err(|e| TransportError::InvalidData(e.to_string()))
+                .and_then(|text| {
+                    EngineIOParserImpl::decode_packet_str(text)
+                        .map_err(|e| TransportError::InvalidData(e.to_string()))
+                })
+        };
+        match packet {
+            Ok(packet) => {
+                if let Some(metrics) = self.metrics.as_ref() {
+                    metrics.on_engine_io_packet(Direction::In, &packet);
+                }
+                let _ = self.events.unbounded_send(TransportEvent::Packet(packet));
+            }
+            Err(err) => self.close_with_error(ctx, err),
+        }
     }
 
-    fn remove_socket_from_adaptor(&mut self) {
-        let mut namespace_manager = self.global_state.namespace_manager.write().unwrap();
-        let adaptor = namespace_manager.get_adaptor_mut("/").unwrap();
-        adaptor.remove_socket(&self.id);
+    /// Decodes a binary message. Engine.IO v3 prefixes it with its packet
+    /// type, v4 sends the data alone.
+    fn decode_binary(&self, data: Vec<u8>) -> Result<Packet, TransportError> {
+        match self.protocol {
+            ProtocolVersion::V3 => {
+                let mut packet = EngineIOParserImpl::decode_packet(&data)
+                    .map_err(|e| TransportError::InvalidData(e.to_string()))?;
+                packet.binary = true;
+                Ok(packet)
+            }
+            ProtocolVersion::V4 => Ok(Packet::binary(data)),
+        }
     }
 }
@@ -0,0 +1,266 @@
+use crate::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use crate::engine_io_parser::packet::Packet;
+use crate::engine_io_parser::{EngineIoParser, ProtocolVersion};
+use crate::engine_io_server::transport::{Transport, TransportError, TransportEvent};
+use crate::socket_io::server_options::PerMessageDeflateOptions;
+use crate::socket_io_connection::transport_message::CloseWebsocket;
+use crate::socket_io_metrics::metrics::{Direction, Metrics};
+use crate::socket_io_websocket::per_message_deflate;
+use crate::socket_io_websocket::socket_io_websocket_impl::SocketIoWebsocket;
+use actix::Addr;
+use actix_http::ws::{CloseCode, OpCode, Parser};
+use actix_web::web::{Bytes, BytesMut};
+use futures::channel::mpsc;
+use futures::future::{self, FutureExt, LocalBoxFuture};
+use futures::stream::{Stream, StreamExt};
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// How many frames may wait to be streamed to the client before the
+/// transport stops being writable
+pub(crate) const MAX_QUEUED_FRAMES: usize = 64;
+
+/// Counts the frames waiting to be streamed to the client
+#[derive(Debug, Default)]
+struct FrameQueue {
+    queued: AtomicUsize,
+    /// Set once the queue is full, so that the session is told when it
+    /// empties
+    full: AtomicBool,
+}
+
+impl FrameQueue {
+    fn push(&self) {
+        if self.queued.fetch_add(1, Ordering::SeqCst) + 1 >= MAX_QUEUED_FRAMES {
+            self.full.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Returns true when the queue empties after having been full
+    fn pop(&self) -> bool {
+        self.queued.fetch_sub(1, Ordering::SeqCst) == 1 && self.full.swap(false, Ordering::SeqCst)
+    }
+
+    fn is_full(&self) -> bool {
+        self.queued.load(Ordering::SeqCst) >= MAX_QUEUED_FRAMES
+    }
+}
+
+/// The frames written to a websocket, streamed as the body of its handshake
+/// response. Once the queue of frames empties after having been full, the
+/// session is sent a [`TransportEvent::Drain`].
+///
+/// [`TransportEvent::Drain`]: ../../engine_io_server/transport/enum.TransportEvent.html#variant.Drain
+#[derive(Debug)]
+pub(crate) struct WebsocketFrames {
+    frames: mpsc::UnboundedReceiver<Bytes>,
+    queue: Arc<FrameQueue>,
+    events: mpsc::UnboundedSender<TransportEvent>,
+}
+
+impl Stream for WebsocketFrames {
+    type Item = Result<Bytes, io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let frame = match self.frames.poll_next_unpin(cx) {
+            Poll::Ready(frame) => frame,
+            Poll::Pending => return Poll::Pending,
+        };
+        if frame.is_some() && self.queue.pop() {
+            let _ = self.events.unbounded_send(TransportEvent::Drain);
+        }
+        Poll::Ready(frame.map(Ok))
+    }
+}
+
+/// Writes the frames of a websocket
+#[derive(Debug, Clone)]
+pub(crate) struct WebsocketWriter {
+    frames: mpsc::UnboundedSender<Bytes>,
+    queue: Arc<FrameQueue>,
+    /// Set when the client accepted the per-message deflate extension
+    deflate: Option<PerMessageDeflateOptions>,
+}
+
+impl WebsocketWriter {
+    /// Creates the writer of a websocket and the frames it writes. `events`
+    /// are the ones of the transport, told when the frames drain.
+    pub(crate) fn new(
+        deflate: Option<PerMessageDeflateOptions>,
+        events: mpsc::UnboundedSender<TransportEvent>,
+    ) -> (Self, WebsocketFrames) {
+        let (frames, receiver) = mpsc::unbounded();
+        let queue = Arc::new(FrameQueue::default());
+        let writer = WebsocketWriter {
+            frames,
+            queue: queue.clone(),
+            deflate,
+        };
+        let frames = WebsocketFrames {
+            frames: receiver,
+            queue,
+            events,
+        };
+        (writer, frames)
+    }
+
+    /// Whether the frames written so far were streamed to the client, but
+    /// for at most [`MAX_QUEUED_FRAMES`](constant.MAX_QUEUED_FRAMES.html)
+    pub(crate) fn is_writable(&self) -> bool {
+        !self.queue.is_full()
+    }
+
+    /// Whether the client accepted the per-message deflate extension
+    pub(crate) fn is_deflate_accepted(&self) -> bool {
+        self.deflate.is_some()
+    }
+
+    /// Writes a frame, compressed when the extension was accepted, `compress`
+    /// is true and the data reaches the threshold
+    pub(crate) fn write_frame(
+        &self,
+        data: &[u8],
+        opcode: OpCode,
+        compress: bool,
+    ) -> Result<(), TransportError> {
+        let compressed = match self.deflate.as_ref() {
+            Some(deflate) if compress && data.len() >= deflate.threshold => {
+                per_message_deflate::deflate(data).ok()
+            }
+            _ => None,
+        };
+
+        let mut frame = BytesMut::new();
+        match compressed {
+            Some(compressed) => {
+                Parser::write_message(&mut frame, compressed, opcode, true, false);
+                frame[0] |= per_message_deflate::RSV1;
+            }
+            None => Parser::write_message(&mut frame, data, opcode, true, false),
+        }
+        self.write(frame)
+    }
+
+    pub(crate) fn write_close(&self) -> Result<(), TransportError> {
+        let mut frame = BytesMut::new();
+        Parser::write_close(&mut frame, Some(CloseCode::Normal.into()), false);
+        self.write(frame)
+    }
+
+    fn write(&self, frame: BytesMut) -> Result<(), TransportError> {
+        self.frames
+            .unbounded_send(frame.freeze())
+            .map_err(|_| TransportError::Closed)?;
+        self.queue.push();
+        Ok(())
+    }
+}
+
+/// The websocket transport. Frames are queued as they are written, and the
+/// transport is not writable while the queue is full.
+#[derive(Debug)]
+pub struct WebsocketTransport {
+    writer: WebsocketWriter,
+    /// The actor reading the frames of the client
+    reader: Addr<SocketIoWebsocket>,
+    /// Engine.IO protocol revision of the client, which tells how binary
+    /// messages are framed
+    protocol: ProtocolVersion,
+    metrics: Option<Arc<Metrics>>,
+}
+
+impl WebsocketTransport {
+    pub(crate) fn new(
+        writer: WebsocketWriter,
+        reader: Addr<SocketIoWebsocket>,
+        protocol: ProtocolVersion,
+        metrics: Option<Arc<Metrics>>,
+    ) -> Self {
+        WebsocketTransport {
+            writer,
+            reader,
+            protocol,
+            metrics,
+        }
+    }
+}
+
+impl Transport for WebsocketTransport {
+    fn name(&self) -> &'static str {
+        "websocket"
+    }
+
+    fn writable(&self) -> bool {
+        self.writer.is_writable()
+    }
+
+    fn send(
+        &mut self,
+        packets: Vec<Packet>,
+        compress: bool,
+    ) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let result = packets.into_iter().try_for_each(|packet| {
+            if let Some(metrics) = self.metrics.as_ref() {
+                metrics.on_engine_io_packet(Direction::Out, &packet);
+            }
+            // Engine.IO v3 prefixes binary messages with their packet type
+            match (packet.binary, self.protocol) {
+                (true, ProtocolVersion::V3) => {
+                    let data = EngineIOParserImpl::encode_packet(packet);
+                    return self.writer.write_frame(&data, OpCode::Binary, compress);
+                }
+                (true, ProtocolVersion::V4) => {
+                    return self
+                        .writer
+                        .write_frame(&packet.data, OpCode::Binary, compress);
+                }
+                (false, _) => {}
+            }
+            let data = EngineIOParserImpl::encode_packet_str(packet)
+                .map_err(|e| TransportError::InvalidData(e.to_string()))?;
+            self.writer
+                .write_frame(data.as_bytes(), OpCode::Text, compress)
+        });
+        future::ready(result).boxed_local()
+    }
+
+    fn close(&mut self) -> LocalBoxFuture<'static, Result<(), TransportError>> {
+        let result = self.writer.write_close();
+        self.reader.do_send(CloseWebsocket);
+        future::ready(result).boxed_local()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::engine_io_server::transport::TransportEvent;
+    use crate::socket_io_websocket::websocket_transport::{WebsocketWriter, MAX_QUEUED_FRAMES};
+    use actix_http::ws::OpCode;
+    use futures::channel::mpsc;
+    use futures::StreamExt;
+
+    #[actix_rt::test]
+    async fn websocket_writer_test() {
+        let (events, mut receiver) = mpsc::unbounded();
+        let (writer, mut frames) = WebsocketWriter::new(None, events);
+        for _ in 0..MAX_QUEUED_FRAMES {
+            assert!(writer.is_writable());
+            writer.write_frame(b"4hello", OpCode::Text, false).unwrap();
+        }
+        assert!(!writer.is_writable());
+
+        for _ in 0..MAX_QUEUED_FRAMES {
+            frames.next().await.unwrap().unwrap();
+        }
+        assert!(writer.is_writable());
+        assert!(matches!(receiver.next().await, Some(TransportEvent::Drain)));
+
+        // the session is told only when the queue was full
+        writer.write_frame(b"4hello", OpCode::Text, false).unwrap();
+        frames.next().await.unwrap().unwrap();
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -0,0 +1,173 @@
+//! Helpers shared by the protocol conformance tests: a server configured
+//! like the one of the Socket.IO protocol test suite, and the requests of a
+//! client speaking the protocol by hand.
+#![allow(dead_code)]
+
+use actix_codec::Framed;
+use actix_http::ws::{Codec, Frame, Message};
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use awc::{BoxedSocket, Client};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use socket_io_rust::socket_io::server_options::ServerOptions;
+use socket_io_rust::socket_io::socket_io_server::SocketIoServer;
+use socket_io_rust::socket_io_testing::test_server::relay_events;
+use std::time::Duration;
+
+pub const PING_INTERVAL: Duration = Duration::from_millis(300);
+pub const PING_TIMEOUT: Duration = Duration::from_millis(200);
+pub const MAX_PAYLOAD: usize = 1_000_000;
+pub const CONNECT_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A websocket opened to the server
+pub type Websocket = Framed<BoxedSocket, Codec>;
+
+/// Starts a server on a local port, with the root namespace and `/custom`,
+/// both relaying the events of their clients
+pub fn start() -> test::TestServer {
+    let options = ServerOptions::builder()
+        .ping_interval(PING_INTERVAL)
+        .ping_timeout(PING_TIMEOUT)
+        .max_http_buffer_size(MAX_PAYLOAD)
+        .connect_timeout(CONNECT_TIMEOUT)
+        .build();
+    let app_data = SocketIoServer::get_app_data(options);
+    {
+        let mut namespace_manager = app_data.namespace_manager.write().unwrap();
+        namespace_manager.create_namespace("/custom").unwrap();
+        for namespace in namespace_manager.get_adaptors_mut() {
+            relay_events(namespace);
+        }
+    }
+    test::start(move || App::new().service(SocketIoServer::get_scope(app_data.clone())))
+}
+
+/// Sends a GET request to the engine endpoint, with the given query
+pub async fn get(srv: &test::TestServer, query: &str) -> (StatusCode, String) {
+    let mut res = srv
+        .get(format!("/socket.io/?{}", query))
+        .send()
+        .await
+        .unwrap();
+    let body = res.body().limit(2 * MAX_PAYLOAD).await.unwrap();
+    (res.status(), String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// Sends a POST request to the engine endpoint, with the given query
+pub async fn post(srv: &test::TestServer, query: &str, body: String) -> (StatusCode, String) {
+    let mut res = srv
+        .post(format!("/socket.io/?{}", query))
+        .send_body(body)
+        .await
+        .unwrap();
+    let body = res.body().await.unwrap();
+    (res.status(), String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// Opens a polling session. Returns the data of the open packet.
+pub async fn handshake(srv: &test::TestServer) -> Value {
+    let (status, body) = get(srv, "EIO=4&transport=polling").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.starts_with('0'), "expected the open packet: {}", body);
+    serde_json::from_str(&body[1..]).unwrap()
+}
+
+/// Get the session id of an open packet
+pub fn sid(open: &Value) -> String {
+    open["sid"].as_str().unwrap().to_string()
+}
+
+/// Polls the packets of a session
+pub async fn poll(srv: &test::TestServer, sid: &str) -> (StatusCode, String) {
+    get(srv, &format!("EIO=4&transport=polling&sid={}", sid)).await
+}
+
+/// Sends a payload to a session
+pub async fn send(srv: &test::TestServer, sid: &str, payload: &str) -> (StatusCode, String) {
+    let query = format!("EIO=4&transport=polling&sid={}", sid);
+    post(srv, &query, payload.to_string()).await
+}
+
+/// Opens a polling session and joins the root namespace, without which the
+/// server closes the session. Returns the session id.
+pub async fn polling_connect(srv: &test::TestServer) -> String {
+    let sid = sid(&handshake(srv).await);
+    assert_eq!(send(srv, &sid, "40").await.0, StatusCode::OK);
+    let (status, body) = poll(srv, &sid).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.starts_with("40{\"sid\":"), "{}", body);
+    sid
+}
+
+/// Opens a websocket, to start a session or to upgrade the given one
+pub async fn ws_connect(srv: &test::TestServer, sid: Option<&str>) -> Websocket {
+    let mut path = String::from("/socket.io/?EIO=4&transport=websocket");
+    if let Some(sid) = sid {
+        path.push_str("&sid=");
+        path.push_str(sid);
+    }
+    let (_, ws) = Client::new().ws(srv.url(&path)).connect().await.unwrap();
+    ws
+}
+
+/// Opens a websocket session. Returns the data of the open packet.
+pub async fn ws_handshake(srv: &test::TestServer) -> (Websocket, Value) {
+    let mut ws = ws_connect(srv, None).await;
+    let open = ws_recv(&mut ws).await.unwrap();
+    assert!(open.starts_with('0'), "expected the open packet: {}", open);
+    let open = serde_json::from_str(&open[1..]).unwrap();
+    (ws, open)
+}
+
+pub async fn ws_send(ws: &mut Websocket, text: &str) {
+    ws.send(Message::Text(text.to_string())).await.unwrap();
+}
+
+/// Waits for the next frame of a websocket, `None` once it is closed
+pub async fn ws_frame(ws: &mut Websocket) -> Option<Frame> {
+    match ws.next().await {
+        Some(Ok(Frame::Close(_))) | Some(Err(_)) | None => None,
+        Some(Ok(frame)) => Some(frame),
+    }
+}
+
+/// Waits for the next text message of a websocket, `None` once it is closed
+pub async fn ws_recv(ws: &mut Websocket) -> Option<String> {
+    match ws_frame(ws).await? {
+        Frame::Text(text) => Some(String::from_utf8(text.to_vec()).unwrap()),
+        frame => panic!("expected a text frame: {:?}", frame),
+    }
+}
+
+/// Waits for the next text message of a websocket that is not a ping,
+/// answering the pings on the way
+pub async fn ws_recv_message(ws: &mut Websocket) -> Option<String> {
+    loop {
+        let text = ws_recv(ws).await?;
+        if text != "2" {
+            return Some(text);
+        }
+        ws_send(ws, "3").await;
+    }
+}
+
+/// Opens a websocket session and joins a namespace. Returns the socket id.
+pub async fn ws_connect_namespace(srv: &test::TestServer, nsp: &str) -> (Websocket, String) {
+    let (mut ws, _) = ws_handshake(srv).await;
+    let connect = if nsp == "/" {
+        String::from("40")
+    } else {
+        format!("40{},", nsp)
+    };
+    ws_send(&mut ws, &connect).await;
+    let answer = ws_recv_message(&mut ws).await.unwrap();
+    assert!(
+        answer.starts_with(&connect),
+        "expected {}: {}",
+        connect,
+        answer
+    );
+    let data: Value = serde_json::from_str(&answer[connect.len()..]).unwrap();
+    (ws, data["sid"].as_str().unwrap().to_string())
+}
@@ -0,0 +1,94 @@
+//! Properties of the Engine.IO and Socket.IO decoders: any input decodes to
+//! a packet or an error without panicking, and encoded packets decode back
+//! to themselves.
+
+use proptest::prelude::*;
+use serde_json::Value;
+use socket_io_rust::engine_io_parser::engine_io_parser_impl::EngineIOParserImpl;
+use socket_io_rust::engine_io_parser::packet::{Packet, PacketType};
+use socket_io_rust::engine_io_parser::EngineIoParser;
+use socket_io_rust::socket_io_packet_codec::default_packet_codec::DefaultPacketCodec;
+use socket_io_rust::socket_io_packet_codec::packet_type::SocketIoPacketType;
+use socket_io_rust::socket_io_packet_codec::socket_io_packet::SocketIoPacket;
+use socket_io_rust::socket_io_packet_codec::SocketIoPacketCodec;
+use std::convert::TryFrom;
+
+/// Text shaped like a Socket.IO packet, to get past the first checks of the
+/// decoder more often than random text does
+const SOCKET_IO_LIKE: &str =
+    "[0-6]([0-9]{0,3}-)?(/[^,]{0,8},?)?[0-9]{0,12}[\\[{\"0-9a-zé,:}\\]]{0,16}";
+
+fn engine_io_packet() -> impl Strategy<Value = Packet> {
+    let text = (0..7u8, "[^\u{1e}]*").prop_map(|(packet_type, data)| {
+        Packet::new(
+            PacketType::try_from(packet_type).unwrap(),
+            data.into_bytes(),
+        )
+    });
+    let binary = any::<Vec<u8>>().prop_map(Packet::binary);
+    prop_oneof![text, binary]
+}
+
+fn socket_io_packet() -> impl Strategy<Value = SocketIoPacket> {
+    let packet_type = (0..7u8).prop_map(|n| SocketIoPacketType::try_from(n).unwrap());
+    let nsp = prop_oneof![Just("/".to_string()), "/[^,]+"];
+    let data = proptest::option::of(
+        any::<Vec<String>>()
+            .prop_map(|args| Value::Array(args.into_iter().map(Value::String).collect())),
+    );
+    (packet_type, nsp, proptest::option::of(any::<u32>()), data).prop_map(
+        |(packet_type, nsp, id, data)| SocketIoPacket {
+            packet_type,
+            nsp,
+            id,
+            data,
+        },
+    )
+}
+
+proptest! {
+    #[test]
+    fn engine_io_decoders_do_not_panic(data in any::<String>(), bytes in any::<Vec<u8>>()) {
+        let _ = EngineIOParserImpl::decode_packet(&bytes);
+        let _ = EngineIOParserImpl::decode_packet_str(&data);
+        let _ = EngineIOParserImpl::decode_payload(&data);
+        let _ = EngineIOParserImpl::decode_payload_v3(&data, 16);
+    }
+
+    #[test]
+    fn engine_io_v3_payload_decoder_does_not_panic(data in "([0-9]{1,3}:(b?[0-6][^:]{0,6})?)*") {
+        let _ = EngineIOParserImpl::decode_payload_v3(&data, 16);
+    }
+
+    #[test]
+    fn engine_io_payload_roundtrip(packets in proptest::collection::vec(engine_io_packet(), 1..8)) {
+        let expected = format!("{:?}", packets);
+        let encoded = EngineIOParserImpl::encode_payload(packets).unwrap();
+        let decoded = EngineIOParserImpl::decode_payload(&encoded).unwrap();
+        prop_assert_eq!(format!("{:?}", decoded), expected);
+    }
+
+    #[test]
+    fn engine_io_v3_payload_roundtrip(packets in proptest::collection::vec(engine_io_packet(), 1..8)) {
+        let expected = format!("{:?}", packets);
+        let encoded = EngineIOParserImpl::encode_payload_v3(packets).unwrap();
+        let decoded = EngineIOParserImpl::decode_payload_v3(&encoded, usize::MAX).unwrap();
+        prop_assert_eq!(format!("{:?}", decoded), expected);
+    }
+
+    #[test]
+    fn socket_io_decoder_does_not_panic(data in any::<String>()) {
+        let _ = DefaultPacketCodec.decode(&data);
+    }
+
+    #[test]
+    fn socket_io_decoder_does_not_panic_on_packet_like_text(data in SOCKET_IO_LIKE) {
+        let _ = DefaultPacketCodec.decode(&data);
+    }
+
+    #[test]
+    fn socket_io_roundtrip(packet in socket_io_packet()) {
+        let encoded = DefaultPacketCodec.encode_packet_to_string(&packet).unwrap();
+        prop_assert_eq!(DefaultPacketCodec.decode(&encoded).unwrap(), packet);
+    }
+}
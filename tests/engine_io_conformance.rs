@@ -0,0 +1,169 @@
+//! The Engine.IO protocol, over HTTP long-polling and WebSocket on a local
+//! port, as a client following https://github.com/socketio/engine.io-protocol
+//! sees it.
+
+mod common;
+
+use actix_http::ws::{Frame, Message};
+use actix_rt::time::delay_for;
+use actix_web::http::StatusCode;
+use common::*;
+use futures::SinkExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn error(body: &str) -> Value {
+    serde_json::from_str(body).unwrap()
+}
+
+#[actix_rt::test]
+async fn handshake_test() {
+    let srv = start();
+
+    // polling
+    let open = handshake(&srv).await;
+    assert!(!sid(&open).is_empty());
+    assert_eq!(open["upgrades"], json!(["websocket"]));
+    assert_eq!(
+        open["pingInterval"],
+        json!(PING_INTERVAL.as_millis() as u64)
+    );
+    assert_eq!(open["pingTimeout"], json!(PING_TIMEOUT.as_millis() as u64));
+    assert_eq!(open["maxPayload"], json!(MAX_PAYLOAD));
+
+    // websocket, which cannot be upgraded
+    let (_ws, ws_open) = ws_handshake(&srv).await;
+    assert_ne!(sid(&ws_open), sid(&open));
+    assert_eq!(ws_open["upgrades"], json!([]));
+}
+
+#[actix_rt::test]
+async fn handshake_error_test() {
+    let srv = start();
+
+    let (status, body) = get(&srv, "EIO=5&transport=polling").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error(&body)["code"], json!(5));
+
+    let (status, body) = get(&srv, "EIO=4&transport=carrier-pigeon").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error(&body)["code"], json!(0));
+
+    let (status, body) = post(&srv, "EIO=4&transport=polling", String::new()).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error(&body)["code"], json!(2));
+
+    let (status, body) = poll(&srv, "unknown").await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(error(&body)["code"], json!(1));
+}
+
+#[actix_rt::test]
+async fn heartbeat_test() {
+    let srv = start();
+    let sid = polling_connect(&srv).await;
+
+    // the server pings every interval, and keeps the session while the
+    // pings are answered
+    for _ in 0..3 {
+        assert_eq!(poll(&srv, &sid).await, (StatusCode::OK, "2".to_string()));
+        assert_eq!(send(&srv, &sid, "3").await.0, StatusCode::OK);
+    }
+    let (mut ws, _) = ws_connect_namespace(&srv, "/").await;
+    for _ in 0..3 {
+        assert_eq!(ws_recv(&mut ws).await.as_deref(), Some("2"));
+        ws_send(&mut ws, "3").await;
+    }
+}
+
+#[actix_rt::test]
+async fn ping_timeout_test() {
+    let srv = start();
+
+    let sid = polling_connect(&srv).await;
+    assert_eq!(poll(&srv, &sid).await.1, "2");
+    delay_for(PING_TIMEOUT * 2).await;
+    assert_eq!(poll(&srv, &sid).await.0, StatusCode::BAD_REQUEST);
+
+    let (mut ws, _) = ws_connect_namespace(&srv, "/").await;
+    assert_eq!(ws_recv(&mut ws).await.as_deref(), Some("2"));
+    assert_eq!(ws_recv(&mut ws).await, None);
+}
+
+#[actix_rt::test]
+async fn close_test() {
+    let srv = start();
+
+    let sid = sid(&handshake(&srv).await);
+    assert_eq!(send(&srv, &sid, "1").await.0, StatusCode::OK);
+    delay_for(Duration::from_millis(50)).await;
+    assert_eq!(poll(&srv, &sid).await.0, StatusCode::BAD_REQUEST);
+
+    let (mut ws, _) = ws_handshake(&srv).await;
+    ws_send(&mut ws, "1").await;
+    assert_eq!(ws_recv(&mut ws).await, None);
+}
+
+#[actix_rt::test]
+async fn upgrade_test() {
+    let srv = start();
+    let sid = sid(&handshake(&srv).await);
+
+    let mut ws = ws_connect(&srv, Some(&sid)).await;
+    // the pending polling request ends with a noop once the websocket is
+    // probed
+    let (pending, probe) = futures::join!(poll(&srv, &sid), async {
+        delay_for(Duration::from_millis(50)).await;
+        ws_send(&mut ws, "2probe").await;
+        ws_recv(&mut ws).await
+    });
+    assert_eq!(probe.as_deref(), Some("3probe"));
+    assert_eq!(pending, (StatusCode::OK, "6".to_string()));
+
+    ws_send(&mut ws, "5").await;
+    ws_send(&mut ws, "40").await;
+    let connect = ws_recv_message(&mut ws).await.unwrap();
+    assert!(connect.starts_with("40{\"sid\":"), "{}", connect);
+
+    // polling is no longer allowed
+    assert_eq!(poll(&srv, &sid).await.0, StatusCode::BAD_REQUEST);
+}
+
+#[actix_rt::test]
+async fn payload_batching_test() {
+    let srv = start();
+    let sid = polling_connect(&srv).await;
+
+    // several packets in one request, and the packets sent meanwhile in one
+    // response
+    let payload = "42[\"message\",\"one\"]\x1e42[\"message\",\"two\"]";
+    assert_eq!(send(&srv, &sid, payload).await.0, StatusCode::OK);
+    delay_for(Duration::from_millis(50)).await;
+    assert_eq!(
+        poll(&srv, &sid).await,
+        (StatusCode::OK, payload.to_string())
+    );
+}
+
+#[actix_rt::test]
+async fn max_payload_test() {
+    let srv = start();
+
+    let sid = sid(&handshake(&srv).await);
+    let body = format!("4{}", "a".repeat(MAX_PAYLOAD));
+    assert_eq!(
+        send(&srv, &sid, &body).await.0,
+        StatusCode::PAYLOAD_TOO_LARGE
+    );
+    assert_eq!(poll(&srv, &sid).await.0, StatusCode::BAD_REQUEST);
+
+    let (mut ws, _) = ws_handshake(&srv).await;
+    ws.send(Message::Text(body)).await.unwrap();
+    loop {
+        match ws_frame(&mut ws).await {
+            Some(Frame::Text(_)) => {}
+            Some(frame) => panic!("unexpected frame: {:?}", frame),
+            None => break,
+        }
+    }
+}
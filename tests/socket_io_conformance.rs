@@ -0,0 +1,153 @@
+//! The Socket.IO protocol, over HTTP long-polling and WebSocket on a local
+//! port, as a client following https://github.com/socketio/socket.io-protocol
+//! sees it.
+
+mod common;
+
+use actix_http::ws::{Frame, Message};
+use actix_rt::time::delay_for;
+use actix_web::http::StatusCode;
+use common::*;
+use futures::SinkExt;
+use serde_json::{json, Value};
+use std::time::Duration;
+
+fn data(packet: &str, prefix: &str) -> Value {
+    assert!(
+        packet.starts_with(prefix),
+        "expected {}: {}",
+        prefix,
+        packet
+    );
+    serde_json::from_str(&packet[prefix.len()..]).unwrap()
+}
+
+#[actix_rt::test]
+async fn connect_test() {
+    let srv = start();
+
+    // polling
+    let sid = sid(&handshake(&srv).await);
+    assert_eq!(send(&srv, &sid, "40").await.0, StatusCode::OK);
+    let (_, body) = poll(&srv, &sid).await;
+    assert!(data(&body, "40")["sid"].is_string());
+
+    // websocket, with auth
+    let (mut ws, _) = ws_handshake(&srv).await;
+    ws_send(&mut ws, r#"40{"token":"123"}"#).await;
+    let packet = ws_recv_message(&mut ws).await.unwrap();
+    let socket_id = data(&packet, "40")["sid"].as_str().unwrap().to_string();
+    assert_ne!(socket_id, sid);
+}
+
+#[actix_rt::test]
+async fn custom_namespace_test() {
+    let srv = start();
+    let (mut ws, root_sid) = ws_connect_namespace(&srv, "/").await;
+
+    ws_send(&mut ws, "40/custom,").await;
+    let packet = ws_recv_message(&mut ws).await.unwrap();
+    let sid = data(&packet, "40/custom,")["sid"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert_ne!(sid, root_sid);
+}
+
+#[actix_rt::test]
+async fn invalid_namespace_test() {
+    let srv = start();
+    let (mut ws, _) = ws_handshake(&srv).await;
+
+    ws_send(&mut ws, "40/random,").await;
+    let packet = ws_recv_message(&mut ws).await.unwrap();
+    assert_eq!(
+        data(&packet, "44/random,"),
+        json!({ "message": "Invalid namespace" })
+    );
+}
+
+#[actix_rt::test]
+async fn invalid_packet_test() {
+    let srv = start();
+
+    let sid = polling_connect(&srv).await;
+    assert_eq!(send(&srv, &sid, "4abc").await.0, StatusCode::OK);
+    delay_for(Duration::from_millis(50)).await;
+    assert_eq!(poll(&srv, &sid).await.0, StatusCode::BAD_REQUEST);
+
+    let (mut ws, _) = ws_connect_namespace(&srv, "/").await;
+    ws_send(&mut ws, "4abc").await;
+    assert_eq!(ws_recv_message(&mut ws).await, None);
+}
+
+#[actix_rt::test]
+async fn connect_timeout_test() {
+    let srv = start();
+    let (mut ws, _) = ws_handshake(&srv).await;
+
+    // a session which joins no namespace is closed
+    delay_for(CONNECT_TIMEOUT).await;
+    assert_eq!(ws_recv_message(&mut ws).await, None);
+}
+
+#[actix_rt::test]
+async fn disconnect_test() {
+    let srv = start();
+    let (mut ws, sid) = ws_connect_namespace(&srv, "/").await;
+
+    ws_send(&mut ws, "41").await;
+    ws_send(&mut ws, "40").await;
+    let packet = ws_recv_message(&mut ws).await.unwrap();
+    assert_ne!(data(&packet, "40")["sid"], json!(sid));
+}
+
+#[actix_rt::test]
+async fn event_test() {
+    let srv = start();
+    let (mut sender, _) = ws_connect_namespace(&srv, "/").await;
+    let (mut receiver, _) = ws_connect_namespace(&srv, "/").await;
+    let (mut custom, _) = ws_connect_namespace(&srv, "/custom").await;
+
+    // events are sent to every socket of the namespace
+    ws_send(&mut sender, r#"42["message","hello"]"#).await;
+    for ws in [&mut sender, &mut receiver].iter_mut() {
+        let packet = ws_recv_message(ws).await.unwrap();
+        assert_eq!(data(&packet, "42"), json!(["message", "hello"]));
+    }
+
+    ws_send(&mut custom, r#"42/custom,["message","hi"]"#).await;
+    let packet = ws_recv_message(&mut custom).await.unwrap();
+    assert_eq!(data(&packet, "42/custom,"), json!(["message", "hi"]));
+}
+
+#[actix_rt::test]
+async fn ack_test() {
+    let srv = start();
+    let (mut ws, _) = ws_connect_namespace(&srv, "/").await;
+
+    ws_send(&mut ws, r#"421["message-with-ack","hello"]"#).await;
+    let packet = ws_recv_message(&mut ws).await.unwrap();
+    assert_eq!(data(&packet, "431"), json!(["hello"]));
+}
+
+#[actix_rt::test]
+async fn binary_event_test() {
+    let srv = start();
+    let (mut ws, _) = ws_connect_namespace(&srv, "/").await;
+
+    // the attachments follow the packet, as binary frames
+    ws_send(&mut ws, r#"451-["message",{"_placeholder":true,"num":0}]"#).await;
+    ws.send(Message::Binary(vec![1, 2, 3].into()))
+        .await
+        .unwrap();
+    let packet = ws_recv_message(&mut ws).await.unwrap();
+    assert_eq!(
+        data(&packet, "451-"),
+        json!(["message", { "_placeholder": true, "num": 0 }])
+    );
+    match ws_frame(&mut ws).await {
+        Some(Frame::Binary(attachment)) => assert_eq!(&attachment[..], [1, 2, 3]),
+        frame => panic!("expected the attachment: {:?}", frame),
+    }
+}